@@ -0,0 +1,69 @@
+// Off-chain service that plays the house's games.
+//
+// Players start house games with `challenge_house`, the service signs with the house key set in
+// the global state and plays every game where it is the house's turn.
+use std::time::Duration;
+
+use rand::{rngs::StdRng, SeedableRng};
+use solana_sdk::{
+    pubkey::Pubkey,
+    signature::{Keypair, Signer},
+};
+use tic_tac_toe_client::{Result, TicTacToeClient};
+
+use crate::{player::next_move, Difficulty};
+
+pub struct HouseService<'a> {
+    client: &'a TicTacToeClient,
+    house: Keypair,
+    difficulty: Difficulty,
+    rng: StdRng,
+}
+
+impl<'a> HouseService<'a> {
+    pub fn new(client: &'a TicTacToeClient, house: Keypair, difficulty: Difficulty) -> Self {
+        HouseService {
+            client,
+            house,
+            difficulty,
+            rng: StdRng::from_entropy(),
+        }
+    }
+
+    // Plays one move in every house game waiting for the house, and returns the moves played.
+    // A game that cannot be read or played, e.g. one the player just resigned, is reported and
+    // skipped so it does not hold up the others; it is retried on the next poll.
+    pub async fn play_pending(&mut self) -> Result<usize> {
+        let mut moves = 0;
+        for address in self.client.house_games(&self.house.pubkey()).await? {
+            match self.play_game(&address).await {
+                Ok(played) => moves += usize::from(played),
+                Err(error) => eprintln!("House move in game {} failed: {}", address, error),
+            }
+        }
+        Ok(moves)
+    }
+
+    // Plays the house's move in a game if it is its turn, and returns whether it played.
+    async fn play_game(&mut self, address: &Pubkey) -> Result<bool> {
+        let game = self.client.game(address).await?;
+        match next_move(&game, &self.house.pubkey(), self.difficulty, &mut self.rng) {
+            Some(position) => {
+                self.client.play(&self.house, address, position.row, position.column).await?;
+                Ok(true)
+            }
+            None => Ok(false),
+        }
+    }
+
+    // Plays the house's games forever, polling the global state every `poll_interval`. Only
+    // failing to read the house's games is reported here, failed moves are by `play_pending`.
+    pub async fn run(&mut self, poll_interval: Duration) {
+        loop {
+            if let Err(error) = self.play_pending().await {
+                eprintln!("Reading the house games failed: {}", error);
+            }
+            tokio::time::sleep(poll_interval).await;
+        }
+    }
+}
@@ -0,0 +1,131 @@
+// Tic-tac-toe AI opponent.
+//
+// - `best_moves`: every optimal move, found with a negamax search with alpha-beta pruning.
+// - `choose_move`: a move for the player to move at a given `Difficulty`.
+// - `player`: a bot that joins matchmaking and plays its games through `play`.
+// - `house`: the service that plays the house's games with the house key.
+use std::fmt;
+use std::str::FromStr;
+
+use rand::{seq::SliceRandom, Rng};
+use tic_tac_toe_client::engine::{legal_moves, next_mark, outcome, Board, Mark, Outcome, Position};
+
+pub mod house;
+pub mod player;
+
+// How well the bot plays.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Difficulty {
+    Random,                     // Any legal move.
+    Greedy,                     // Wins or blocks a line when it can, otherwise any legal move.
+    Perfect,                    // An optimal move, it never loses.
+}
+
+impl FromStr for Difficulty {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value {
+            "random" => Ok(Difficulty::Random),
+            "greedy" => Ok(Difficulty::Greedy),
+            "perfect" => Ok(Difficulty::Perfect),
+            _ => Err(format!("`{}` is not a difficulty (random, greedy or perfect)", value)),
+        }
+    }
+}
+
+impl fmt::Display for Difficulty {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match self {
+            Difficulty::Random => "random",
+            Difficulty::Greedy => "greedy",
+            Difficulty::Perfect => "perfect",
+        };
+        f.write_str(name)
+    }
+}
+
+fn set(board: &mut Board, position: Position, mark: Option<Mark>) {
+    board[position.row as usize][position.column as usize] = mark;
+}
+
+fn empty_cells(board: &Board) -> i8 {
+    board.iter().flatten().filter(|cell| cell.is_none()).count() as i8
+}
+
+// Value of the board for the player to move with perfect play from both sides: positive if it
+// wins, negative if it loses and 0 for a tie. Faster wins and slower losses are worth more.
+pub fn score(board: &Board) -> i8 {
+    let mut board = *board;
+    negamax(&mut board, -i8::MAX, i8::MAX)
+}
+
+fn negamax(board: &mut Board, mut alpha: i8, beta: i8) -> i8 {
+    match outcome(board) {
+        // The last move won, so the player to move lost
+        Outcome::Won(_) => return -(1 + empty_cells(board)),
+        Outcome::Tie => return 0,
+        Outcome::InProgress => {}
+    }
+    let mark = next_mark(board);
+    let mut best = -i8::MAX;
+    for position in legal_moves(board) {
+        set(board, position, Some(mark));
+        let value = -negamax(board, -beta, -alpha);
+        set(board, position, None);
+        best = best.max(value);
+        alpha = alpha.max(value);
+        if alpha >= beta {
+            break;
+        }
+    }
+    best
+}
+
+// Every move that keeps the best value for the player to move, in row order.
+pub fn best_moves(board: &Board) -> Vec<Position> {
+    let mut board = *board;
+    let mark = next_mark(&board);
+    let scored: Vec<(Position, i8)> = legal_moves(&board)
+        .into_iter()
+        .map(|position| {
+            set(&mut board, position, Some(mark));
+            let value = -negamax(&mut board, -i8::MAX, i8::MAX);
+            set(&mut board, position, None);
+            (position, value)
+        })
+        .collect();
+    let best = scored.iter().map(|&(_, value)| value).max();
+    scored
+        .into_iter()
+        .filter(|&(_, value)| Some(value) == best)
+        .map(|(position, _)| position)
+        .collect()
+}
+
+// First move that completes a line for `mark`, if any.
+fn winning_move(board: &Board, mark: Mark) -> Option<Position> {
+    let mut board = *board;
+    legal_moves(&board).into_iter().find(|&position| {
+        set(&mut board, position, Some(mark));
+        let wins = outcome(&board) == Outcome::Won(mark);
+        set(&mut board, position, None);
+        wins
+    })
+}
+
+// Move for the player to move, or none once the game is over.
+pub fn choose_move(board: &Board, difficulty: Difficulty, rng: &mut impl Rng) -> Option<Position> {
+    let mark = next_mark(board);
+    let candidates = match difficulty {
+        Difficulty::Random => legal_moves(board),
+        Difficulty::Greedy => {
+            if let Some(position) = winning_move(board, mark).or_else(|| winning_move(board, mark.opponent())) {
+                return Some(position);
+            }
+            legal_moves(board)
+        }
+        Difficulty::Perfect => best_moves(board),
+    };
+    candidates.choose(rng).copied()
+}
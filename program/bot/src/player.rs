@@ -0,0 +1,71 @@
+// Bot that plays matchmaking games on its own.
+//
+// It polls the game account and sends a `play` whenever it is its turn, so any RPC node works
+// without a websocket.
+use std::time::Duration;
+
+use rand::{rngs::StdRng, Rng, SeedableRng};
+use solana_sdk::{
+    pubkey::Pubkey,
+    signature::{Keypair, Signer},
+};
+use tic_tac_toe_client::{engine::Position, Game, GameState, Result, TicTacToeClient};
+
+use crate::{choose_move, Difficulty};
+
+// Move the bot plays in `game`, or none when it is not the bot's turn.
+pub fn next_move(game: &Game, bot: &Pubkey, difficulty: Difficulty, rng: &mut impl Rng) -> Option<Position> {
+    if game.state != GameState::InProgress || game.players[(game.turn % 2) as usize] != Some(*bot) {
+        return None;
+    }
+    choose_move(&game.engine_board(), difficulty, rng)
+}
+
+pub struct BotPlayer<'a> {
+    client: &'a TicTacToeClient,
+    keypair: Keypair,
+    difficulty: Difficulty,
+    poll_interval: Duration,
+    rng: StdRng,
+}
+
+impl<'a> BotPlayer<'a> {
+    pub fn new(client: &'a TicTacToeClient, keypair: Keypair, difficulty: Difficulty) -> Self {
+        BotPlayer {
+            client,
+            keypair,
+            difficulty,
+            poll_interval: Duration::from_secs(1),
+            rng: StdRng::from_entropy(),
+        }
+    }
+
+    pub fn with_poll_interval(mut self, poll_interval: Duration) -> Self {
+        self.poll_interval = poll_interval;
+        self
+    }
+
+    pub fn pubkey(&self) -> Pubkey {
+        self.keypair.pubkey()
+    }
+
+    // Plays the bot's active game, or joins matchmaking first, and returns the game once it ends.
+    pub async fn play_game(&mut self) -> Result<(Pubkey, Game)> {
+        let address = match self.client.active_game(&self.pubkey()).await? {
+            Some(address) => address,
+            None => self.client.join_matchmaking(&self.keypair, false).await?,
+        };
+        loop {
+            let game = self.client.game(&address).await?;
+            if !matches!(game.state, GameState::Waiting | GameState::InProgress) {
+                return Ok((address, game));
+            }
+            match next_move(&game, &self.pubkey(), self.difficulty, &mut self.rng) {
+                Some(position) => {
+                    self.client.play(&self.keypair, &address, position.row, position.column).await?;
+                }
+                None => tokio::time::sleep(self.poll_interval).await,
+            }
+        }
+    }
+}
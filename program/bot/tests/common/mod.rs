@@ -0,0 +1,155 @@
+// Stand-in validator for the bot tests.
+//
+// `BanksSender` answers the RPC requests `TicTacToeClient` makes from an in-process bank, so the
+// bots run unchanged, over a real `RpcClient`, against solana-program-test.
+#![allow(dead_code)]
+
+use anchor_lang::{AccountDeserialize, AccountSerialize};
+use async_trait::async_trait;
+use base64::{engine::general_purpose::STANDARD, Engine};
+use serde_json::{json, Value};
+use solana_account_decoder::{UiAccount, UiAccountEncoding};
+use solana_client::{
+    client_error::{ClientError, ClientErrorKind, Result as ClientResult},
+    nonblocking::rpc_client::RpcClient,
+    rpc_client::RpcClientConfig,
+    rpc_request::RpcRequest,
+    rpc_sender::{RpcSender, RpcTransportStats},
+};
+use solana_program_test::{processor, BanksClient, BanksClientError, ProgramTest, ProgramTestContext};
+use solana_sdk::{
+    account_info::AccountInfo,
+    commitment_config::CommitmentConfig,
+    entrypoint::ProgramResult,
+    native_token::LAMPORTS_PER_SOL,
+    pubkey::Pubkey,
+    signature::{Keypair, Signer},
+    system_instruction,
+    transaction::Transaction,
+};
+use tic_tac_toe_client::{global_state_address, tic_tac_toe, GlobalState, TicTacToeClient};
+
+// Anchor's entrypoint ties the accounts to the instruction lifetime, the builtin processor does not.
+fn process_instruction(program_id: &Pubkey, accounts: &[AccountInfo], data: &[u8]) -> ProgramResult {
+    let accounts = Box::leak(Box::new(accounts.to_vec()));
+    tic_tac_toe::entry(program_id, accounts, data)
+}
+
+pub struct BanksSender {
+    banks_client: BanksClient,
+}
+
+fn banks_error(error: BanksClientError) -> ClientError {
+    match error {
+        BanksClientError::TransactionError(error) | BanksClientError::SimulationError { err: error, .. } => error.into(),
+        other => ClientErrorKind::Custom(other.to_string()).into(),
+    }
+}
+
+fn pubkey(param: &Value) -> Pubkey {
+    param.as_str().unwrap().parse().unwrap()
+}
+
+// Every response is reported at slot 0, the bank has no notion of RPC slots.
+fn response(value: Value) -> Value {
+    json!({ "context": { "slot": 0 }, "value": value })
+}
+
+#[async_trait]
+impl RpcSender for BanksSender {
+    async fn send(&self, request: RpcRequest, params: Value) -> ClientResult<Value> {
+        let mut banks_client = self.banks_client.clone();
+        match request {
+            RpcRequest::GetVersion => Ok(json!({ "solana-core": "1.18.0", "feature-set": 0 })),
+            RpcRequest::GetLatestBlockhash => {
+                let blockhash = banks_client.get_latest_blockhash().await.map_err(banks_error)?;
+                Ok(response(json!({ "blockhash": blockhash.to_string(), "lastValidBlockHeight": u64::MAX })))
+            }
+            RpcRequest::GetAccountInfo => {
+                let address = pubkey(&params[0]);
+                let account = banks_client.get_account(address).await.map_err(banks_error)?;
+                let account = account.map(|account| UiAccount::encode(&address, &account, UiAccountEncoding::Base64, None, None));
+                Ok(response(json!(account)))
+            }
+            RpcRequest::GetBalance => {
+                let balance = banks_client.get_balance(pubkey(&params[0])).await.map_err(banks_error)?;
+                Ok(response(json!(balance)))
+            }
+            RpcRequest::SendTransaction => {
+                let data = STANDARD.decode(params[0].as_str().unwrap()).unwrap();
+                let transaction: Transaction = bincode::deserialize(&data).unwrap();
+                let signature = transaction.signatures[0];
+                banks_client.process_transaction(transaction).await.map_err(banks_error)?;
+                Ok(json!(signature.to_string()))
+            }
+            // Transactions are processed when sent, so any sent signature is finalized
+            RpcRequest::GetSignatureStatuses => {
+                let statuses: Vec<Value> = params[0]
+                    .as_array()
+                    .unwrap()
+                    .iter()
+                    .map(|_| json!({ "slot": 0, "confirmations": null, "err": null, "status": { "Ok": null }, "confirmationStatus": "finalized" }))
+                    .collect();
+                Ok(response(json!(statuses)))
+            }
+            other => panic!("the stand-in validator does not support {}", other),
+        }
+    }
+
+    fn get_transport_stats(&self) -> RpcTransportStats {
+        RpcTransportStats::default()
+    }
+
+    fn url(&self) -> String {
+        "stand-in validator".to_string()
+    }
+}
+
+pub struct StandIn {
+    pub context: ProgramTestContext,
+    pub client: TicTacToeClient,
+}
+
+// Starts a bank with the global state initialized and returns a client connected to it.
+pub async fn start() -> StandIn {
+    let program_test = ProgramTest::new("tic_tac_toe", tic_tac_toe::ID, processor!(process_instruction));
+    let context = program_test.start_with_context().await;
+    let sender = BanksSender {
+        banks_client: context.banks_client.clone(),
+    };
+    let rpc = RpcClient::new_sender(sender, RpcClientConfig::with_commitment(CommitmentConfig::confirmed()));
+    let stand_in = StandIn {
+        client: TicTacToeClient::new(rpc),
+        context,
+    };
+    stand_in.client.initialize_global_state(&stand_in.context.payer).await.unwrap();
+    stand_in
+}
+
+impl StandIn {
+    pub async fn create_funded_wallet(&self) -> Keypair {
+        let wallet = Keypair::new();
+        let transfer = system_instruction::transfer(&self.context.payer.pubkey(), &wallet.pubkey(), 10 * LAMPORTS_PER_SOL);
+        self.client.send(&[transfer], &self.context.payer).await.unwrap();
+        wallet
+    }
+
+    // Sets the house key directly in the global state, as the owner wallet cannot sign here, and
+    // funds the treasury.
+    pub async fn setup_house(&mut self, house: Pubkey, treasury: u64) {
+        self.update_global_state(|global_state| global_state.house = Some(house)).await;
+
+        let transfer = system_instruction::transfer(&self.context.payer.pubkey(), &global_state_address(), treasury);
+        self.client.send(&[transfer], &self.context.payer).await.unwrap();
+    }
+
+    // Rewrites the global state account in place.
+    pub async fn update_global_state(&mut self, update: impl FnOnce(&mut GlobalState)) {
+        let address = global_state_address();
+        let mut account = self.context.banks_client.get_account(address).await.unwrap().unwrap();
+        let mut global_state = GlobalState::try_deserialize(&mut account.data.as_slice()).unwrap();
+        update(&mut global_state);
+        global_state.try_serialize(&mut account.data.as_mut_slice()).unwrap();
+        self.context.set_account(&address, &account.into());
+    }
+}
@@ -0,0 +1,92 @@
+mod common;
+
+use rand::{rngs::StdRng, SeedableRng};
+use solana_sdk::{
+    native_token::LAMPORTS_PER_SOL,
+    pubkey::Pubkey,
+    signature::{Keypair, Signer},
+};
+use tic_tac_toe_bot::{house::HouseService, player::next_move, Difficulty};
+use tic_tac_toe_client::{global_state_address, GameState};
+
+#[tokio::test]
+async fn house_service_plays_its_games() {
+    let mut stand_in = common::start().await;
+    let house = stand_in.create_funded_wallet().await;
+    stand_in.setup_house(house.pubkey(), LAMPORTS_PER_SOL).await;
+    let client = &stand_in.client;
+    let mut service = HouseService::new(client, house.insecure_clone(), Difficulty::Perfect);
+    let mut rng = StdRng::seed_from_u64(0);
+
+    let global_state = client.global_state().await.unwrap();
+    let pot = 2 * global_state.bet;
+    let fee = pot * global_state.fee / 100;
+
+    for _ in 0..3 {
+        let player = stand_in.create_funded_wallet().await;
+        let treasury_before = client.rpc().get_balance(&global_state_address()).await.unwrap();
+        let game = client.challenge_house(&player, false).await.unwrap();
+        assert_eq!(client.house_games(&house.pubkey()).await.unwrap(), vec![game]);
+
+        loop {
+            let state = client.game(&game).await.unwrap();
+            if !state.is_in_progress() {
+                break;
+            }
+            // The player moves first, and the house answers on its next poll unless the game ended
+            let position = next_move(&state, &player.pubkey(), Difficulty::Random, &mut rng).unwrap();
+            client.play(&player, &game, position.row, position.column).await.unwrap();
+            let in_progress = client.game(&game).await.unwrap().is_in_progress();
+            assert_eq!(service.play_pending().await.unwrap(), usize::from(in_progress));
+        }
+
+        // The perfect house never loses, and its share of the pot goes back to the treasury
+        let treasury = client.rpc().get_balance(&global_state_address()).await.unwrap();
+        match client.game(&game).await.unwrap().state {
+            GameState::Won { winner } => {
+                assert_eq!(winner, house.pubkey());
+                assert_eq!(treasury, treasury_before - global_state.bet + pot);
+            }
+            GameState::Tie => assert_eq!(treasury, treasury_before - global_state.bet + fee + (pot - fee) / 2),
+            other => panic!("unexpected state {:?}", other),
+        }
+        assert!(client.house_games(&house.pubkey()).await.unwrap().is_empty());
+    }
+}
+
+#[tokio::test]
+async fn house_service_waits_for_the_player() {
+    let mut stand_in = common::start().await;
+    let house = Keypair::new();
+    stand_in.setup_house(house.pubkey(), LAMPORTS_PER_SOL).await;
+    let player = stand_in.create_funded_wallet().await;
+    let mut service = HouseService::new(&stand_in.client, house, Difficulty::Perfect);
+
+    assert_eq!(service.play_pending().await.unwrap(), 0);
+    stand_in.client.challenge_house(&player, false).await.unwrap();
+    // The player moves first
+    assert_eq!(service.play_pending().await.unwrap(), 0);
+}
+
+#[tokio::test]
+async fn house_service_skips_games_it_cannot_play() {
+    let mut stand_in = common::start().await;
+    let house = stand_in.create_funded_wallet().await;
+    stand_in.setup_house(house.pubkey(), LAMPORTS_PER_SOL).await;
+    let player = stand_in.create_funded_wallet().await;
+    let game = stand_in.client.challenge_house(&player, false).await.unwrap();
+    stand_in.client.play(&player, &game, 1, 1).await.unwrap();
+    // A game of the house listed first that cannot be read
+    let missing_game = Pubkey::new_unique();
+    stand_in
+        .update_global_state(|global_state| {
+            global_state.players_mapping.insert(0, house.pubkey());
+            global_state.games_mapping.insert(0, missing_game);
+        })
+        .await;
+    let mut service = HouseService::new(&stand_in.client, house, Difficulty::Perfect);
+
+    // The readable game is still played, and only its move is counted
+    assert_eq!(service.play_pending().await.unwrap(), 1);
+    assert_eq!(stand_in.client.game(&game).await.unwrap().turn, 2);
+}
@@ -0,0 +1,109 @@
+use rand::{rngs::StdRng, SeedableRng};
+use solana_sdk::pubkey::Pubkey;
+use tic_tac_toe_bot::{best_moves, choose_move, player::next_move, score, Difficulty};
+use tic_tac_toe_client::{
+    engine::{apply_move, next_mark, outcome, Board, Mark, Outcome, Position},
+    Game, GameState, Sign,
+};
+
+fn board(positions: &[(u8, u8)]) -> Board {
+    let mut board = Board::default();
+    for &(row, column) in positions {
+        let mark = next_mark(&board);
+        apply_move(&mut board, mark, Position::new(row, column)).unwrap();
+    }
+    board
+}
+
+// Plays a full game between two difficulties, X first, and returns the outcome.
+fn play(x: Difficulty, o: Difficulty, rng: &mut StdRng) -> Outcome {
+    let mut board = Board::default();
+    while outcome(&board) == Outcome::InProgress {
+        let mark = next_mark(&board);
+        let difficulty = if mark == Mark::X { x } else { o };
+        let position = choose_move(&board, difficulty, rng).unwrap();
+        apply_move(&mut board, mark, position).unwrap();
+    }
+    outcome(&board)
+}
+
+#[test]
+fn perfect_play_ties_from_the_empty_board() {
+    assert_eq!(score(&Board::default()), 0);
+    // Every opening move keeps the tie
+    assert_eq!(best_moves(&Board::default()).len(), 9);
+    let mut rng = StdRng::seed_from_u64(0);
+    for _ in 0..10 {
+        assert_eq!(play(Difficulty::Perfect, Difficulty::Perfect, &mut rng), Outcome::Tie);
+    }
+}
+
+#[test]
+fn perfect_never_loses() {
+    let mut rng = StdRng::seed_from_u64(1);
+    for opponent in [Difficulty::Random, Difficulty::Greedy] {
+        for _ in 0..20 {
+            assert_ne!(play(Difficulty::Perfect, opponent, &mut rng), Outcome::Won(Mark::O));
+            assert_ne!(play(opponent, Difficulty::Perfect, &mut rng), Outcome::Won(Mark::X));
+        }
+    }
+}
+
+#[test]
+fn prefers_the_fastest_win() {
+    // X to move wins now on (0, 2), blocking on (1, 2) only wins later
+    let board = board(&[(0, 0), (1, 0), (0, 1), (1, 1)]);
+
+    assert_eq!(best_moves(&board), vec![Position::new(0, 2)]);
+    assert_eq!(score(&board), 5);
+}
+
+#[test]
+fn greedy_wins_then_blocks() {
+    let mut rng = StdRng::seed_from_u64(2);
+    let winning = board(&[(0, 0), (1, 0), (0, 1), (1, 1)]);
+    assert_eq!(choose_move(&winning, Difficulty::Greedy, &mut rng), Some(Position::new(0, 2)));
+
+    // O cannot win yet and must block X on (0, 2)
+    let blocking = board(&[(0, 0), (1, 1), (0, 1)]);
+    assert_eq!(choose_move(&blocking, Difficulty::Greedy, &mut rng), Some(Position::new(0, 2)));
+}
+
+#[test]
+fn no_move_once_the_game_is_over() {
+    let mut rng = StdRng::seed_from_u64(3);
+    let won = board(&[(0, 0), (1, 0), (0, 1), (1, 1), (0, 2)]);
+
+    assert!(best_moves(&won).is_empty());
+    assert_eq!(choose_move(&won, Difficulty::Random, &mut rng), None);
+}
+
+#[test]
+fn bot_moves_only_on_its_turn() {
+    let mut rng = StdRng::seed_from_u64(4);
+    let bot = Pubkey::new_unique();
+    let mut game = Game {
+        version: Game::VERSION,
+        number: 1,
+        players: [Some(Pubkey::new_unique()), Some(bot)],
+        turn: 1,
+        board: [[None; 3]; 3],
+        state: GameState::InProgress,
+        pot: 0,
+        paid: false,
+        league: None,
+        moves: Vec::new(),
+        house: false,
+        hook: None,
+        hook_called: false,
+        relayer_fees: [0; 2],
+    };
+    game.board[0][0] = Some(Sign::X);
+
+    assert_eq!(next_move(&game, &bot, Difficulty::Perfect, &mut rng), Some(Position::new(1, 1)));
+    game.turn = 2;
+    assert_eq!(next_move(&game, &bot, Difficulty::Perfect, &mut rng), None);
+    game.turn = 1;
+    game.state = GameState::Waiting;
+    assert_eq!(next_move(&game, &bot, Difficulty::Perfect, &mut rng), None);
+}
@@ -0,0 +1,123 @@
+// Parsing and rendering helpers of the `tictactoe` command-line client.
+use std::fmt::Write;
+use std::str::FromStr;
+
+use solana_sdk::{native_token::lamports_to_sol, pubkey::Pubkey};
+use tic_tac_toe_client::{game_address, Game, GameState, GlobalState, Pause, Sign};
+
+pub mod tui;
+
+// Game given on the command line, either by number or by address.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum GameRef {
+    Number(u64),
+    Address(Pubkey),
+}
+
+impl GameRef {
+    pub fn address(&self) -> Pubkey {
+        match self {
+            GameRef::Number(number) => game_address(*number),
+            GameRef::Address(address) => *address,
+        }
+    }
+}
+
+impl FromStr for GameRef {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        if let Ok(number) = value.parse() {
+            return Ok(GameRef::Number(number));
+        }
+        Pubkey::from_str(value)
+            .map(GameRef::Address)
+            .map_err(|_| format!("`{}` is neither a game number nor an address", value))
+    }
+}
+
+fn sign_symbol(sign: Option<Sign>) -> char {
+    match sign {
+        Some(Sign::X) => 'X',
+        Some(Sign::O) => 'O',
+        None => ' ',
+    }
+}
+
+// Draws the board with the row and column coordinates used by `play`.
+pub fn render_board(game: &Game) -> String {
+    let mut board = String::from("    0   1   2\n");
+    for (row, cells) in game.board.iter().enumerate() {
+        if row > 0 {
+            board.push_str("   ---+---+---\n");
+        }
+        let symbols: Vec<String> = cells.iter().map(|&cell| sign_symbol(cell).to_string()).collect();
+        writeln!(board, "{}   {}", row, symbols.join(" | ")).unwrap();
+    }
+    board
+}
+
+fn player(game: &Game, index: usize) -> String {
+    match game.players[index] {
+        Some(player) => player.to_string(),
+        None => "-".to_string(),
+    }
+}
+
+pub fn describe_state(game: &Game) -> String {
+    match &game.state {
+        GameState::Uninitialized => "Not initialized".to_string(),
+        GameState::Waiting => "Waiting for player O".to_string(),
+        GameState::InProgress => {
+            let index = (game.turn % 2) as usize;
+            format!("In progress, {} to move ({})", ['X', 'O'][index], player(game, index))
+        }
+        GameState::Tie => "Tie".to_string(),
+        GameState::Won { winner } => format!("Won by {}", winner),
+        GameState::Canceled => "Canceled".to_string(),
+    }
+}
+
+pub fn render_game(address: &Pubkey, game: &Game) -> String {
+    let mut output = String::new();
+    writeln!(output, "Game #{} ({})", game.number, address).unwrap();
+    writeln!(output, "Player X: {}", player(game, 0)).unwrap();
+    writeln!(output, "Player O: {}{}", player(game, 1), if game.house { " (house)" } else { "" }).unwrap();
+    writeln!(output, "State:    {}", describe_state(game)).unwrap();
+    writeln!(output, "Pot:      {} SOL", lamports_to_sol(game.pot)).unwrap();
+    writeln!(output).unwrap();
+    output.push_str(&render_board(game));
+    output
+}
+
+// Paused groups, e.g. "moves, withdrawals", or "-" when nothing is paused.
+pub fn describe_pause(paused: &Pause) -> String {
+    let groups: Vec<&str> = [(paused.new_games, "new games"), (paused.moves, "moves"), (paused.withdrawals, "withdrawals")]
+        .into_iter()
+        .filter(|(paused, _)| *paused)
+        .map(|(_, group)| group)
+        .collect();
+    if groups.is_empty() {
+        "-".to_string()
+    } else {
+        groups.join(", ")
+    }
+}
+
+pub fn render_global_state(address: &Pubkey, global_state: &GlobalState, lamports: u64) -> String {
+    let mut output = String::new();
+    writeln!(output, "Global state: {}", address).unwrap();
+    writeln!(output, "Owner:        {}", global_state.owner).unwrap();
+    writeln!(output, "Fee:          {}%", global_state.fee).unwrap();
+    writeln!(output, "Bet:          {} SOL", lamports_to_sol(global_state.bet)).unwrap();
+    let house = global_state.house.map_or("-".to_string(), |house| house.to_string());
+    writeln!(output, "House:        {}", house).unwrap();
+    writeln!(output, "Paused:       {}", describe_pause(&global_state.paused)).unwrap();
+    writeln!(output, "Next game:    #{}", global_state.game_count).unwrap();
+    writeln!(output, "Balance:      {} SOL", lamports_to_sol(lamports)).unwrap();
+    writeln!(output, "Active players: {}", global_state.players_mapping.len()).unwrap();
+    for (player, game) in global_state.players_mapping.iter().zip(&global_state.games_mapping) {
+        writeln!(output, "  {} in game {}", player, game).unwrap();
+    }
+    output
+}
@@ -0,0 +1,313 @@
+// `tictactoe`: command-line client to play and administer games.
+//
+// The RPC URL and the keypair come from the Solana CLI config (`solana config get`), and can
+// be overridden with `--url` and `--keypair`.
+use std::error::Error;
+use std::time::Duration;
+
+use clap::{Parser, Subcommand};
+use solana_sdk::{
+    pubkey::Pubkey,
+    signature::{read_keypair_file, Keypair, Signer},
+};
+use tic_tac_toe_bot::{
+    house::HouseService,
+    player::{next_move, BotPlayer},
+    Difficulty,
+};
+use tic_tac_toe_client::{balance_address, global_state_address, Pause, TicTacToeClient};
+use tictactoe_cli::{describe_pause, describe_state, render_game, render_global_state, tui, GameRef};
+
+#[derive(Parser)]
+#[command(name = "tictactoe", version, about = "Play and administer tic-tac-toe games")]
+struct Cli {
+    /// Solana CLI config file [default: ~/.config/solana/cli/config.yml]
+    #[arg(long, global = true)]
+    config: Option<String>,
+    /// RPC URL, overrides the config
+    #[arg(long, short = 'u', global = true)]
+    url: Option<String>,
+    /// Websocket URL, overrides the config [default: derived from the RPC URL]
+    #[arg(long, global = true)]
+    ws: Option<String>,
+    /// Keypair file, overrides the config
+    #[arg(long, short = 'k', global = true)]
+    keypair: Option<String>,
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Initialize the global state
+    Init,
+    /// Upgrade the global state to the current account layout
+    Migrate,
+    /// Refund a game the first program version left running
+    RefundLegacy { game: Pubkey },
+    /// Join the waiting game or open a new one
+    Join {
+        /// Bet from your balance instead of your wallet
+        #[arg(long)]
+        from_balance: bool,
+    },
+    /// Show a game and its board [default: your active game]
+    Show { game: Option<GameRef> },
+    /// Play the tile at ROW and COLUMN (0 to 2) in your active game
+    Play {
+        row: u8,
+        column: u8,
+        #[arg(long)]
+        game: Option<GameRef>,
+    },
+    /// Suggest a move in your active game
+    Hint {
+        #[arg(long)]
+        game: Option<GameRef>,
+        /// random, greedy or perfect
+        #[arg(long, default_value = "perfect")]
+        difficulty: Difficulty,
+    },
+    /// Join matchmaking and play games automatically
+    Bot {
+        /// random, greedy or perfect
+        #[arg(long, default_value = "perfect")]
+        difficulty: Difficulty,
+        /// Number of games to play
+        #[arg(long, default_value_t = 1)]
+        games: u32,
+        /// Milliseconds between polls of the game account
+        #[arg(long, default_value_t = 1000)]
+        poll_ms: u64,
+    },
+    /// Play against the house bot when no player is waiting
+    ChallengeHouse {
+        /// Bet from your balance instead of your wallet
+        #[arg(long)]
+        from_balance: bool,
+    },
+    /// Run the house bot service with the wallet as the house key
+    House {
+        /// random, greedy or perfect
+        #[arg(long, default_value = "perfect")]
+        difficulty: Difficulty,
+        /// Milliseconds between polls of the global state
+        #[arg(long, default_value_t = 1000)]
+        poll_ms: u64,
+    },
+    /// Set the house bot key, or disable the house when omitted
+    SetHouse { house: Option<Pubkey> },
+    /// Allow a hook program that new games can call when they end
+    SetHook {
+        hook: Pubkey,
+        /// Remove the hook from the allowlist instead
+        #[arg(long)]
+        remove: bool,
+    },
+    /// Play a game live in a terminal UI [default: your active game]
+    Tui { game: Option<GameRef> },
+    /// Cancel your waiting game, or resign from your game in progress
+    Cancel { game: Option<GameRef> },
+    /// Close a finished game you opened and reclaim its rent
+    Close { game: GameRef },
+    /// Close any game settled more than a day ago, returning the rent to whoever paid it
+    CloseFinished { game: GameRef },
+    /// Call the hook a settled game skipped when it ended
+    CallHook { game: GameRef },
+    /// Show the lamports available in your balance
+    Balance,
+    /// Deposit LAMPORTS from your wallet into your balance
+    Deposit { lamports: u64 },
+    /// Withdraw LAMPORTS from your balance to your wallet
+    Withdraw { lamports: u64 },
+    /// Show the fee, the bet and the active players
+    GlobalState,
+    /// Withdraw LAMPORTS of fees to the owner wallet
+    WithdrawFees { lamports: u64 },
+    /// Pause new games, moves and withdrawals, resuming every group not passed
+    Pause {
+        #[arg(long)]
+        new_games: bool,
+        #[arg(long)]
+        moves: bool,
+        #[arg(long)]
+        withdrawals: bool,
+    },
+    /// Cancel a waiting or in progress game and refund both bets without fee
+    EmergencyRefund { game: GameRef },
+}
+
+struct Context {
+    client: TicTacToeClient,
+    websocket_url: String,
+    keypair_path: String,
+}
+
+impl Context {
+    fn load(cli: &Cli) -> Self {
+        let config_file = cli.config.clone().or_else(|| solana_cli_config::CONFIG_FILE.clone());
+        let config = config_file
+            .and_then(|file| solana_cli_config::Config::load(&file).ok())
+            .unwrap_or_default();
+        let websocket_url = match (&cli.ws, &cli.url) {
+            (Some(ws), _) => ws.clone(),
+            (None, Some(url)) => solana_cli_config::Config::compute_websocket_url(url),
+            (None, None) => config.websocket_url,
+        };
+        let url = cli.url.clone().unwrap_or(config.json_rpc_url);
+        let keypair_path = cli.keypair.clone().unwrap_or(config.keypair_path);
+        Context {
+            client: TicTacToeClient::with_url(url),
+            websocket_url,
+            keypair_path,
+        }
+    }
+
+    fn keypair(&self) -> Result<Keypair, Box<dyn Error>> {
+        read_keypair_file(&self.keypair_path).map_err(|error| format!("failed to read keypair {}: {}", self.keypair_path, error).into())
+    }
+
+    // The given game, or the active game of the wallet.
+    async fn game(&self, game: Option<GameRef>) -> Result<Pubkey, Box<dyn Error>> {
+        if let Some(game) = game {
+            return Ok(game.address());
+        }
+        let player = self.keypair()?.pubkey();
+        self.client
+            .active_game(&player)
+            .await?
+            .ok_or_else(|| format!("{} has no active game, pass one explicitly", player).into())
+    }
+
+    async fn show(&self, game: &Pubkey) -> Result<(), Box<dyn Error>> {
+        print!("{}", render_game(game, &self.client.game(game).await?));
+        Ok(())
+    }
+}
+
+#[tokio::main(flavor = "current_thread")]
+async fn main() {
+    if let Err(error) = run(Cli::parse()).await {
+        eprintln!("Error: {}", error);
+        std::process::exit(1);
+    }
+}
+
+async fn run(cli: Cli) -> Result<(), Box<dyn Error>> {
+    let context = Context::load(&cli);
+    let client = &context.client;
+
+    match cli.command {
+        Command::Init => {
+            let signature = client.initialize_global_state(&context.keypair()?).await?;
+            println!("Global state {} initialized: {}", global_state_address(), signature);
+        }
+        Command::Migrate => {
+            let signature = client.migrate_global_state(&context.keypair()?).await?;
+            println!("Global state {} migrated: {}", global_state_address(), signature);
+        }
+        Command::RefundLegacy { game } => {
+            let signature = client.refund_legacy_game(&context.keypair()?, &game).await?;
+            println!("Refunded legacy game {}: {}", game, signature);
+        }
+        Command::Join { from_balance } => {
+            let game = client.join_matchmaking(&context.keypair()?, from_balance).await?;
+            context.show(&game).await?;
+        }
+        Command::Show { game } => {
+            context.show(&context.game(game).await?).await?;
+        }
+        Command::Play { row, column, game } => {
+            let player = context.keypair()?;
+            let game = context.game(game).await?;
+            client.play(&player, &game, row, column).await?;
+            context.show(&game).await?;
+        }
+        Command::Hint { game, difficulty } => {
+            let player = context.keypair()?.pubkey();
+            let game = client.game(&context.game(game).await?).await?;
+            match next_move(&game, &player, difficulty, &mut rand::thread_rng()) {
+                Some(position) => println!("Play row {}, column {}", position.row, position.column),
+                None => println!("No move to play: {}", describe_state(&game)),
+            }
+        }
+        Command::Bot { difficulty, games, poll_ms } => {
+            let mut bot = BotPlayer::new(client, context.keypair()?, difficulty).with_poll_interval(Duration::from_millis(poll_ms));
+            for _ in 0..games {
+                let (address, game) = bot.play_game().await?;
+                println!("Game #{} ({}): {}", game.number, address, describe_state(&game));
+            }
+        }
+        Command::ChallengeHouse { from_balance } => {
+            let game = client.challenge_house(&context.keypair()?, from_balance).await?;
+            context.show(&game).await?;
+        }
+        Command::House { difficulty, poll_ms } => {
+            let house = context.keypair()?;
+            println!("Playing the house games of {} at {} difficulty", house.pubkey(), difficulty);
+            HouseService::new(client, house, difficulty).run(Duration::from_millis(poll_ms)).await;
+        }
+        Command::SetHouse { house } => {
+            let signature = client.set_house(&context.keypair()?, house).await?;
+            println!("House set to {}: {}", house.map_or("none".to_string(), |house| house.to_string()), signature);
+        }
+        Command::SetHook { hook, remove } => {
+            let signature = client.set_hook(&context.keypair()?, &hook, !remove).await?;
+            println!("Hook {} {}: {}", hook, if remove { "removed" } else { "allowed" }, signature);
+        }
+        Command::Tui { game } => {
+            let player = context.keypair()?;
+            let game = context.game(game).await?;
+            tui::run(client, context.websocket_url.clone(), &player, game).await?;
+        }
+        Command::Cancel { game } => {
+            let player = context.keypair()?;
+            let game = context.game(game).await?;
+            client.cancel_game(&player, &game).await?;
+            context.show(&game).await?;
+        }
+        Command::Close { game } => {
+            let signature = client.close_game_account(&context.keypair()?, &game.address()).await?;
+            println!("Game {} closed: {}", game.address(), signature);
+        }
+        Command::CloseFinished { game } => {
+            let signature = client.close_finished_game(&context.keypair()?, &game.address()).await?;
+            println!("Game {} closed: {}", game.address(), signature);
+        }
+        Command::CallHook { game } => {
+            let signature = client.call_game_hook(&context.keypair()?, &game.address()).await?;
+            println!("Hook of game {} called: {}", game.address(), signature);
+        }
+        Command::Balance => {
+            let owner = context.keypair()?.pubkey();
+            println!("Balance {}: {} lamports", balance_address(&owner), client.balance(&owner).await?);
+        }
+        Command::Deposit { lamports } => {
+            let signature = client.deposit(&context.keypair()?, lamports).await?;
+            println!("Deposited {} lamports: {}", lamports, signature);
+        }
+        Command::Withdraw { lamports } => {
+            let signature = client.withdraw(&context.keypair()?, lamports).await?;
+            println!("Withdrew {} lamports from the balance: {}", lamports, signature);
+        }
+        Command::GlobalState => {
+            let address = global_state_address();
+            let lamports = client.rpc().get_balance(&address).await?;
+            print!("{}", render_global_state(&address, &client.global_state().await?, lamports));
+        }
+        Command::WithdrawFees { lamports } => {
+            let signature = client.withdraw_fees(&context.keypair()?, lamports).await?;
+            println!("Withdrew {} lamports: {}", lamports, signature);
+        }
+        Command::Pause { new_games, moves, withdrawals } => {
+            let paused = Pause { new_games, moves, withdrawals };
+            let signature = client.set_paused(&context.keypair()?, paused).await?;
+            println!("Paused {}: {}", describe_pause(&paused), signature);
+        }
+        Command::EmergencyRefund { game } => {
+            let signature = client.emergency_refund(&context.keypair()?, &game.address()).await?;
+            println!("Refunded game {}: {}", game.address(), signature);
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,276 @@
+// Terminal UI to play a live game.
+//
+// The game account and the program logs are streamed over the RPC websocket, so the board
+// follows the rival's moves as they land and the settlement is read from `GameFinished`.
+use std::error::Error;
+
+use futures::StreamExt;
+use ratatui::{
+    crossterm::event::{self, Event, KeyCode, KeyEventKind},
+    layout::{Constraint, Layout},
+    style::{Color, Modifier, Style},
+    text::{Line, Span},
+    widgets::{Block, Borders, Paragraph},
+    DefaultTerminal, Frame,
+};
+use solana_account_decoder::UiAccountEncoding;
+use solana_client::{
+    nonblocking::pubsub_client::{PubsubClient, PubsubClientError},
+    rpc_config::{RpcAccountInfoConfig, RpcTransactionLogsConfig, RpcTransactionLogsFilter},
+};
+use solana_sdk::{
+    commitment_config::CommitmentConfig,
+    native_token::lamports_to_sol,
+    pubkey::Pubkey,
+    signature::{Keypair, Signer},
+};
+use tic_tac_toe_client::{decode_events, decode_game, EndReason, Game, GameFinished, GameState, TicTacToeClient};
+use tokio::sync::mpsc::{self, UnboundedSender};
+
+use crate::sign_symbol;
+
+// What a key press asks the event loop to do.
+#[derive(Debug, PartialEq, Eq)]
+pub enum Action {
+    Play(u8, u8),
+    Quit,
+}
+
+// Changes pushed by the websocket subscriptions.
+pub enum Update {
+    Game(Box<Game>),
+    Finished(Box<GameFinished>),
+    Error(String),
+}
+
+pub struct App {
+    pub game_address: Pubkey,
+    pub player: Pubkey,
+    pub game: Game,
+    pub cursor: (u8, u8),       // Selected (row, column)
+    pub status: String,
+    pub finished: Option<GameFinished>,
+}
+
+impl App {
+    pub fn new(game_address: Pubkey, player: Pubkey, game: Game) -> Self {
+        App {
+            game_address,
+            player,
+            game,
+            cursor: (1, 1),
+            status: String::new(),
+            finished: None,
+        }
+    }
+
+    pub fn is_my_turn(&self) -> bool {
+        self.game.state == GameState::InProgress && self.game.players[(self.game.turn % 2) as usize] == Some(self.player)
+    }
+
+    pub fn handle_key(&mut self, key: KeyCode) -> Option<Action> {
+        let (row, column) = &mut self.cursor;
+        match key {
+            KeyCode::Up => *row = row.saturating_sub(1),
+            KeyCode::Down => *row = (*row + 1).min(2),
+            KeyCode::Left => *column = column.saturating_sub(1),
+            KeyCode::Right => *column = (*column + 1).min(2),
+            KeyCode::Char('q') | KeyCode::Esc => return Some(Action::Quit),
+            KeyCode::Enter | KeyCode::Char(' ') => {
+                let (row, column) = self.cursor;
+                if !self.is_my_turn() {
+                    self.status = "Not your turn".to_string();
+                } else if self.game.board[row as usize][column as usize].is_some() {
+                    self.status = "Tile already set".to_string();
+                } else {
+                    self.status = format!("Playing ({}, {})...", row, column);
+                    return Some(Action::Play(row, column));
+                }
+            }
+            _ => {}
+        }
+        None
+    }
+
+    pub fn apply(&mut self, update: Update) {
+        match update {
+            Update::Game(game) => self.game = *game,
+            Update::Finished(finished) => self.finished = Some(*finished),
+            Update::Error(error) => self.status = error,
+        }
+    }
+
+    fn player_line(&self, index: usize) -> Line<'_> {
+        let player = match self.game.players[index] {
+            Some(player) if player == self.player => format!("{} (you)", player),
+            Some(player) => player.to_string(),
+            None => "waiting...".to_string(),
+        };
+        let to_move = self.game.state == GameState::InProgress && (self.game.turn % 2) as usize == index;
+        let style = if to_move {
+            Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD)
+        } else {
+            Style::default()
+        };
+        let marker = if to_move { "> " } else { "  " };
+        Line::styled(format!("{}{}: {}", marker, ['X', 'O'][index], player), style)
+    }
+
+    fn board_lines(&self) -> Vec<Line<'_>> {
+        let mut lines = Vec::new();
+        for (row, cells) in self.game.board.iter().enumerate() {
+            if row > 0 {
+                lines.push(Line::raw("---+---+---"));
+            }
+            let mut spans = Vec::new();
+            for (column, &cell) in cells.iter().enumerate() {
+                if column > 0 {
+                    spans.push(Span::raw("|"));
+                }
+                let style = if self.cursor == (row as u8, column as u8) {
+                    Style::default().add_modifier(Modifier::REVERSED)
+                } else {
+                    Style::default()
+                };
+                spans.push(Span::styled(format!(" {} ", sign_symbol(cell)), style));
+            }
+            lines.push(Line::from(spans));
+        }
+        lines
+    }
+
+    fn footer_lines(&self) -> Vec<Line<'_>> {
+        let Some(finished) = &self.finished else {
+            let turn = if self.is_my_turn() { "Your turn" } else { "Waiting for the rival" };
+            return vec![
+                Line::raw(if self.game.state == GameState::InProgress { turn } else { "" }),
+                Line::raw(self.status.as_str()),
+                Line::raw("arrows: select  enter: play  q: quit"),
+            ];
+        };
+        let result = match (finished.reason, finished.winner) {
+            (EndReason::Tie, _) => "Tie".to_string(),
+            (EndReason::Canceled, _) => "Canceled".to_string(),
+            (_, Some(winner)) if winner == self.player => "You won".to_string(),
+            (EndReason::Resignation, Some(winner)) => format!("Resigned, won by {}", winner),
+            (_, Some(winner)) => format!("Won by {}", winner),
+            (_, None) => format!("{:?}", finished.reason),
+        };
+        vec![
+            Line::styled(result, Style::default().add_modifier(Modifier::BOLD)),
+            Line::raw(format!(
+                "Pot: {} SOL, fee: {} SOL",
+                lamports_to_sol(finished.pot),
+                lamports_to_sol(finished.fee)
+            )),
+            Line::raw(format!(
+                "Payouts: X {} SOL, O {} SOL",
+                lamports_to_sol(finished.player_one_payout),
+                lamports_to_sol(finished.player_two_payout)
+            )),
+            Line::raw("q: quit"),
+        ]
+    }
+}
+
+pub fn draw(frame: &mut Frame, app: &App) {
+    let [players, board, footer] =
+        Layout::vertical([Constraint::Length(4), Constraint::Length(7), Constraint::Min(6)]).areas(frame.area());
+    let title = format!(" Game #{} ", app.game.number);
+    frame.render_widget(
+        Paragraph::new(vec![app.player_line(0), app.player_line(1)]).block(Block::default().borders(Borders::ALL).title(title)),
+        players,
+    );
+    frame.render_widget(Paragraph::new(app.board_lines()).block(Block::default().borders(Borders::ALL)), board);
+    frame.render_widget(Paragraph::new(app.footer_lines()).block(Block::default().borders(Borders::ALL)), footer);
+}
+
+// Plays `game` as `player` until the user quits.
+pub async fn run(client: &TicTacToeClient, websocket_url: String, player: &Keypair, game: Pubkey) -> Result<(), Box<dyn Error>> {
+    let mut app = App::new(game, player.pubkey(), client.game(&game).await?);
+
+    let (updates_sender, mut updates) = mpsc::unbounded_channel();
+    tokio::spawn(async move {
+        if let Err(error) = subscribe(&websocket_url, game, &updates_sender).await {
+            let _ = updates_sender.send(Update::Error(format!("Subscription failed: {}", error)));
+        }
+    });
+    // Terminal events are read on a blocking thread
+    let (keys_sender, mut keys) = mpsc::unbounded_channel();
+    std::thread::spawn(move || loop {
+        match event::read() {
+            Ok(Event::Key(key)) if key.kind == KeyEventKind::Press => {
+                if keys_sender.send(key.code).is_err() {
+                    break;
+                }
+            }
+            Ok(_) => {}
+            Err(_) => break,
+        }
+    });
+
+    let mut terminal = ratatui::init();
+    let result = event_loop(&mut terminal, &mut app, client, player, &mut keys, &mut updates).await;
+    ratatui::restore();
+    result
+}
+
+async fn event_loop(
+    terminal: &mut DefaultTerminal,
+    app: &mut App,
+    client: &TicTacToeClient,
+    player: &Keypair,
+    keys: &mut mpsc::UnboundedReceiver<KeyCode>,
+    updates: &mut mpsc::UnboundedReceiver<Update>,
+) -> Result<(), Box<dyn Error>> {
+    loop {
+        terminal.draw(|frame| draw(frame, app))?;
+        tokio::select! {
+            Some(key) = keys.recv() => match app.handle_key(key) {
+                Some(Action::Quit) => return Ok(()),
+                Some(Action::Play(row, column)) => {
+                    app.status = match client.play(player, &app.game_address, row, column).await {
+                        Ok(signature) => format!("Played ({}, {}): {}", row, column, signature),
+                        Err(error) => format!("Move failed: {}", error),
+                    };
+                }
+                None => {}
+            },
+            Some(update) = updates.recv() => app.apply(update),
+            else => return Ok(()),
+        }
+    }
+}
+
+// Forwards the game account changes and its `GameFinished` event until the websocket closes.
+async fn subscribe(websocket_url: &str, game: Pubkey, updates: &UnboundedSender<Update>) -> Result<(), PubsubClientError> {
+    let pubsub = PubsubClient::new(websocket_url).await?;
+    let account_config = RpcAccountInfoConfig {
+        encoding: Some(UiAccountEncoding::Base64),
+        commitment: Some(CommitmentConfig::confirmed()),
+        ..RpcAccountInfoConfig::default()
+    };
+    let (mut accounts, _) = pubsub.account_subscribe(&game, Some(account_config)).await?;
+    let logs_config = RpcTransactionLogsConfig {
+        commitment: Some(CommitmentConfig::confirmed()),
+    };
+    let (mut logs, _) = pubsub
+        .logs_subscribe(RpcTransactionLogsFilter::Mentions(vec![game.to_string()]), logs_config)
+        .await?;
+    loop {
+        let update = tokio::select! {
+            Some(account) = accounts.next() => match account.value.data.decode().map(|data| decode_game(&data)) {
+                Some(Ok(game)) => Update::Game(Box::new(game)),
+                _ => continue,
+            },
+            Some(response) = logs.next() => match decode_events::<GameFinished>(&response.value.logs).pop() {
+                Some(finished) if finished.game == game => Update::Finished(Box::new(finished)),
+                _ => continue,
+            },
+            else => return Ok(()),
+        };
+        if updates.send(update).is_err() {
+            return Ok(());
+        }
+    }
+}
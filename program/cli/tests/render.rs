@@ -0,0 +1,81 @@
+use solana_sdk::pubkey::Pubkey;
+use tic_tac_toe_client::{game_address, Game, GameState, GlobalState, Pause, Sign};
+use tictactoe_cli::{describe_state, render_board, render_global_state, GameRef};
+
+fn game(board: [[Option<Sign>; 3]; 3], turn: u8, state: GameState) -> Game {
+    Game {
+        version: Game::VERSION,
+        number: 3,
+        players: [Some(Pubkey::new_unique()), Some(Pubkey::new_unique())],
+        turn,
+        board,
+        state,
+        pot: 0,
+        paid: false,
+        league: None,
+        moves: Vec::new(),
+        house: false,
+        hook: None,
+        hook_called: false,
+        relayer_fees: [0; 2],
+    }
+}
+
+#[test]
+fn renders_the_board_with_coordinates() {
+    let board = [
+        [Some(Sign::X), None, None],
+        [None, Some(Sign::O), None],
+        [None, None, Some(Sign::X)],
+    ];
+
+    let expected = "    0   1   2\n\
+                    0   X |   |  \n   ---+---+---\n\
+                    1     | O |  \n   ---+---+---\n\
+                    2     |   | X\n";
+    assert_eq!(render_board(&game(board, 3, GameState::InProgress)), expected);
+}
+
+#[test]
+fn describes_whose_turn_it_is() {
+    let game = game([[None; 3]; 3], 1, GameState::InProgress);
+
+    assert_eq!(describe_state(&game), format!("In progress, O to move ({})", game.players[1].unwrap()));
+}
+
+#[test]
+fn lists_active_players() {
+    let player = Pubkey::new_unique();
+    let global_state = GlobalState {
+        version: GlobalState::VERSION,
+        owner: Pubkey::new_unique(),
+        game_count: 2,
+        players_mapping: vec![player],
+        games_mapping: vec![game_address(2)],
+        fee: 5,
+        bet: 100_000_000,
+        house: None,
+        paused: Pause {
+            moves: true,
+            withdrawals: true,
+            ..Pause::default()
+        },
+        hooks: Vec::new(),
+    };
+
+    let output = render_global_state(&Pubkey::new_unique(), &global_state, 0);
+    assert!(output.contains("Fee:          5%"));
+    assert!(output.contains("Bet:          0.1 SOL"));
+    assert!(output.contains("House:        -"));
+    assert!(output.contains("Paused:       moves, withdrawals"));
+    assert!(output.contains(&format!("  {} in game {}", player, game_address(2))));
+}
+
+#[test]
+fn parses_games_by_number_or_address() {
+    let address = Pubkey::new_unique();
+
+    assert_eq!("7".parse::<GameRef>().unwrap().address(), game_address(7));
+    assert_eq!(address.to_string().parse::<GameRef>().unwrap(), GameRef::Address(address));
+    assert!("seven".parse::<GameRef>().is_err());
+}
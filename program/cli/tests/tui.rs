@@ -0,0 +1,91 @@
+use ratatui::{backend::TestBackend, crossterm::event::KeyCode, Terminal};
+use solana_sdk::pubkey::Pubkey;
+use tic_tac_toe_client::{EndReason, Game, GameFinished, GameState, Sign};
+use tictactoe_cli::tui::{draw, Action, App, Update};
+
+fn app(turn: u8) -> App {
+    let player = Pubkey::new_unique();
+    let game = Game {
+        version: Game::VERSION,
+        number: 5,
+        players: [Some(player), Some(Pubkey::new_unique())],
+        turn,
+        board: [[None; 3]; 3],
+        state: GameState::InProgress,
+        pot: 0,
+        paid: false,
+        league: None,
+        moves: Vec::new(),
+        house: false,
+        hook: None,
+        hook_called: false,
+        relayer_fees: [0; 2],
+    };
+    App::new(Pubkey::new_unique(), player, game)
+}
+
+fn screen(app: &App) -> String {
+    let mut terminal = Terminal::new(TestBackend::new(80, 20)).unwrap();
+    terminal.draw(|frame| draw(frame, app)).unwrap();
+    terminal.backend().buffer().content().iter().map(|cell| cell.symbol()).collect()
+}
+
+#[test]
+fn arrows_move_the_cursor_within_the_board() {
+    let mut app = app(0);
+
+    for key in [KeyCode::Up, KeyCode::Up, KeyCode::Left, KeyCode::Left] {
+        assert_eq!(app.handle_key(key), None);
+    }
+    assert_eq!(app.cursor, (0, 0));
+    for key in [KeyCode::Down, KeyCode::Down, KeyCode::Down, KeyCode::Right] {
+        app.handle_key(key);
+    }
+    assert_eq!(app.cursor, (2, 1));
+    assert_eq!(app.handle_key(KeyCode::Char('q')), Some(Action::Quit));
+}
+
+#[test]
+fn plays_only_empty_tiles_on_the_players_turn() {
+    let mut app = app(0);
+
+    assert_eq!(app.handle_key(KeyCode::Enter), Some(Action::Play(1, 1)));
+    app.game.board[1][1] = Some(Sign::X);
+    assert_eq!(app.handle_key(KeyCode::Enter), None);
+    assert_eq!(app.status, "Tile already set");
+
+    let mut app = self::app(1);
+    assert_eq!(app.handle_key(KeyCode::Char(' ')), None);
+    assert_eq!(app.status, "Not your turn");
+}
+
+#[test]
+fn shows_the_turn_and_the_settlement() {
+    let mut app = app(0);
+    assert!(screen(&app).contains("Game #5"));
+    assert!(screen(&app).contains("Your turn"));
+
+    let mut game = app.game.clone();
+    game.state = GameState::Won { winner: app.player };
+    app.apply(Update::Game(Box::new(game)));
+    app.apply(Update::Finished(Box::new(GameFinished {
+        game: app.game_address,
+        number: 5,
+        player_one: app.player,
+        player_two: app.game.players[1].unwrap(),
+        winner: Some(app.player),
+        reason: EndReason::LineWin,
+        board: app.game.board,
+        turns: 5,
+        pot: 2_000_000_000,
+        fee: 100_000_000,
+        player_one_payout: 1_900_000_000,
+        player_two_payout: 0,
+        moves: Vec::new(),
+        timestamp: 0,
+    })));
+
+    let screen = screen(&app);
+    assert!(screen.contains("You won"));
+    assert!(screen.contains("Payouts: X 1.9 SOL, O 0 SOL"));
+}
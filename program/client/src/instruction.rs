@@ -0,0 +1,527 @@
+// Typed builders for every program instruction.
+//
+// Each builder derives the program accounts from its arguments, so callers only pass the
+// wallets and the accounts that cannot be derived.
+use anchor_lang::{InstructionData, ToAccountMetas};
+use solana_sdk::{
+    ed25519_program,
+    instruction::{AccountMeta, Instruction},
+    pubkey::Pubkey,
+    signature::Signature,
+    system_program, sysvar,
+};
+use tic_tac_toe::{accounts, instruction, AdminAction, Pause, RelayedMove, SideBetOutcome, Tile};
+
+use crate::pda::{admin_set_address, balance_address, escrow_address, global_state_address, hook_authority_address, hosted_game_address, league_address, proposal_address, league_game_address, relay_nonce_address, session_address, side_bet_address, side_pool_address};
+
+// Balance account of `owner` when it is listed in `balances`.
+fn balance_of(owner: &Pubkey, balances: &[Pubkey]) -> Option<Pubkey> {
+    balances.contains(owner).then(|| balance_address(owner))
+}
+
+// Asks for the player's signature unless `session_key` signs for them. The program accounts do
+// not mark the player as a signer, as a session key can sign instead.
+fn signed_by(mut instruction: Instruction, player: &Pubkey, session_key: Option<&Pubkey>) -> Instruction {
+    if session_key.is_none() {
+        if let Some(meta) = instruction.accounts.iter_mut().find(|meta| meta.pubkey == *player) {
+            meta.is_signer = true;
+        }
+    }
+    instruction
+}
+
+fn build(accounts: impl ToAccountMetas, data: impl InstructionData) -> Instruction {
+    Instruction {
+        program_id: tic_tac_toe::ID,
+        accounts: accounts.to_account_metas(None),
+        data: data.data(),
+    }
+}
+
+pub fn initialize_global_state(payer: &Pubkey) -> Instruction {
+    let accounts = accounts::InitializeGlobalState {
+        global_state: global_state_address(),
+        payer: *payer,
+        system_program: system_program::ID,
+    };
+    build(accounts, instruction::InitializeGlobalState {})
+}
+
+// Upgrades a global state with an older layout, `payer` covers any rent the new size needs.
+pub fn migrate_global_state(payer: &Pubkey) -> Instruction {
+    let accounts = accounts::MigrateGlobalState {
+        global_state: global_state_address(),
+        payer: *payer,
+        system_program: system_program::ID,
+    };
+    build(accounts, instruction::MigrateGlobalState {})
+}
+
+// Refunds a game of the first program version. `player_two` is ignored, but must still be passed,
+// while the game waits for a rival.
+pub fn refund_legacy_game(game: &Pubkey, player_one: &Pubkey, player_two: &Pubkey) -> Instruction {
+    let accounts = accounts::RefundLegacyGame {
+        global_state: global_state_address(),
+        game: *game,
+        player_one: *player_one,
+        player_two: *player_two,
+    };
+    build(accounts, instruction::RefundLegacyGame {})
+}
+
+// Joins matchmaking on `game`, the game PDA for the current global state game count. The bet comes
+// from the player's balance with `from_balance`, else from the wallet. A new game calls `hook` when it ends.
+pub fn setup_game(player: &Pubkey, game: &Pubkey, from_balance: bool, hook: Option<Pubkey>) -> Instruction {
+    let accounts = accounts::SetupGame {
+        global_state: global_state_address(),
+        game: *game,
+        escrow: escrow_address(game),
+        player: *player,
+        balance: from_balance.then(|| balance_address(player)),
+        system_program: system_program::ID,
+    };
+    build(accounts, instruction::SetupGame { hook })
+}
+
+// Calls the hook of a settled game, passing the hook program, its hook authority and then
+// `hook_accounts`. Anyone can pay for it.
+pub fn call_game_hook(game: &Pubkey, hook: &Pubkey, hook_accounts: &[AccountMeta]) -> Instruction {
+    let accounts = accounts::CallGameHook {
+        global_state: global_state_address(),
+        game: *game,
+    };
+    let mut instruction = build(accounts, instruction::CallGameHook {});
+    instruction.accounts.push(AccountMeta::new_readonly(*hook, false));
+    instruction.accounts.push(AccountMeta::new_readonly(hook_authority_address(hook), false));
+    instruction.accounts.extend_from_slice(hook_accounts);
+    instruction
+}
+
+// Plays a tile. `balances` lists the players that bet from their balance, which get their payout.
+pub fn play(player: &Pubkey, rival: &Pubkey, game: &Pubkey, row: u8, column: u8, balances: &[Pubkey], session_key: Option<&Pubkey>) -> Instruction {
+    let accounts = accounts::Play {
+        global_state: global_state_address(),
+        game: *game,
+        escrow: escrow_address(game),
+        player: *player,
+        rival: *rival,
+        player_balance: balance_of(player, balances),
+        rival_balance: balance_of(rival, balances),
+        session: session_key.map(|key| session_address(player, key)),
+        session_key: session_key.copied(),
+    };
+    signed_by(build(accounts, instruction::Play { tile: Tile { row, column } }), player, session_key)
+}
+
+// Cancels or resigns from a game. `balances` lists the players that bet from their balance.
+pub fn cancel_game(signer: &Pubkey, rival: &Pubkey, game: &Pubkey, balances: &[Pubkey], session_key: Option<&Pubkey>) -> Instruction {
+    let accounts = accounts::CancelGame {
+        global_state: global_state_address(),
+        game: *game,
+        escrow: escrow_address(game),
+        signer: *signer,
+        rival: *rival,
+        signer_balance: balance_of(signer, balances),
+        // The signer stands in for the rival of a waiting game
+        rival_balance: balance_of(rival, balances).filter(|_| rival != signer),
+        session: session_key.map(|key| session_address(signer, key)),
+        session_key: session_key.copied(),
+    };
+    signed_by(build(accounts, instruction::CancelGame {}), signer, session_key)
+}
+
+// Plays a move signed off-chain by `player`, paid for by `relayer`. It must come right after the
+// `verify_signature` instruction of the move.
+pub fn relay_move(relayer: &Pubkey, player: &Pubkey, rival: &Pubkey, relayed: &RelayedMove, balances: &[Pubkey]) -> Instruction {
+    let accounts = accounts::RelayMove {
+        global_state: global_state_address(),
+        game: relayed.game,
+        escrow: escrow_address(&relayed.game),
+        player: *player,
+        rival: *rival,
+        player_balance: balance_of(player, balances),
+        rival_balance: balance_of(rival, balances),
+        relay_nonce: relay_nonce_address(player),
+        relayer: *relayer,
+        instructions: sysvar::instructions::ID,
+        system_program: system_program::ID,
+    };
+    build(accounts, instruction::RelayMove { relayed: relayed.clone() })
+}
+
+// Ed25519 program instruction verifying a signature made elsewhere, e.g. by a browser wallet,
+// with the key, the signature and the message all in its own data.
+pub fn verify_signature(signer: &Pubkey, signature: &Signature, message: &[u8]) -> Instruction {
+    // A signature count and a padding byte, then seven offsets, then the signed data
+    const KEY_OFFSET: u16 = 2 + 14;
+    const SIGNATURE_OFFSET: u16 = KEY_OFFSET + 32;
+    const MESSAGE_OFFSET: u16 = SIGNATURE_OFFSET + 64;
+    let offsets = [SIGNATURE_OFFSET, u16::MAX, KEY_OFFSET, u16::MAX, MESSAGE_OFFSET, message.len() as u16, u16::MAX];
+
+    let mut data = vec![1, 0];
+    data.extend(offsets.iter().flat_map(|offset| offset.to_le_bytes()));
+    data.extend_from_slice(signer.as_ref());
+    data.extend_from_slice(signature.as_ref());
+    data.extend_from_slice(message);
+    Instruction {
+        program_id: ed25519_program::ID,
+        accounts: vec![],
+        data,
+    }
+}
+
+// Authorizes `key` to play and cancel for `authority` until `expires_at`, in `game` only when given.
+pub fn create_session(authority: &Pubkey, key: &Pubkey, game: Option<Pubkey>, expires_at: i64, spending_cap: u64) -> Instruction {
+    let accounts = accounts::CreateSession {
+        session: session_address(authority, key),
+        authority: *authority,
+        system_program: system_program::ID,
+    };
+    build(accounts, instruction::CreateSession { key: *key, game, expires_at, spending_cap })
+}
+
+// Closes the session of `key`, signed by the player or the key itself.
+pub fn revoke_session(authority: &Pubkey, key: &Pubkey, signer: &Pubkey) -> Instruction {
+    let accounts = accounts::RevokeSession {
+        session: session_address(authority, key),
+        authority: *authority,
+        signer: *signer,
+    };
+    build(accounts, instruction::RevokeSession {})
+}
+
+// Closes a finished game. `rent_payer` funded the escrow and gets its rent back, `league` is the
+// league of a league game.
+pub fn close_game_account(signer: &Pubkey, game: &Pubkey, rent_payer: &Pubkey, league: Option<Pubkey>) -> Instruction {
+    let accounts = accounts::CloseGameAccount {
+        global_state: global_state_address(),
+        game: *game,
+        escrow: escrow_address(game),
+        signer: *signer,
+        rent_payer: *rent_payer,
+        side_pool: side_pool_address(game),
+        league,
+    };
+    build(accounts, instruction::CloseGameAccount {})
+}
+
+// Closes a game settled more than `CLOSE_GRACE_PERIOD` ago. `league` is the league of a league game.
+pub fn close_finished_game(game: &Pubkey, rent_payer: &Pubkey, league: Option<Pubkey>) -> Instruction {
+    let accounts = accounts::CloseFinishedGame {
+        global_state: global_state_address(),
+        game: *game,
+        escrow: escrow_address(game),
+        rent_payer: *rent_payer,
+        side_pool: side_pool_address(game),
+        league,
+    };
+    build(accounts, instruction::CloseFinishedGame {})
+}
+
+// Starts game `id` of `host` between the players, who both sign. Composing programs sign it with
+// their host PDA.
+pub fn create_hosted_game(host: &Pubkey, id: u64, players: [Pubkey; 2], hook: Option<Pubkey>) -> Instruction {
+    let game = hosted_game_address(host, id);
+    let accounts = accounts::CreateHostedGame {
+        global_state: global_state_address(),
+        game,
+        escrow: escrow_address(&game),
+        host: *host,
+        player_one: players[0],
+        player_two: players[1],
+        system_program: system_program::ID,
+    };
+    build(accounts, instruction::CreateHostedGame { id, hook })
+}
+
+// Returns the `GameResult` of a game as return data.
+pub fn game_result(game: &Pubkey) -> Instruction {
+    build(accounts::ReadGameResult { game: *game }, instruction::GameResult {})
+}
+
+// Closes finished game `id` of `host`, returning its `GameResult`.
+pub fn settle_hosted_game(host: &Pubkey, id: u64) -> Instruction {
+    let game = hosted_game_address(host, id);
+    let accounts = accounts::SettleHostedGame {
+        global_state: global_state_address(),
+        game,
+        escrow: escrow_address(&game),
+        host: *host,
+        side_pool: side_pool_address(&game),
+    };
+    build(accounts, instruction::SettleHostedGame { id })
+}
+
+// Deposits `amount` lamports into the owner's balance, creating it if needed.
+pub fn deposit(owner: &Pubkey, amount: u64) -> Instruction {
+    let accounts = accounts::Deposit {
+        balance: balance_address(owner),
+        owner: *owner,
+        system_program: system_program::ID,
+    };
+    build(accounts, instruction::Deposit { amount })
+}
+
+pub fn withdraw(owner: &Pubkey, amount: u64) -> Instruction {
+    let accounts = accounts::Withdraw {
+        global_state: global_state_address(),
+        balance: balance_address(owner),
+        owner: *owner,
+    };
+    build(accounts, instruction::Withdraw { amount })
+}
+
+pub fn withdraw_fees(owner: &Pubkey, amount: u64) -> Instruction {
+    let accounts = accounts::WithdrawFees {
+        global_state: global_state_address(),
+        owner: *owner,
+        system_program: system_program::ID,
+    };
+    build(accounts, instruction::WithdrawFees { amount })
+}
+
+// Sets the house bot key, or disables the house with `None`.
+pub fn set_house(owner: &Pubkey, house: Option<Pubkey>) -> Instruction {
+    let accounts = accounts::SetHouse {
+        global_state: global_state_address(),
+        owner: *owner,
+    };
+    build(accounts, instruction::SetHouse { house })
+}
+
+pub fn set_hook(owner: &Pubkey, hook: &Pubkey, allowed: bool) -> Instruction {
+    let accounts = accounts::SetHook {
+        global_state: global_state_address(),
+        owner: *owner,
+    };
+    build(accounts, instruction::SetHook { hook: *hook, allowed })
+}
+
+pub fn set_paused(owner: &Pubkey, paused: Pause) -> Instruction {
+    let accounts = accounts::SetPaused {
+        global_state: global_state_address(),
+        owner: *owner,
+    };
+    build(accounts, instruction::SetPaused { paused })
+}
+
+pub fn set_config(owner: &Pubkey, fee: u64, bet: u64) -> Instruction {
+    let accounts = accounts::SetConfig {
+        global_state: global_state_address(),
+        owner: *owner,
+    };
+    build(accounts, instruction::SetConfig { fee, bet })
+}
+
+// Hands the admin actions from the single owner key to an M-of-N admin set.
+pub fn create_admin_set(owner: &Pubkey, signers: Vec<Pubkey>, threshold: u8, timelock: i64) -> Instruction {
+    let accounts = accounts::CreateAdminSet {
+        global_state: global_state_address(),
+        admin_set: admin_set_address(),
+        owner: *owner,
+        system_program: system_program::ID,
+    };
+    build(accounts, instruction::CreateAdminSet { signers, threshold, timelock })
+}
+
+// Proposes `action` as proposal `id`, the admin set proposal count.
+pub fn propose(proposer: &Pubkey, id: u64, action: AdminAction) -> Instruction {
+    let accounts = accounts::Propose {
+        admin_set: admin_set_address(),
+        proposal: proposal_address(id),
+        proposer: *proposer,
+        system_program: system_program::ID,
+    };
+    build(accounts, instruction::Propose { action })
+}
+
+pub fn approve_proposal(signer: &Pubkey, id: u64) -> Instruction {
+    let accounts = accounts::ApproveProposal {
+        admin_set: admin_set_address(),
+        proposal: proposal_address(id),
+        signer: *signer,
+    };
+    build(accounts, instruction::ApproveProposal {})
+}
+
+// Executes proposal `id`. `recipient` is only needed to withdraw fees.
+pub fn execute_proposal(id: u64, recipient: Option<Pubkey>) -> Instruction {
+    let accounts = accounts::ExecuteProposal {
+        global_state: global_state_address(),
+        admin_set: admin_set_address(),
+        proposal: proposal_address(id),
+        recipient,
+        game: None,
+        escrow: None,
+        player_one: None,
+        player_two: None,
+        player_one_balance: None,
+        player_two_balance: None,
+    };
+    build(accounts, instruction::ExecuteProposal {})
+}
+
+// Executes proposal `id` refunding `game`, like `emergency_refund` does for a single owner.
+pub fn execute_refund_proposal(id: u64, game: &Pubkey, player_one: &Pubkey, player_two: &Pubkey, balances: &[Pubkey]) -> Instruction {
+    let accounts = accounts::ExecuteProposal {
+        global_state: global_state_address(),
+        admin_set: admin_set_address(),
+        proposal: proposal_address(id),
+        recipient: None,
+        game: Some(*game),
+        escrow: Some(escrow_address(game)),
+        player_one: Some(*player_one),
+        player_two: Some(*player_two),
+        player_one_balance: balance_of(player_one, balances),
+        player_two_balance: balance_of(player_two, balances).filter(|_| player_two != player_one),
+    };
+    build(accounts, instruction::ExecuteProposal {})
+}
+
+// Refunds both bets of `game`. `player_two` is ignored, but must still be passed, while the game
+// waits for a rival. `balances` lists the players that bet from their balance.
+pub fn emergency_refund(owner: &Pubkey, game: &Pubkey, player_one: &Pubkey, player_two: &Pubkey, balances: &[Pubkey]) -> Instruction {
+    let accounts = accounts::EmergencyRefund {
+        global_state: global_state_address(),
+        owner: *owner,
+        game: *game,
+        escrow: escrow_address(game),
+        player_one: *player_one,
+        player_two: *player_two,
+        player_one_balance: balance_of(player_one, balances),
+        player_two_balance: balance_of(player_two, balances).filter(|_| player_two != player_one),
+    };
+    build(accounts, instruction::EmergencyRefund {})
+}
+
+// Starts a game against the house on `game`, the game PDA for the current global state game count.
+pub fn challenge_house(player: &Pubkey, game: &Pubkey, from_balance: bool) -> Instruction {
+    let accounts = accounts::ChallengeHouse {
+        global_state: global_state_address(),
+        game: *game,
+        escrow: escrow_address(game),
+        player: *player,
+        balance: from_balance.then(|| balance_address(player)),
+        system_program: system_program::ID,
+    };
+    build(accounts, instruction::ChallengeHouse {})
+}
+
+pub fn create_league(authority: &Pubkey, id: u64, entry_fee: u64, prize_shares: Vec<u8>) -> Instruction {
+    let accounts = accounts::CreateLeague {
+        league: league_address(authority, id),
+        authority: *authority,
+        system_program: system_program::ID,
+    };
+    build(accounts, instruction::CreateLeague { id, entry_fee, prize_shares })
+}
+
+pub fn join_league(player: &Pubkey, league: &Pubkey) -> Instruction {
+    let accounts = accounts::JoinLeague {
+        league: *league,
+        player: *player,
+        system_program: system_program::ID,
+    };
+    build(accounts, instruction::JoinLeague {})
+}
+
+pub fn start_league(authority: &Pubkey, league: &Pubkey) -> Instruction {
+    let accounts = accounts::StartLeague {
+        league: *league,
+        authority: *authority,
+    };
+    build(accounts, instruction::StartLeague {})
+}
+
+pub fn setup_league_game(player: &Pubkey, league: &Pubkey, fixture_index: u16) -> Instruction {
+    let game = league_game_address(league, fixture_index);
+    let accounts = accounts::SetupLeagueGame {
+        global_state: global_state_address(),
+        league: *league,
+        game,
+        escrow: escrow_address(&game),
+        player: *player,
+        system_program: system_program::ID,
+    };
+    build(accounts, instruction::SetupLeagueGame { fixture_index })
+}
+
+pub fn record_league_result(league: &Pubkey, fixture_index: u16) -> Instruction {
+    let accounts = accounts::RecordLeagueResult {
+        league: *league,
+        game: league_game_address(league, fixture_index),
+    };
+    build(accounts, instruction::RecordLeagueResult { fixture_index })
+}
+
+// Finalizes a league, `ranking` holds the players' wallets from first to last.
+pub fn finalize_league(league: &Pubkey, ranking: &[Pubkey]) -> Instruction {
+    let accounts = accounts::FinalizeLeague { league: *league };
+    let mut instruction = build(accounts, instruction::FinalizeLeague {});
+    instruction
+        .accounts
+        .extend(ranking.iter().map(|player| AccountMeta::new(*player, false)));
+    instruction
+}
+
+// Refunds a league past its season with fixtures pending, `players` holds the players' wallets
+// in registration order.
+pub fn refund_league(league: &Pubkey, players: &[Pubkey]) -> Instruction {
+    let accounts = accounts::RefundLeague { league: *league };
+    let mut instruction = build(accounts, instruction::RefundLeague {});
+    instruction
+        .accounts
+        .extend(players.iter().map(|player| AccountMeta::new(*player, false)));
+    instruction
+}
+
+pub fn open_side_pool(payer: &Pubkey, game: &Pubkey) -> Instruction {
+    let accounts = accounts::OpenSidePool {
+        side_pool: side_pool_address(game),
+        game: *game,
+        payer: *payer,
+        system_program: system_program::ID,
+    };
+    build(accounts, instruction::OpenSidePool {})
+}
+
+pub fn place_side_bet(bettor: &Pubkey, game: &Pubkey, outcome: SideBetOutcome, amount: u64) -> Instruction {
+    let side_pool = side_pool_address(game);
+    let accounts = accounts::PlaceSideBet {
+        side_pool,
+        game: *game,
+        side_bet: side_bet_address(&side_pool, bettor),
+        bettor: *bettor,
+        system_program: system_program::ID,
+    };
+    build(accounts, instruction::PlaceSideBet { outcome, amount })
+}
+
+pub fn claim_side_bet(bettor: &Pubkey, game: &Pubkey) -> Instruction {
+    let side_pool = side_pool_address(game);
+    let accounts = accounts::ClaimSideBet {
+        global_state: global_state_address(),
+        side_pool,
+        game: *game,
+        side_bet: side_bet_address(&side_pool, bettor),
+        bettor: *bettor,
+    };
+    build(accounts, instruction::ClaimSideBet {})
+}
+
+// Closes the side pool of a game, paying out the bets of `bettors` left unclaimed. `payer` opened
+// the pool and gets its rent back.
+pub fn close_side_pool(payer: &Pubkey, game: &Pubkey, bettors: &[Pubkey]) -> Instruction {
+    let side_pool = side_pool_address(game);
+    let accounts = accounts::CloseSidePool {
+        global_state: global_state_address(),
+        side_pool,
+        game: *game,
+        payer: *payer,
+    };
+    let mut instruction = build(accounts, instruction::CloseSidePool {});
+    instruction.accounts.extend(bettors.iter().flat_map(|bettor| {
+        [AccountMeta::new(side_bet_address(&side_pool, bettor), false), AccountMeta::new(*bettor, false)]
+    }));
+    instruction
+}
@@ -0,0 +1,404 @@
+// Rust client for the tic-tac-toe program.
+//
+// - `pda`: addresses of the global state, games, hosted games, the hook authority, escrows, balances, sessions, relay nonces, leagues, side bets and admin proposals.
+// - `instruction`: typed builders for every instruction.
+// - `decode_game` / `decode_global_state`: decoders for the raw account data.
+// - `decode_events`: decoder for the events emitted in transaction logs.
+// - `TicTacToeClient`: async API over an RPC node that fetches what each call needs.
+use anchor_lang::{AccountDeserialize, AnchorDeserialize, Event};
+use base64::{engine::general_purpose::STANDARD, Engine};
+use solana_client::nonblocking::rpc_client::RpcClient;
+use solana_sdk::{
+    commitment_config::CommitmentConfig,
+    instruction::{AccountMeta, Instruction},
+    pubkey::Pubkey,
+    signature::{Keypair, Signature, Signer},
+    system_instruction,
+    transaction::Transaction,
+};
+
+pub use tic_tac_toe::{
+    self, engine, AdminAction, AdminSet, BalanceChanged, EndReason, Escrow, Game, GameArchived, GameFinished, GameResult, GameState, GlobalState, HookCalled, LegacyGame, MoveRelayed, Pause, Proposal, RelayNonce,
+    RelayedMove, Session, SessionCreated, SessionRevoked, Sign, Tile,
+};
+
+pub mod instruction;
+pub mod pda;
+
+pub use pda::{admin_set_address, balance_address, escrow_address, game_address, global_state_address, hook_authority_address, hosted_game_address, proposal_address, relay_nonce_address, session_address};
+
+#[derive(Debug, thiserror::Error)]
+pub enum ClientError {
+    #[error(transparent)]
+    Rpc(Box<solana_client::client_error::ClientError>),
+    #[error("failed to decode account: {0}")]
+    Decode(#[from] anchor_lang::error::Error),
+    #[error("{player} is not a player of game {game}")]
+    NotAPlayer { player: Pubkey, game: Pubkey },
+}
+
+// RPC errors are boxed, as they are much larger than the other variants.
+impl From<solana_client::client_error::ClientError> for ClientError {
+    fn from(error: solana_client::client_error::ClientError) -> Self {
+        ClientError::Rpc(Box::new(error))
+    }
+}
+
+pub type Result<T> = std::result::Result<T, ClientError>;
+
+// Decodes the data of any program account, checking its discriminator.
+pub fn decode<T: AccountDeserialize>(data: &[u8]) -> Result<T> {
+    Ok(T::try_deserialize(&mut &data[..])?)
+}
+
+pub fn decode_game(data: &[u8]) -> Result<Game> {
+    decode(data)
+}
+
+pub fn decode_global_state(data: &[u8]) -> Result<GlobalState> {
+    decode(data)
+}
+
+// Decodes the events of type `T` emitted in a transaction's logs.
+pub fn decode_events<T: Event>(logs: &[String]) -> Vec<T> {
+    logs.iter()
+        .filter_map(|log| log.strip_prefix("Program data: "))
+        .filter_map(|data| STANDARD.decode(data).ok())
+        .filter(|data| data.starts_with(&T::DISCRIMINATOR))
+        .filter_map(|data| T::try_from_slice(&data[8..]).ok())
+        .collect()
+}
+
+// Accounts the hook of a game needs, from the game address and account.
+pub type HookAccounts = Box<dyn Fn(&Pubkey, &Game) -> Vec<AccountMeta> + Send + Sync>;
+
+pub struct TicTacToeClient {
+    rpc: RpcClient,
+    hook_accounts: Option<HookAccounts>,
+}
+
+impl TicTacToeClient {
+    pub fn new(rpc: RpcClient) -> Self {
+        TicTacToeClient { rpc, hook_accounts: None }
+    }
+
+    // Connects to the RPC node at `url` with confirmed commitment.
+    pub fn with_url(url: impl ToString) -> Self {
+        Self::new(RpcClient::new_with_commitment(url.to_string(), CommitmentConfig::confirmed()))
+    }
+
+    // Lists the accounts game hooks need, passed after the hook program and the hook authority
+    // when the hook of an ended game is called. Without it, hooks get no other account.
+    pub fn with_hook_accounts(mut self, hook_accounts: impl Fn(&Pubkey, &Game) -> Vec<AccountMeta> + Send + Sync + 'static) -> Self {
+        self.hook_accounts = Some(Box::new(hook_accounts));
+        self
+    }
+
+    pub fn rpc(&self) -> &RpcClient {
+        &self.rpc
+    }
+
+    pub async fn account<T: AccountDeserialize>(&self, address: &Pubkey) -> Result<T> {
+        decode(&self.rpc.get_account_data(address).await?)
+    }
+
+    pub async fn global_state(&self) -> Result<GlobalState> {
+        self.account(&global_state_address()).await
+    }
+
+    pub async fn game(&self, game: &Pubkey) -> Result<Game> {
+        self.account(game).await
+    }
+
+    pub async fn admin_set(&self) -> Result<AdminSet> {
+        self.account(&admin_set_address()).await
+    }
+
+    pub async fn proposal(&self, id: u64) -> Result<Proposal> {
+        self.account(&proposal_address(id)).await
+    }
+
+    // Game a player is currently mapped to, if any.
+    pub async fn active_game(&self, player: &Pubkey) -> Result<Option<Pubkey>> {
+        Ok(self.global_state().await?.find_game_from_player(*player).ok())
+    }
+
+    // Sends the instructions in a transaction paid and signed by `signer`.
+    pub async fn send(&self, instructions: &[Instruction], signer: &Keypair) -> Result<Signature> {
+        let blockhash = self.rpc.get_latest_blockhash().await?;
+        let transaction = Transaction::new_signed_with_payer(instructions, Some(&signer.pubkey()), &[signer], blockhash);
+        Ok(self.rpc.send_and_confirm_transaction(&transaction).await?)
+    }
+
+    pub async fn initialize_global_state(&self, payer: &Keypair) -> Result<Signature> {
+        self.send(&[instruction::initialize_global_state(&payer.pubkey())], payer).await
+    }
+
+    pub async fn migrate_global_state(&self, payer: &Keypair) -> Result<Signature> {
+        self.send(&[instruction::migrate_global_state(&payer.pubkey())], payer).await
+    }
+
+    // Refunds a game the first program version left running, reading its players from the
+    // legacy layout.
+    pub async fn refund_legacy_game(&self, payer: &Keypair, game: &Pubkey) -> Result<Signature> {
+        let data = self.rpc.get_account_data(game).await?;
+        let legacy = LegacyGame::deserialize(&mut &data[8..]).map_err(anchor_lang::error::Error::from)?;
+        let player_one = legacy.players[0].unwrap_or_default();
+        let player_two = legacy.players[1].unwrap_or(player_one);
+        self.send(&[instruction::refund_legacy_game(game, &player_one, &player_two)], payer).await
+    }
+
+    // Joins the waiting game or opens a new one, and returns the game the player was placed in.
+    // The bet comes from the player's balance with `from_balance`, else from the wallet.
+    pub async fn join_matchmaking(&self, player: &Keypair, from_balance: bool) -> Result<Pubkey> {
+        let game = game_address(self.global_state().await?.game_count);
+        self.send(&[instruction::setup_game(&player.pubkey(), &game, from_balance, None)], player).await?;
+        Ok(game)
+    }
+
+    // Starts a game against the house bot and returns it.
+    pub async fn challenge_house(&self, player: &Keypair, from_balance: bool) -> Result<Pubkey> {
+        let game = game_address(self.global_state().await?.game_count);
+        self.send(&[instruction::challenge_house(&player.pubkey(), &game, from_balance)], player).await?;
+        Ok(game)
+    }
+
+    // Lamports of the player's balance available to bet or withdraw, 0 without a balance.
+    pub async fn balance(&self, owner: &Pubkey) -> Result<u64> {
+        let address = balance_address(owner);
+        let Some(account) = self.rpc.get_account_with_commitment(&address, self.rpc.commitment()).await?.value else {
+            return Ok(0);
+        };
+        let rent = self.rpc.get_minimum_balance_for_rent_exemption(account.data.len()).await?;
+        Ok(account.lamports.saturating_sub(rent))
+    }
+
+    pub async fn deposit(&self, owner: &Keypair, amount: u64) -> Result<Signature> {
+        self.send(&[instruction::deposit(&owner.pubkey(), amount)], owner).await
+    }
+
+    pub async fn withdraw(&self, owner: &Keypair, amount: u64) -> Result<Signature> {
+        self.send(&[instruction::withdraw(&owner.pubkey(), amount)], owner).await
+    }
+
+    // Games the house bot is currently playing.
+    pub async fn house_games(&self, house: &Pubkey) -> Result<Vec<Pubkey>> {
+        let global_state = self.global_state().await?;
+        Ok(global_state
+            .players_mapping
+            .iter()
+            .zip(&global_state.games_mapping)
+            .filter(|(player, _)| *player == house)
+            .map(|(_, game)| *game)
+            .collect())
+    }
+
+    // Plays a tile, reading the rival from the game account.
+    pub async fn play(&self, player: &Keypair, game: &Pubkey, row: u8, column: u8) -> Result<Signature> {
+        let rival = self.rival(&player.pubkey(), game).await?;
+        let balances = self.balances(game).await?;
+        let signature = self.send(&[instruction::play(&player.pubkey(), &rival, game, row, column, &balances, None)], player).await?;
+        self.call_pending_hook(player, game).await;
+        Ok(signature)
+    }
+
+    // Plays a tile for `player`, signed and paid by their session key.
+    pub async fn play_with_session(&self, session_key: &Keypair, player: &Pubkey, game: &Pubkey, row: u8, column: u8) -> Result<Signature> {
+        let rival = self.rival(player, game).await?;
+        let balances = self.balances(game).await?;
+        let signature = self.send(&[instruction::play(player, &rival, game, row, column, &balances, Some(&session_key.pubkey()))], session_key).await?;
+        self.call_pending_hook(session_key, game).await;
+        Ok(signature)
+    }
+
+    // Cancels a waiting game or resigns from a game in progress.
+    pub async fn cancel_game(&self, signer: &Keypair, game: &Pubkey) -> Result<Signature> {
+        let rival = self.rival(&signer.pubkey(), game).await?;
+        let balances = self.balances(game).await?;
+        let signature = self.send(&[instruction::cancel_game(&signer.pubkey(), &rival, game, &balances, None)], signer).await?;
+        self.call_pending_hook(signer, game).await;
+        Ok(signature)
+    }
+
+    // Cancels or resigns for `player`, signed and paid by their session key.
+    pub async fn cancel_game_with_session(&self, session_key: &Keypair, player: &Pubkey, game: &Pubkey) -> Result<Signature> {
+        let rival = self.rival(player, game).await?;
+        let balances = self.balances(game).await?;
+        let signature = self.send(&[instruction::cancel_game(player, &rival, game, &balances, Some(&session_key.pubkey()))], session_key).await?;
+        self.call_pending_hook(session_key, game).await;
+        Ok(signature)
+    }
+
+    // Signs a move of the player for a relayer to submit, reading the turn and the player's next
+    // nonce. `relayer_fee` is the reimbursement the relayer gets from the pot.
+    pub async fn sign_move(&self, player: &Keypair, game: &Pubkey, row: u8, column: u8, relayer_fee: u64) -> Result<(RelayedMove, Signature)> {
+        let relayed = RelayedMove {
+            game: *game,
+            turn: self.game(game).await?.turn,
+            tile: Tile { row, column },
+            nonce: self.relay_nonce(&player.pubkey()).await?,
+            relayer_fee,
+        };
+        let signature = player.sign_message(&relayed.message());
+        Ok((relayed, signature))
+    }
+
+    // Submits a move signed by `player`, with the relayer paying the transaction.
+    pub async fn relay_move(&self, relayer: &Keypair, player: &Pubkey, relayed: &RelayedMove, signature: &Signature) -> Result<Signature> {
+        let rival = self.rival(player, &relayed.game).await?;
+        let balances = self.balances(&relayed.game).await?;
+        let instructions = [
+            instruction::verify_signature(player, signature, &relayed.message()),
+            instruction::relay_move(&relayer.pubkey(), player, &rival, relayed, &balances),
+        ];
+        let signature = self.send(&instructions, relayer).await?;
+        self.call_pending_hook(relayer, &relayed.game).await;
+        Ok(signature)
+    }
+
+    // Nonce the next relayed move of the player must carry, 0 before their first one.
+    pub async fn relay_nonce(&self, player: &Pubkey) -> Result<u64> {
+        let address = relay_nonce_address(player);
+        match self.rpc.get_account_with_commitment(&address, self.rpc.commitment()).await?.value {
+            Some(account) => Ok(decode::<RelayNonce>(&account.data)?.nonce),
+            None => Ok(0),
+        }
+    }
+
+    // Authorizes `session_key` to play for the player, and sends it `fees` lamports so it can pay
+    // for its own transactions.
+    pub async fn create_session(&self, player: &Keypair, session_key: &Pubkey, game: Option<Pubkey>, expires_at: i64, spending_cap: u64, fees: u64) -> Result<Signature> {
+        let instructions = [
+            instruction::create_session(&player.pubkey(), session_key, game, expires_at, spending_cap),
+            system_instruction::transfer(&player.pubkey(), session_key, fees),
+        ];
+        self.send(&instructions, player).await
+    }
+
+    // Closes the session of `key`, signed by the player or by the key itself.
+    pub async fn revoke_session(&self, signer: &Keypair, authority: &Pubkey, key: &Pubkey) -> Result<Signature> {
+        self.send(&[instruction::revoke_session(authority, key, &signer.pubkey())], signer).await
+    }
+
+    // Closes a finished game, reading the rent payer and the league from the escrow and game accounts.
+    pub async fn close_game_account(&self, signer: &Keypair, game: &Pubkey) -> Result<Signature> {
+        let escrow: Escrow = self.account(&escrow_address(game)).await?;
+        let league = self.game(game).await?.league;
+        self.send(&[instruction::close_game_account(&signer.pubkey(), game, &escrow.rent_payer, league)], signer).await
+    }
+
+    // Closes a game settled more than a grace period ago, reading the rent payer and the league
+    // from the escrow and game accounts. Anyone can pay for it.
+    pub async fn close_finished_game(&self, payer: &Keypair, game: &Pubkey) -> Result<Signature> {
+        let escrow: Escrow = self.account(&escrow_address(game)).await?;
+        let league = self.game(game).await?.league;
+        self.send(&[instruction::close_finished_game(game, &escrow.rent_payer, league)], payer).await
+    }
+
+    // Calls the pending hook of a settled game, passing the accounts from `with_hook_accounts`.
+    pub async fn call_game_hook(&self, payer: &Keypair, game: &Pubkey) -> Result<Signature> {
+        let game_account = self.game(game).await?;
+        let hook = game_account.hook.unwrap_or_default();
+        self.send(&[instruction::call_game_hook(game, &hook, &self.hook_accounts(game, &game_account))], payer).await
+    }
+
+    pub async fn set_house(&self, owner: &Keypair, house: Option<Pubkey>) -> Result<Signature> {
+        self.send(&[instruction::set_house(&owner.pubkey(), house)], owner).await
+    }
+
+    pub async fn set_hook(&self, owner: &Keypair, hook: &Pubkey, allowed: bool) -> Result<Signature> {
+        self.send(&[instruction::set_hook(&owner.pubkey(), hook, allowed)], owner).await
+    }
+
+    pub async fn set_paused(&self, owner: &Keypair, paused: Pause) -> Result<Signature> {
+        self.send(&[instruction::set_paused(&owner.pubkey(), paused)], owner).await
+    }
+
+    pub async fn set_config(&self, owner: &Keypair, fee: u64, bet: u64) -> Result<Signature> {
+        self.send(&[instruction::set_config(&owner.pubkey(), fee, bet)], owner).await
+    }
+
+    pub async fn create_admin_set(&self, owner: &Keypair, signers: Vec<Pubkey>, threshold: u8, timelock: i64) -> Result<Signature> {
+        self.send(&[instruction::create_admin_set(&owner.pubkey(), signers, threshold, timelock)], owner).await
+    }
+
+    // Proposes an admin action and returns the proposal id.
+    pub async fn propose(&self, proposer: &Keypair, action: AdminAction) -> Result<u64> {
+        let id = self.admin_set().await?.proposal_count;
+        self.send(&[instruction::propose(&proposer.pubkey(), id, action)], proposer).await?;
+        Ok(id)
+    }
+
+    pub async fn approve_proposal(&self, signer: &Keypair, id: u64) -> Result<Signature> {
+        self.send(&[instruction::approve_proposal(&signer.pubkey(), id)], signer).await
+    }
+
+    // Executes a proposal, reading the fee recipient or the refunded game from the proposal account.
+    pub async fn execute_proposal(&self, payer: &Keypair, id: u64) -> Result<Signature> {
+        let execute = match self.proposal(id).await?.action {
+            AdminAction::WithdrawFees { recipient, .. } => instruction::execute_proposal(id, Some(recipient)),
+            AdminAction::EmergencyRefund { game } => {
+                let [player_one, player_two] = self.refunded_players(&game).await?;
+                let balances = self.balances(&game).await?;
+                instruction::execute_refund_proposal(id, &game, &player_one, &player_two, &balances)
+            }
+            _ => instruction::execute_proposal(id, None),
+        };
+        self.send(&[execute], payer).await
+    }
+
+    // Refunds both bets of a waiting or in progress game, reading the players from the game account.
+    pub async fn emergency_refund(&self, owner: &Keypair, game: &Pubkey) -> Result<Signature> {
+        let [player_one, player_two] = self.refunded_players(game).await?;
+        let balances = self.balances(game).await?;
+        self.send(&[instruction::emergency_refund(&owner.pubkey(), game, &player_one, &player_two, &balances)], owner).await
+    }
+
+    pub async fn withdraw_fees(&self, owner: &Keypair, amount: u64) -> Result<Signature> {
+        self.send(&[instruction::withdraw_fees(&owner.pubkey(), amount)], owner).await
+    }
+
+    // Calls the hook of a game the last instruction ended, in a transaction of its own so the
+    // hook cannot revert the move. A failing hook is left pending for `call_game_hook`.
+    async fn call_pending_hook(&self, payer: &Keypair, game: &Pubkey) {
+        let Ok(game_account) = self.game(game).await else {
+            return;
+        };
+        if let Some(hook) = game_account.hook.filter(|_| game_account.paid && !game_account.hook_called) {
+            let call = instruction::call_game_hook(game, &hook, &self.hook_accounts(game, &game_account));
+            let _ = self.send(&[call], payer).await;
+        }
+    }
+
+    fn hook_accounts(&self, game: &Pubkey, game_account: &Game) -> Vec<AccountMeta> {
+        self.hook_accounts.as_ref().map(|hook_accounts| hook_accounts(game, game_account)).unwrap_or_default()
+    }
+
+    // Players of a game to refund. Player one stands in for player two while the game waits for
+    // a rival.
+    async fn refunded_players(&self, game: &Pubkey) -> Result<[Pubkey; 2]> {
+        let [player_one, player_two] = self.game(game).await?.players;
+        let player_one = player_one.unwrap_or_default();
+        Ok([player_one, player_two.unwrap_or(player_one)])
+    }
+
+    // Players of the game that bet from their balance, read from the escrow account.
+    async fn balances(&self, game: &Pubkey) -> Result<Vec<Pubkey>> {
+        let escrow: Escrow = self.account(&escrow_address(game)).await?;
+        let players = self.game(game).await?.players;
+        Ok(players
+            .iter()
+            .zip(escrow.from_balance)
+            .filter_map(|(player, from_balance)| player.filter(|_| from_balance))
+            .collect())
+    }
+
+    // The other player of the game, or the player itself while the game waits for a rival.
+    async fn rival(&self, player: &Pubkey, game: &Pubkey) -> Result<Pubkey> {
+        match self.game(game).await?.players {
+            [Some(player_one), rival] if player_one == *player => Ok(rival.unwrap_or(*player)),
+            [Some(rival), Some(player_two)] if player_two == *player => Ok(rival),
+            _ => Err(ClientError::NotAPlayer { player: *player, game: *game }),
+        }
+    }
+}
@@ -0,0 +1,68 @@
+// Program derived addresses of the program accounts.
+use solana_sdk::pubkey::Pubkey;
+use tic_tac_toe::{ADMIN_SET_SEED, BALANCE_SEED, ESCROW_SEED, GAME_SEED, GLOBAL_STATE_SEED, HOOK_AUTHORITY_SEED, LEAGUE_SEED, PROPOSAL_SEED, RELAY_NONCE_SEED, SESSION_SEED, SIDE_BET_SEED, SIDE_POOL_SEED};
+
+pub fn global_state_address() -> Pubkey {
+    Pubkey::find_program_address(&[GLOBAL_STATE_SEED], &tic_tac_toe::ID).0
+}
+
+// Matchmaking game with the given number.
+pub fn game_address(number: u64) -> Pubkey {
+    Pubkey::find_program_address(&[GAME_SEED, &number.to_le_bytes()], &tic_tac_toe::ID).0
+}
+
+// Game `id` of a host, e.g. a PDA of a composing program.
+pub fn hosted_game_address(host: &Pubkey, id: u64) -> Pubkey {
+    Pubkey::find_program_address(&[GAME_SEED, host.as_ref(), &id.to_le_bytes()], &tic_tac_toe::ID).0
+}
+
+// Vault holding the wagers of a game.
+pub fn escrow_address(game: &Pubkey) -> Pubkey {
+    Pubkey::find_program_address(&[ESCROW_SEED, game.as_ref()], &tic_tac_toe::ID).0
+}
+
+// Internal balance of a player's wallet.
+pub fn balance_address(owner: &Pubkey) -> Pubkey {
+    Pubkey::find_program_address(&[BALANCE_SEED, owner.as_ref()], &tic_tac_toe::ID).0
+}
+
+// Session authorizing `key` to sign for `authority`.
+pub fn session_address(authority: &Pubkey, key: &Pubkey) -> Pubkey {
+    Pubkey::find_program_address(&[SESSION_SEED, authority.as_ref(), key.as_ref()], &tic_tac_toe::ID).0
+}
+
+// Next nonce of the moves `player` signs for relayers.
+pub fn relay_nonce_address(player: &Pubkey) -> Pubkey {
+    Pubkey::find_program_address(&[RELAY_NONCE_SEED, player.as_ref()], &tic_tac_toe::ID).0
+}
+
+pub fn league_address(authority: &Pubkey, id: u64) -> Pubkey {
+    Pubkey::find_program_address(&[LEAGUE_SEED, authority.as_ref(), &id.to_le_bytes()], &tic_tac_toe::ID).0
+}
+
+// Game of a league fixture.
+pub fn league_game_address(league: &Pubkey, fixture_index: u16) -> Pubkey {
+    Pubkey::find_program_address(&[GAME_SEED, league.as_ref(), &fixture_index.to_le_bytes()], &tic_tac_toe::ID).0
+}
+
+pub fn side_pool_address(game: &Pubkey) -> Pubkey {
+    Pubkey::find_program_address(&[SIDE_POOL_SEED, game.as_ref()], &tic_tac_toe::ID).0
+}
+
+pub fn side_bet_address(side_pool: &Pubkey, bettor: &Pubkey) -> Pubkey {
+    Pubkey::find_program_address(&[SIDE_BET_SEED, side_pool.as_ref(), bettor.as_ref()], &tic_tac_toe::ID).0
+}
+
+// Signs the calls to the game hook `hook`.
+pub fn hook_authority_address(hook: &Pubkey) -> Pubkey {
+    Pubkey::find_program_address(&[HOOK_AUTHORITY_SEED, hook.as_ref()], &tic_tac_toe::ID).0
+}
+
+pub fn admin_set_address() -> Pubkey {
+    Pubkey::find_program_address(&[ADMIN_SET_SEED], &tic_tac_toe::ID).0
+}
+
+// Admin set proposal with the given id.
+pub fn proposal_address(id: u64) -> Pubkey {
+    Pubkey::find_program_address(&[PROPOSAL_SEED, &id.to_le_bytes()], &tic_tac_toe::ID).0
+}
@@ -0,0 +1,246 @@
+// Runs the instruction builders against the program in an in-process bank.
+use anchor_lang::Event;
+use base64::{engine::general_purpose::STANDARD, Engine};
+use solana_program_test::{processor, BanksClientError, ProgramTest, ProgramTestContext};
+use solana_sdk::{
+    account_info::AccountInfo,
+    clock::Clock,
+    entrypoint::ProgramResult,
+    instruction::Instruction,
+    native_token::LAMPORTS_PER_SOL,
+    pubkey::Pubkey,
+    signature::{Keypair, Signer},
+    system_instruction,
+    transaction::Transaction,
+};
+use tic_tac_toe::{GameClosed, League, LeagueState, RelayNonce, RelayedMove, SideBetOutcome, SideBetPool, Tile};
+use tic_tac_toe_client::{
+    decode, decode_events, decode_game, decode_global_state, game_address, global_state_address, instruction, pda, GameFinished,
+    GameState,
+};
+
+// Anchor's entrypoint ties the accounts to the instruction lifetime, the builtin processor does not.
+fn process_instruction(program_id: &Pubkey, accounts: &[AccountInfo], data: &[u8]) -> ProgramResult {
+    let accounts = Box::leak(Box::new(accounts.to_vec()));
+    tic_tac_toe::entry(program_id, accounts, data)
+}
+
+async fn start() -> ProgramTestContext {
+    let program_test = ProgramTest::new("tic_tac_toe", tic_tac_toe::ID, processor!(process_instruction));
+    let mut context = program_test.start_with_context().await;
+    let payer = context.payer.insecure_clone();
+    send(&mut context, &[instruction::initialize_global_state(&payer.pubkey())], &[]).await.unwrap();
+    context
+}
+
+async fn send(context: &mut ProgramTestContext, instructions: &[Instruction], signers: &[&Keypair]) -> Result<(), BanksClientError> {
+    let payer = context.payer.insecure_clone();
+    let mut all_signers = vec![&payer];
+    all_signers.extend_from_slice(signers);
+    let blockhash = context.get_new_latest_blockhash().await.unwrap();
+    let transaction = Transaction::new_signed_with_payer(instructions, Some(&payer.pubkey()), &all_signers, blockhash);
+    context.banks_client.process_transaction(transaction).await
+}
+
+async fn funded_wallet(context: &mut ProgramTestContext) -> Keypair {
+    let wallet = Keypair::new();
+    let payer = context.payer.pubkey();
+    send(context, &[system_instruction::transfer(&payer, &wallet.pubkey(), 10 * LAMPORTS_PER_SOL)], &[])
+        .await
+        .unwrap();
+    wallet
+}
+
+async fn data(context: &mut ProgramTestContext, address: Pubkey) -> Vec<u8> {
+    context.banks_client.get_account(address).await.unwrap().unwrap().data
+}
+
+// Sets up a matchmaking game between two new wallets.
+async fn start_game(context: &mut ProgramTestContext) -> (Pubkey, Keypair, Keypair) {
+    let player_one = funded_wallet(context).await;
+    let player_two = funded_wallet(context).await;
+    let game_count = decode_global_state(&data(context, global_state_address()).await).unwrap().game_count;
+    let game = game_address(game_count);
+    for player in [&player_one, &player_two] {
+        send(context, &[instruction::setup_game(&player.pubkey(), &game, false, None)], &[player]).await.unwrap();
+    }
+    (game, player_one, player_two)
+}
+
+#[tokio::test]
+async fn plays_and_closes_a_game() {
+    let mut context = start().await;
+    let (game, player_one, player_two) = start_game(&mut context).await;
+
+    let moves = [(0, 0), (1, 0), (0, 1), (1, 1), (0, 2)];
+    for (index, &(row, column)) in moves.iter().enumerate() {
+        let (player, rival) = if index % 2 == 0 { (&player_one, &player_two) } else { (&player_two, &player_one) };
+        let play = instruction::play(&player.pubkey(), &rival.pubkey(), &game, row, column, &[], None);
+        send(&mut context, &[play], &[player]).await.unwrap();
+    }
+
+    let game_account = decode_game(&data(&mut context, game).await).unwrap();
+    assert_eq!(game_account.state, GameState::Won { winner: player_one.pubkey() });
+    assert_eq!(game_account.moves.len(), moves.len());
+    assert!(decode_global_state(&data(&mut context, global_state_address()).await).unwrap().players_mapping.is_empty());
+
+    send(&mut context, &[instruction::close_game_account(&player_one.pubkey(), &game, &player_one.pubkey(), None)], &[&player_one]).await.unwrap();
+    assert!(context.banks_client.get_account(game).await.unwrap().is_none());
+}
+
+#[tokio::test]
+async fn plays_with_a_session_key() {
+    let mut context = start().await;
+    let (game, player_one, player_two) = start_game(&mut context).await;
+    let key = Keypair::new();
+    let expires_at = context.banks_client.get_sysvar::<Clock>().await.unwrap().unix_timestamp + 3600;
+
+    let bet = decode_global_state(&data(&mut context, global_state_address()).await).unwrap().bet;
+    let session = instruction::create_session(&player_one.pubkey(), &key.pubkey(), Some(game), expires_at, bet);
+    send(&mut context, &[session], &[&player_one]).await.unwrap();
+    let play = instruction::play(&player_one.pubkey(), &player_two.pubkey(), &game, 1, 1, &[], Some(&key.pubkey()));
+    send(&mut context, &[play], &[&key]).await.unwrap();
+    send(&mut context, &[instruction::revoke_session(&player_one.pubkey(), &key.pubkey(), &key.pubkey())], &[&key])
+        .await
+        .unwrap();
+
+    assert_eq!(decode_game(&data(&mut context, game).await).unwrap().moves.len(), 1);
+    assert!(context.banks_client.get_account(pda::session_address(&player_one.pubkey(), &key.pubkey())).await.unwrap().is_none());
+}
+
+#[tokio::test]
+async fn relays_a_signed_move() {
+    let mut context = start().await;
+    let (game, player_one, player_two) = start_game(&mut context).await;
+    let relayer = funded_wallet(&mut context).await;
+    let relayed = RelayedMove {
+        game,
+        turn: 0,
+        tile: Tile { row: 1, column: 1 },
+        nonce: 0,
+        relayer_fee: 0,
+    };
+
+    let signature = player_one.sign_message(&relayed.message());
+    let verify = instruction::verify_signature(&player_one.pubkey(), &signature, &relayed.message());
+    let relay = instruction::relay_move(&relayer.pubkey(), &player_one.pubkey(), &player_two.pubkey(), &relayed, &[]);
+    send(&mut context, &[verify, relay], &[&relayer]).await.unwrap();
+
+    assert_eq!(decode_game(&data(&mut context, game).await).unwrap().moves.len(), 1);
+    let relay_nonce = decode::<RelayNonce>(&data(&mut context, pda::relay_nonce_address(&player_one.pubkey())).await).unwrap();
+    assert_eq!(relay_nonce.nonce, 1);
+}
+
+#[tokio::test]
+async fn hosts_and_settles_a_game() {
+    let mut context = start().await;
+    let host = funded_wallet(&mut context).await;
+    let player_one = funded_wallet(&mut context).await;
+    let player_two = funded_wallet(&mut context).await;
+    let create = instruction::create_hosted_game(&host.pubkey(), 3, [player_one.pubkey(), player_two.pubkey()], None);
+    send(&mut context, &[create], &[&host, &player_one, &player_two]).await.unwrap();
+
+    let game = pda::hosted_game_address(&host.pubkey(), 3);
+    for (index, (row, column)) in [(0, 0), (1, 0), (0, 1), (1, 1), (0, 2)].into_iter().enumerate() {
+        let (player, rival) = if index % 2 == 0 { (&player_one, &player_two) } else { (&player_two, &player_one) };
+        let play = instruction::play(&player.pubkey(), &rival.pubkey(), &game, row, column, &[], None);
+        send(&mut context, &[play], &[player]).await.unwrap();
+    }
+    send(&mut context, &[instruction::settle_hosted_game(&host.pubkey(), 3)], &[&host]).await.unwrap();
+
+    assert!(context.banks_client.get_account(game).await.unwrap().is_none());
+}
+
+#[tokio::test]
+async fn cancels_a_waiting_game() {
+    let mut context = start().await;
+    let player = funded_wallet(&mut context).await;
+    let game = game_address(1);
+    send(&mut context, &[instruction::setup_game(&player.pubkey(), &game, false, None)], &[&player]).await.unwrap();
+
+    let cancel = instruction::cancel_game(&player.pubkey(), &player.pubkey(), &game, &[], None);
+    send(&mut context, &[cancel], &[&player]).await.unwrap();
+
+    assert_eq!(decode_game(&data(&mut context, game).await).unwrap().state, GameState::Canceled);
+    // The owner wallet cannot sign here, so only the account derivation is exercised
+    assert!(send(&mut context, &[instruction::withdraw_fees(&player.pubkey(), 1)], &[&player]).await.is_err());
+}
+
+#[tokio::test]
+async fn runs_a_league() {
+    let mut context = start().await;
+    let authority = funded_wallet(&mut context).await;
+    let players = [funded_wallet(&mut context).await, funded_wallet(&mut context).await];
+    let league = pda::league_address(&authority.pubkey(), 7);
+
+    send(&mut context, &[instruction::create_league(&authority.pubkey(), 7, LAMPORTS_PER_SOL, vec![100])], &[&authority])
+        .await
+        .unwrap();
+    for player in &players {
+        send(&mut context, &[instruction::join_league(&player.pubkey(), &league)], &[player]).await.unwrap();
+    }
+    send(&mut context, &[instruction::start_league(&authority.pubkey(), &league)], &[&authority]).await.unwrap();
+
+    let fixture = decode::<League>(&data(&mut context, league).await).unwrap().fixtures[0].clone();
+    let player_one = &players[fixture.player_one as usize];
+    let player_two = &players[fixture.player_two as usize];
+    for player in [player_one, player_two] {
+        send(&mut context, &[instruction::setup_league_game(&player.pubkey(), &league, 0)], &[player]).await.unwrap();
+    }
+    let game = pda::league_game_address(&league, 0);
+    let resign = instruction::cancel_game(&player_two.pubkey(), &player_one.pubkey(), &game, &[], None);
+    send(&mut context, &[resign], &[player_two]).await.unwrap();
+
+    send(&mut context, &[instruction::record_league_result(&league, 0)], &[]).await.unwrap();
+    send(&mut context, &[instruction::finalize_league(&league, &[player_one.pubkey(), player_two.pubkey()])], &[])
+        .await
+        .unwrap();
+
+    assert_eq!(decode::<League>(&data(&mut context, league).await).unwrap().state, LeagueState::Finished);
+}
+
+#[tokio::test]
+async fn settles_a_side_bet() {
+    let mut context = start().await;
+    let (game, player_one, player_two) = start_game(&mut context).await;
+    let bettor = funded_wallet(&mut context).await;
+    let payer = context.payer.pubkey();
+
+    send(&mut context, &[instruction::open_side_pool(&payer, &game)], &[]).await.unwrap();
+    let bet = instruction::place_side_bet(&bettor.pubkey(), &game, SideBetOutcome::PlayerTwo, LAMPORTS_PER_SOL);
+    send(&mut context, &[bet], &[&bettor]).await.unwrap();
+    let resign = instruction::cancel_game(&player_one.pubkey(), &player_two.pubkey(), &game, &[], None);
+    send(&mut context, &[resign], &[&player_one]).await.unwrap();
+    send(&mut context, &[instruction::claim_side_bet(&bettor.pubkey(), &game)], &[&bettor]).await.unwrap();
+
+    let side_pool = decode::<SideBetPool>(&data(&mut context, pda::side_pool_address(&game)).await).unwrap();
+    assert_eq!(side_pool.outcome, Some(SideBetOutcome::PlayerTwo));
+    let side_bet = pda::side_bet_address(&pda::side_pool_address(&game), &bettor.pubkey());
+    assert!(context.banks_client.get_account(side_bet).await.unwrap().is_none());
+}
+
+#[test]
+fn decoders_check_the_account_type() {
+    assert!(decode_game(&[0; 16]).is_err());
+}
+
+// The builtin processor prints event data instead of logging it, so the logs are built here.
+#[test]
+fn decodes_events_from_logs() {
+    let closed = GameClosed {
+        game: Pubkey::new_unique(),
+        number: 4,
+        closed_by: Pubkey::new_unique(),
+        lamports: 1_000,
+    };
+    let logs = vec![
+        "Program log: Instruction: CloseGameAccount".to_string(),
+        format!("Program data: {}", STANDARD.encode(closed.data())),
+        "Program data: not base64".to_string(),
+    ];
+
+    let events = decode_events::<GameClosed>(&logs);
+    assert_eq!(events.len(), 1);
+    assert_eq!((events[0].game, events[0].number), (closed.game, 4));
+    assert!(decode_events::<GameFinished>(&logs).is_empty());
+}
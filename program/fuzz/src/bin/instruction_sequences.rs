@@ -0,0 +1,12 @@
+// Honggfuzz target, run it with `cargo hfuzz run instruction_sequences` from the `fuzz` directory.
+use honggfuzz::fuzz;
+use tic_tac_toe_fuzz::{run, FuzzData};
+
+fn main() {
+    let runtime = tokio::runtime::Builder::new_current_thread().enable_all().build().unwrap();
+    loop {
+        fuzz!(|data: FuzzData| {
+            runtime.block_on(run(&data));
+        });
+    }
+}
@@ -0,0 +1,349 @@
+// Fuzzing harness for sequences of the game instructions.
+//
+// Every sequence runs against its own in-process bank with the program loaded natively and a
+// few funded wallets. The instructions pick their signers, rivals and games from those
+// wallets and game accounts, and after every instruction the harness checks that:
+//
+// - No lamports are created or destroyed between the wallets, the global state, the games and
+//   their escrows.
+// - Fees only leave the global state through the owner.
+// - A signer that is not a player of the game never moves funds, and only players of the
+//   game get paid.
+// - `players_mapping` and `games_mapping` match the players of every waiting or in progress game.
+use anchor_lang::{AccountDeserialize, InstructionData, ToAccountMetas};
+use arbitrary::Arbitrary;
+use solana_program_test::{processor, ProgramTest, ProgramTestContext};
+use solana_sdk::{
+    account_info::AccountInfo,
+    entrypoint::ProgramResult,
+    instruction::Instruction,
+    native_token::LAMPORTS_PER_SOL,
+    pubkey::Pubkey,
+    signature::{Keypair, Signer},
+    system_instruction, system_program,
+    transaction::Transaction,
+};
+use tic_tac_toe::{Game, GameState, GlobalState, Tile};
+
+// Number of funded wallets the instructions pick their accounts from.
+pub const WALLETS: usize = 4;
+
+pub const WALLET_FUNDS: u64 = 10 * LAMPORTS_PER_SOL;
+
+// Longer sequences are truncated.
+pub const MAX_INSTRUCTIONS: usize = 32;
+
+// Instruction generated by the fuzzer, indices wrap around the wallets and known games.
+#[derive(Arbitrary, Clone, Debug)]
+pub enum FuzzInstruction {
+    InitializeGlobalState { payer: u8 },
+    SetupGame { player: u8 },
+    Play { game: u8, player: u8, rival: u8, row: u8, column: u8 },
+    CancelGame { game: u8, signer: u8, rival: u8 },
+    CloseGameAccount { game: u8, signer: u8, rent_payer: u8 },
+    WithdrawFees { signer: u8, amount: u64 },
+}
+
+#[derive(Arbitrary, Clone, Debug)]
+pub struct FuzzData {
+    pub instructions: Vec<FuzzInstruction>,
+}
+
+// Anchor's entrypoint ties the accounts to the instruction lifetime, the builtin processor does not.
+// The accounts are copied to a local for every instruction rather than leaked, which would grow
+// the fuzzer's memory with every iteration.
+fn process_instruction(program_id: &Pubkey, accounts: &[AccountInfo], data: &[u8]) -> ProgramResult {
+    let accounts = accounts.to_vec();
+    // SAFETY: the program returns before `accounts` is dropped and keeps no reference to them.
+    let accounts: &[AccountInfo] = unsafe { std::mem::transmute::<&[AccountInfo], &[AccountInfo]>(&accounts) };
+    tic_tac_toe::entry(program_id, accounts, data)
+}
+
+pub fn global_state_address() -> Pubkey {
+    Pubkey::find_program_address(&[b"global_state"], &tic_tac_toe::ID).0
+}
+
+pub fn game_address(number: u64) -> Pubkey {
+    Pubkey::find_program_address(&[b"game", &number.to_le_bytes()], &tic_tac_toe::ID).0
+}
+
+pub fn escrow_address(game: &Pubkey) -> Pubkey {
+    Pubkey::find_program_address(&[b"escrow", game.as_ref()], &tic_tac_toe::ID).0
+}
+
+fn instruction(accounts: impl ToAccountMetas, data: impl InstructionData) -> Instruction {
+    Instruction {
+        program_id: tic_tac_toe::ID,
+        accounts: accounts.to_account_metas(None),
+        data: data.data(),
+    }
+}
+
+// Marks the player as a signer, as `play` and `cancel_game` also accept a session key instead.
+fn signed_by(mut instruction: Instruction, player: &Pubkey) -> Instruction {
+    if let Some(meta) = instruction.accounts.iter_mut().find(|meta| meta.pubkey == *player) {
+        meta.is_signer = true;
+    }
+    instruction
+}
+
+// Runs a generated sequence on a new bank.
+pub async fn run(data: &FuzzData) {
+    let mut harness = Harness::start().await;
+    for fuzz_instruction in data.instructions.iter().take(MAX_INSTRUCTIONS) {
+        harness.execute(fuzz_instruction).await;
+    }
+}
+
+pub struct Harness {
+    pub context: ProgramTestContext,
+    pub wallets: Vec<Keypair>,
+}
+
+impl Harness {
+    // Starts a bank with the program loaded and the wallets funded, without a global state.
+    pub async fn start() -> Self {
+        let program_test = ProgramTest::new("tic_tac_toe", tic_tac_toe::ID, processor!(process_instruction));
+        let context = program_test.start_with_context().await;
+        let mut harness = Harness { context, wallets: Vec::new() };
+
+        let payer = harness.context.payer.pubkey();
+        let wallets: Vec<Keypair> = (0..WALLETS).map(|_| Keypair::new()).collect();
+        let transfers: Vec<Instruction> = wallets
+            .iter()
+            .map(|wallet| system_instruction::transfer(&payer, &wallet.pubkey(), WALLET_FUNDS))
+            .collect();
+        harness.send(&transfers, None).await.unwrap();
+        harness.wallets = wallets;
+        harness
+    }
+
+    fn wallet(&self, index: u8) -> &Keypair {
+        &self.wallets[index as usize % WALLETS]
+    }
+
+    async fn send(&mut self, instructions: &[Instruction], signer: Option<&Keypair>) -> Result<(), solana_program_test::BanksClientError> {
+        let payer = self.context.payer.insecure_clone();
+        let mut signers = vec![&payer];
+        signers.extend(signer);
+        let blockhash = self.context.get_new_latest_blockhash().await.unwrap();
+        let transaction = Transaction::new_signed_with_payer(instructions, Some(&payer.pubkey()), &signers, blockhash);
+        self.context.banks_client.process_transaction(transaction).await
+    }
+
+    async fn fetch<T: AccountDeserialize>(&mut self, address: Pubkey) -> Option<T> {
+        let account = self.context.banks_client.get_account(address).await.unwrap()?;
+        T::try_deserialize(&mut account.data.as_slice()).ok()
+    }
+
+    async fn game_count(&mut self) -> u64 {
+        match self.fetch::<GlobalState>(global_state_address()).await {
+            Some(global_state) => global_state.game_count,
+            None => 0,
+        }
+    }
+
+    // Accounts whose lamports the instructions can move: wallets, global state, games and escrows.
+    async fn tracked_accounts(&mut self) -> Vec<Pubkey> {
+        let mut accounts: Vec<Pubkey> = self.wallets.iter().map(Keypair::pubkey).collect();
+        accounts.push(global_state_address());
+        // The next game may be created by the instruction
+        let games: Vec<Pubkey> = (0..=self.game_count().await + 1).map(game_address).collect();
+        accounts.extend(games.iter().map(escrow_address));
+        accounts.extend(games);
+        accounts
+    }
+
+    async fn balances(&mut self, accounts: &[Pubkey]) -> Vec<u64> {
+        let mut balances = Vec::new();
+        for &account in accounts {
+            balances.push(self.context.banks_client.get_balance(account).await.unwrap());
+        }
+        balances
+    }
+
+    // Builds the instruction, returning it with its signer and the game it targets.
+    async fn build(&mut self, fuzz_instruction: &FuzzInstruction) -> (Instruction, Keypair, Option<Pubkey>) {
+        let global_state = global_state_address();
+        // Picks one of the games created so far or the next one
+        let known_games = self.game_count().await + 1;
+        let game = |index: u8| game_address(index as u64 % known_games);
+
+        match *fuzz_instruction {
+            FuzzInstruction::InitializeGlobalState { payer } => {
+                let payer = self.wallet(payer).insecure_clone();
+                let accounts = tic_tac_toe::accounts::InitializeGlobalState {
+                    global_state,
+                    payer: payer.pubkey(),
+                    system_program: system_program::ID,
+                };
+                (instruction(accounts, tic_tac_toe::instruction::InitializeGlobalState {}), payer, None)
+            }
+            FuzzInstruction::SetupGame { player } => {
+                let player = self.wallet(player).insecure_clone();
+                let game = game_address(known_games - 1);
+                let accounts = tic_tac_toe::accounts::SetupGame {
+                    global_state,
+                    game,
+                    escrow: escrow_address(&game),
+                    player: player.pubkey(),
+                    balance: None,
+                    system_program: system_program::ID,
+                };
+                (instruction(accounts, tic_tac_toe::instruction::SetupGame { hook: None }), player, Some(game))
+            }
+            FuzzInstruction::Play { game: index, player, rival, row, column } => {
+                let player = self.wallet(player).insecure_clone();
+                let accounts = tic_tac_toe::accounts::Play {
+                    global_state,
+                    game: game(index),
+                    escrow: escrow_address(&game(index)),
+                    player: player.pubkey(),
+                    rival: self.wallet(rival).pubkey(),
+                    player_balance: None,
+                    rival_balance: None,
+                    session: None,
+                    session_key: None,
+                };
+                // Out of bounds tiles are still generated, just not too often
+                let tile = Tile { row: row % 4, column: column % 4 };
+                (signed_by(instruction(accounts, tic_tac_toe::instruction::Play { tile }), &player.pubkey()), player, Some(game(index)))
+            }
+            FuzzInstruction::CancelGame { game: index, signer, rival } => {
+                let signer = self.wallet(signer).insecure_clone();
+                let accounts = tic_tac_toe::accounts::CancelGame {
+                    global_state,
+                    game: game(index),
+                    escrow: escrow_address(&game(index)),
+                    signer: signer.pubkey(),
+                    rival: self.wallet(rival).pubkey(),
+                    signer_balance: None,
+                    rival_balance: None,
+                    session: None,
+                    session_key: None,
+                };
+                (signed_by(instruction(accounts, tic_tac_toe::instruction::CancelGame {}), &signer.pubkey()), signer, Some(game(index)))
+            }
+            FuzzInstruction::CloseGameAccount { game: index, signer, rent_payer } => {
+                let signer = self.wallet(signer).insecure_clone();
+                let accounts = tic_tac_toe::accounts::CloseGameAccount {
+                    global_state,
+                    game: game(index),
+                    escrow: escrow_address(&game(index)),
+                    signer: signer.pubkey(),
+                    rent_payer: self.wallet(rent_payer).pubkey(),
+                    side_pool: Pubkey::find_program_address(&[b"side_pool", game(index).as_ref()], &tic_tac_toe::ID).0,
+                    league: None,
+                };
+                (instruction(accounts, tic_tac_toe::instruction::CloseGameAccount {}), signer, Some(game(index)))
+            }
+            FuzzInstruction::WithdrawFees { signer, amount } => {
+                let signer = self.wallet(signer).insecure_clone();
+                let accounts = tic_tac_toe::accounts::WithdrawFees {
+                    global_state,
+                    owner: signer.pubkey(),
+                    system_program: system_program::ID,
+                };
+                (instruction(accounts, tic_tac_toe::instruction::WithdrawFees { amount }), signer, None)
+            }
+        }
+    }
+
+    // Executes one instruction and checks the invariants.
+    pub async fn execute(&mut self, fuzz_instruction: &FuzzInstruction) {
+        let (instruction, signer, game) = self.build(fuzz_instruction).await;
+        let accounts = self.tracked_accounts().await;
+        let balances_before = self.balances(&accounts).await;
+        let game_before = match game {
+            Some(game) => self.fetch::<Game>(game).await,
+            None => None,
+        };
+
+        let result = self.send(&[instruction], Some(&signer)).await;
+
+        let balances_after = self.balances(&accounts).await;
+        if result.is_err() {
+            assert_eq!(balances_after, balances_before, "{:?} failed but moved lamports", fuzz_instruction);
+        } else {
+            let game_after = match game {
+                Some(game) => self.fetch::<Game>(game).await,
+                None => None,
+            };
+            let players: Vec<Pubkey> = [game_before, game_after]
+                .iter()
+                .flatten()
+                .flat_map(|game| game.players.iter().flatten().copied())
+                .collect();
+            self.check_funds(fuzz_instruction, &signer.pubkey(), &players, &accounts, &balances_before, &balances_after)
+                .await;
+        }
+        self.check_mappings().await;
+    }
+
+    async fn check_funds(&mut self, fuzz_instruction: &FuzzInstruction, signer: &Pubkey, players: &[Pubkey], accounts: &[Pubkey], before: &[u64], after: &[u64]) {
+        assert_eq!(
+            after.iter().map(|&lamports| lamports as u128).sum::<u128>(),
+            before.iter().map(|&lamports| lamports as u128).sum::<u128>(),
+            "{:?} created or destroyed lamports",
+            fuzz_instruction
+        );
+
+        let global_state = global_state_address();
+        for ((account, &before), &after) in accounts.iter().zip(before).zip(after) {
+            if *account == global_state && after < before {
+                let owner = self.fetch::<GlobalState>(global_state).await.map(|global_state| global_state.owner);
+                assert_eq!(owner, Some(*signer), "{:?} withdrew fees without the owner", fuzz_instruction);
+            }
+            let is_wallet = self.wallets.iter().any(|wallet| wallet.pubkey() == *account);
+            if is_wallet && after > before {
+                assert!(players.contains(account), "{:?} paid {} to a non-player", fuzz_instruction, account);
+            }
+        }
+
+        let moved_funds = before != after;
+        let initializes = matches!(fuzz_instruction, FuzzInstruction::InitializeGlobalState { .. });
+        if moved_funds && !initializes {
+            assert!(players.contains(signer), "non-player {} moved funds with {:?}", signer, fuzz_instruction);
+        }
+    }
+
+    // Checks that the mapping holds exactly the players of the waiting and in progress games.
+    async fn check_mappings(&mut self) {
+        let Some(global_state) = self.fetch::<GlobalState>(global_state_address()).await else {
+            return;
+        };
+        assert_eq!(global_state.players_mapping.len(), global_state.games_mapping.len());
+
+        for (index, (player, game)) in global_state.players_mapping.iter().zip(&global_state.games_mapping).enumerate() {
+            assert!(!global_state.players_mapping[..index].contains(player), "{} is mapped twice", player);
+            let game_account = self.fetch::<Game>(*game).await.unwrap_or_else(|| panic!("mapped game {} does not exist", game));
+            assert!(
+                matches!(game_account.state, GameState::Waiting | GameState::InProgress),
+                "{} is mapped to game {} in state {:?}",
+                player,
+                game,
+                game_account.state
+            );
+            assert!(game_account.players.contains(&Some(*player)), "{} is mapped to game {} without playing it", player, game);
+        }
+
+        for number in 0..=global_state.game_count {
+            let game = game_address(number);
+            let Some(game_account) = self.fetch::<Game>(game).await else {
+                continue;
+            };
+            if matches!(game_account.state, GameState::Waiting | GameState::InProgress) {
+                for player in game_account.players.iter().flatten() {
+                    let index = global_state.players_mapping.iter().position(|mapped| mapped == player);
+                    assert_eq!(
+                        index.map(|index| global_state.games_mapping[index]),
+                        Some(game),
+                        "{} plays game {} but is not mapped to it",
+                        player,
+                        game
+                    );
+                }
+            }
+        }
+    }
+}
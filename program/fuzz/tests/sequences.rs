@@ -0,0 +1,45 @@
+// Replays a few generated and hand-written sequences, so the harness runs without honggfuzz.
+use arbitrary::{Arbitrary, Unstructured};
+use rand::{rngs::StdRng, RngCore, SeedableRng};
+use tic_tac_toe_fuzz::{run, FuzzData, FuzzInstruction, Harness};
+
+#[tokio::test]
+async fn random_sequences_keep_the_invariants() {
+    for seed in 0..8 {
+        let mut bytes = vec![0; 1024];
+        StdRng::seed_from_u64(seed).fill_bytes(&mut bytes);
+        let data = FuzzData::arbitrary(&mut Unstructured::new(&bytes)).unwrap();
+        run(&data).await;
+    }
+}
+
+#[tokio::test]
+async fn full_games_keep_the_invariants() {
+    let mut harness = Harness::start().await;
+    let play = |game, player, row, column| FuzzInstruction::Play { game, player, rival: 1 - player, row, column };
+    let sequence = [
+        FuzzInstruction::InitializeGlobalState { payer: 0 },
+        FuzzInstruction::SetupGame { player: 0 },
+        FuzzInstruction::SetupGame { player: 1 },
+        // A third wallet tries to resign and to steal the pot
+        FuzzInstruction::CancelGame { game: 1, signer: 2, rival: 2 },
+        FuzzInstruction::CancelGame { game: 1, signer: 0, rival: 2 },
+        play(1, 0, 0, 0),
+        play(1, 1, 1, 0),
+        play(1, 0, 0, 1),
+        play(1, 1, 1, 1),
+        play(1, 0, 0, 2),
+        FuzzInstruction::WithdrawFees { signer: 0, amount: 1 },
+        FuzzInstruction::CloseGameAccount { game: 1, signer: 1, rent_payer: 1 },
+        // The escrow rent only goes back to the wallet that funded it
+        FuzzInstruction::CloseGameAccount { game: 1, signer: 0, rent_payer: 1 },
+        FuzzInstruction::CloseGameAccount { game: 1, signer: 0, rent_payer: 0 },
+        // Waiting game canceled by its player
+        FuzzInstruction::SetupGame { player: 2 },
+        FuzzInstruction::CancelGame { game: 2, signer: 3, rival: 2 },
+        FuzzInstruction::CancelGame { game: 2, signer: 2, rival: 3 },
+    ];
+    for fuzz_instruction in &sequence {
+        harness.execute(fuzz_instruction).await;
+    }
+}
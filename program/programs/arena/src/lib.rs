@@ -0,0 +1,180 @@
+// Example program composing tic-tac-toe through CPI: it hosts matches between two users and
+// records their winners, as a tournament or quest program would. It is also the hook of its
+// games, so the winner is recorded once anyone calls the hook of an ended game. See
+// `tic_tac_toe::composer`.
+use anchor_lang::prelude::*;
+use anchor_lang::system_program;
+use tic_tac_toe::cpi::accounts::{CreateHostedGame, SettleHostedGame};
+use tic_tac_toe::program::TicTacToe;
+use tic_tac_toe::{composer, Escrow, Game, GameResult, GameState};
+
+// Declare the program ID.
+declare_id!("6sZtDm6MrysGAHwDSm6WqsYxhRBvVuEQVh8Fw7Qiov9A");
+
+pub const HOST_SEED: &[u8] = b"host";
+pub const MATCH_SEED: &[u8] = b"match";
+
+#[program]
+pub mod arena {
+
+    use super::*;
+
+    // Starts a tic-tac-toe game between the players, who both sign to enter the match, calling
+    // this program when it ends. The host PDA signs the CPI and pays the rent, topped up by the
+    // organizer; the rent of settled games is reused by the next matches. The arena must be an
+    // allowed hook of tic-tac-toe.
+    pub fn create_match(ctx: Context<CreateMatch>, id: u64) -> Result<()> {
+        let rent = Rent::get()?;
+        let needed = rent.minimum_balance(Game::MAXIMUM_SIZE) + rent.minimum_balance(Escrow::MAXIMUM_SIZE);
+        let shortfall = needed.saturating_sub(ctx.accounts.host.lamports());
+        if shortfall > 0 {
+            let accounts = system_program::Transfer {
+                from: ctx.accounts.organizer.to_account_info(),
+                to: ctx.accounts.host.to_account_info(),
+            };
+            system_program::transfer(CpiContext::new(ctx.accounts.system_program.to_account_info(), accounts), shortfall)?;
+        }
+
+        let accounts = CreateHostedGame {
+            global_state: ctx.accounts.global_state.to_account_info(),
+            game: ctx.accounts.game.to_account_info(),
+            escrow: ctx.accounts.escrow.to_account_info(),
+            host: ctx.accounts.host.to_account_info(),
+            player_one: ctx.accounts.player_one.to_account_info(),
+            player_two: ctx.accounts.player_two.to_account_info(),
+            system_program: ctx.accounts.system_program.to_account_info(),
+        };
+        let seeds: &[&[&[u8]]] = &[&[HOST_SEED, &[ctx.bumps.host]]];
+        let program = ctx.accounts.tic_tac_toe_program.to_account_info();
+        tic_tac_toe::cpi::create_hosted_game(CpiContext::new_with_signer(program, accounts, seeds), id, Some(crate::ID))?;
+
+        let arena_match = &mut ctx.accounts.arena_match;
+        arena_match.version = ArenaMatch::VERSION;
+        arena_match.id = id;
+        arena_match.organizer = ctx.accounts.organizer.key();
+        arena_match.game = ctx.accounts.game.key();
+        arena_match.players = [ctx.accounts.player_one.key(), ctx.accounts.player_two.key()];
+        arena_match.winner = None;
+        arena_match.settled = false;
+        msg!("Match {} started in game {}.", id, arena_match.game);
+        Ok(())
+    }
+
+    // Hook called by tic-tac-toe when the game of a match ends, signed by the hook authority of
+    // this program.
+    pub fn on_game_end(ctx: Context<OnGameEnd>, result: GameResult) -> Result<()> {
+        // The game account is the source of truth, the result only locates the match
+        let arena_match = &mut ctx.accounts.arena_match;
+        arena_match.winner = match ctx.accounts.game.state {
+            GameState::Won { winner } => Some(winner),
+            _ => None,
+        };
+        msg!("Match {} ended, winner {:?}.", result.number, arena_match.winner);
+        Ok(())
+    }
+
+    // Permissionless: settles the finished game of the match and records its winner.
+    pub fn settle_match(ctx: Context<SettleMatch>) -> Result<()> {
+        let id = ctx.accounts.arena_match.id;
+        require!(!ctx.accounts.arena_match.settled, ErrorCode::MatchAlreadySettled);
+
+        let accounts = SettleHostedGame {
+            global_state: ctx.accounts.global_state.to_account_info(),
+            game: ctx.accounts.game.to_account_info(),
+            escrow: ctx.accounts.escrow.to_account_info(),
+            host: ctx.accounts.host.to_account_info(),
+            side_pool: ctx.accounts.side_pool.to_account_info(),
+        };
+        let seeds: &[&[&[u8]]] = &[&[HOST_SEED, &[ctx.bumps.host]]];
+        let program = ctx.accounts.tic_tac_toe_program.to_account_info();
+        let result = tic_tac_toe::cpi::settle_hosted_game(CpiContext::new_with_signer(program, accounts, seeds), id)?.get();
+
+        let arena_match = &mut ctx.accounts.arena_match;
+        arena_match.winner = match result.state {
+            GameState::Won { winner } => Some(winner),
+            _ => None,
+        };
+        arena_match.settled = true;
+        msg!("Match {} settled, winner {:?}.", id, arena_match.winner);
+        Ok(())
+    }
+}
+
+// Structure representing a match hosted by the arena.
+#[account]
+pub struct ArenaMatch {
+    pub version: u8,                // Layout version
+    pub id: u64,                    // Match id, also the id of the hosted game
+    pub organizer: Pubkey,          // Paid the match account rent
+    pub game: Pubkey,               // Hosted tic-tac-toe game
+    pub players: [Pubkey; 2],       // Players, X first
+    pub winner: Option<Pubkey>,     // Winner, None for a tie or a canceled game
+    pub settled: bool,              // The game result is recorded
+}
+
+impl ArenaMatch {
+    pub const VERSION: u8 = 1;
+    // Size of the match account.
+    pub const MAXIMUM_SIZE: usize = 8 + 1 + 8 + 32 + 32 + 64 + (32 + 1) + 1;
+}
+
+#[derive(Accounts)]
+#[instruction(id: u64)]
+pub struct CreateMatch<'info> {
+    #[account(init, payer = organizer, space = ArenaMatch::MAXIMUM_SIZE, seeds = [MATCH_SEED, &id.to_le_bytes()], bump)]
+    pub arena_match: Account<'info, ArenaMatch>,
+    #[account(mut, seeds = [HOST_SEED], bump)]
+    pub host: SystemAccount<'info>,                     // Hosts the games and pays their rent.
+    /// CHECK: Validated by tic-tac-toe.
+    #[account(mut)]
+    pub global_state: UncheckedAccount<'info>,
+    /// CHECK: Created by tic-tac-toe.
+    #[account(mut)]
+    pub game: UncheckedAccount<'info>,
+    /// CHECK: Created by tic-tac-toe.
+    #[account(mut)]
+    pub escrow: UncheckedAccount<'info>,
+    #[account(mut)]
+    pub organizer: Signer<'info>,
+    pub player_one: Signer<'info>,
+    pub player_two: Signer<'info>,
+    pub tic_tac_toe_program: Program<'info, TicTacToe>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct SettleMatch<'info> {
+    #[account(mut, seeds = [MATCH_SEED, &arena_match.id.to_le_bytes()], bump)]
+    pub arena_match: Account<'info, ArenaMatch>,
+    #[account(mut, seeds = [HOST_SEED], bump)]
+    pub host: SystemAccount<'info>,
+    /// CHECK: Validated by tic-tac-toe.
+    #[account(mut)]
+    pub global_state: UncheckedAccount<'info>,
+    /// CHECK: Closed by tic-tac-toe.
+    #[account(mut, address = arena_match.game)]
+    pub game: UncheckedAccount<'info>,
+    /// CHECK: Closed by tic-tac-toe.
+    #[account(mut)]
+    pub escrow: UncheckedAccount<'info>,
+    /// CHECK: Settled by tic-tac-toe if the game has side bets.
+    #[account(mut)]
+    pub side_pool: UncheckedAccount<'info>,
+    pub tic_tac_toe_program: Program<'info, TicTacToe>,
+}
+
+#[derive(Accounts)]
+#[instruction(result: GameResult)]
+pub struct OnGameEnd<'info> {
+    pub game: Account<'info, Game>,
+    #[account(address = composer::hook_authority_address(&crate::ID))]
+    pub hook_authority: Signer<'info>,                  // Only tic-tac-toe can sign for it.
+    #[account(mut, seeds = [MATCH_SEED, &result.number.to_le_bytes()], bump, has_one = game)]
+    pub arena_match: Account<'info, ArenaMatch>,
+}
+
+#[error_code]
+pub enum ErrorCode {
+    #[msg("The match is already settled.")]
+    MatchAlreadySettled,
+}
@@ -0,0 +1,238 @@
+use arena::ArenaMatch;
+use anchor_lang::{AccountDeserialize, AccountSerialize, InstructionData, ToAccountMetas};
+use solana_program_test::{processor, BanksClientError, ProgramTest, ProgramTestContext};
+use solana_sdk::{
+    account_info::AccountInfo,
+    entrypoint::ProgramResult,
+    instruction::{AccountMeta, Instruction, InstructionError},
+    native_token::LAMPORTS_PER_SOL,
+    pubkey::Pubkey,
+    signature::{Keypair, Signer},
+    system_instruction, system_program,
+    transaction::{Transaction, TransactionError},
+};
+use tic_tac_toe::{composer, GlobalState, Tile};
+
+// Anchor's entrypoints tie the accounts to the instruction lifetime, the builtin processor does not.
+fn process_arena(program_id: &Pubkey, accounts: &[AccountInfo], data: &[u8]) -> ProgramResult {
+    let accounts = Box::leak(Box::new(accounts.to_vec()));
+    arena::entry(program_id, accounts, data)
+}
+
+fn process_tic_tac_toe(program_id: &Pubkey, accounts: &[AccountInfo], data: &[u8]) -> ProgramResult {
+    let accounts = Box::leak(Box::new(accounts.to_vec()));
+    tic_tac_toe::entry(program_id, accounts, data)
+}
+
+fn host_address() -> Pubkey {
+    Pubkey::find_program_address(&[arena::HOST_SEED], &arena::ID).0
+}
+
+fn match_address(id: u64) -> Pubkey {
+    Pubkey::find_program_address(&[arena::MATCH_SEED, &id.to_le_bytes()], &arena::ID).0
+}
+
+async fn process(context: &mut ProgramTestContext, instruction: Instruction, signers: &[&Keypair]) -> Result<(), BanksClientError> {
+    let payer = context.payer.insecure_clone();
+    let mut all_signers = vec![&payer];
+    all_signers.extend_from_slice(signers);
+    let blockhash = context.get_new_latest_blockhash().await.unwrap();
+    let transaction = Transaction::new_signed_with_payer(&[instruction], Some(&payer.pubkey()), &all_signers, blockhash);
+    context.banks_client.process_transaction(transaction).await
+}
+
+async fn fetch<T: AccountDeserialize>(context: &mut ProgramTestContext, address: Pubkey) -> Option<T> {
+    let account = context.banks_client.get_account(address).await.unwrap()?;
+    Some(T::try_deserialize(&mut account.data.as_slice()).unwrap())
+}
+
+// Starts a bank with both programs and the tic-tac-toe global state, with the arena as an allowed hook.
+async fn start() -> ProgramTestContext {
+    let mut program_test = ProgramTest::new("arena", arena::ID, processor!(process_arena));
+    program_test.add_program("tic_tac_toe", tic_tac_toe::ID, processor!(process_tic_tac_toe));
+    let mut context = program_test.start_with_context().await;
+
+    let accounts = tic_tac_toe::accounts::InitializeGlobalState {
+        global_state: composer::global_state_address(),
+        payer: context.payer.pubkey(),
+        system_program: system_program::ID,
+    };
+    let initialize = Instruction {
+        program_id: tic_tac_toe::ID,
+        accounts: accounts.to_account_metas(None),
+        data: tic_tac_toe::instruction::InitializeGlobalState {}.data(),
+    };
+    process(&mut context, initialize, &[]).await.unwrap();
+
+    let mut account = context.banks_client.get_account(composer::global_state_address()).await.unwrap().unwrap();
+    let mut global_state = GlobalState::try_deserialize(&mut account.data.as_slice()).unwrap();
+    global_state.hooks.push(arena::ID);
+    global_state.try_serialize(&mut account.data.as_mut_slice()).unwrap();
+    context.set_account(&composer::global_state_address(), &account.into());
+    context
+}
+
+async fn create_player(context: &mut ProgramTestContext) -> Keypair {
+    let player = Keypair::new();
+    let transfer = system_instruction::transfer(&context.payer.pubkey(), &player.pubkey(), LAMPORTS_PER_SOL);
+    process(context, transfer, &[]).await.unwrap();
+    player
+}
+
+async fn create_match(context: &mut ProgramTestContext, organizer: &Keypair, id: u64, players: [&Keypair; 2]) -> Result<(), BanksClientError> {
+    let game = composer::hosted_game_address(&host_address(), id);
+    let accounts = arena::accounts::CreateMatch {
+        arena_match: match_address(id),
+        host: host_address(),
+        global_state: composer::global_state_address(),
+        game,
+        escrow: composer::escrow_address(&game),
+        organizer: organizer.pubkey(),
+        player_one: players[0].pubkey(),
+        player_two: players[1].pubkey(),
+        tic_tac_toe_program: tic_tac_toe::ID,
+        system_program: system_program::ID,
+    };
+    let instruction = Instruction {
+        program_id: arena::ID,
+        accounts: accounts.to_account_metas(None),
+        data: arena::instruction::CreateMatch { id }.data(),
+    };
+    process(context, instruction, &[organizer, players[0], players[1]]).await
+}
+
+async fn settle_match(context: &mut ProgramTestContext, id: u64) -> Result<(), BanksClientError> {
+    let game = composer::hosted_game_address(&host_address(), id);
+    let accounts = arena::accounts::SettleMatch {
+        arena_match: match_address(id),
+        host: host_address(),
+        global_state: composer::global_state_address(),
+        game,
+        escrow: composer::escrow_address(&game),
+        side_pool: composer::side_pool_address(&game),
+        tic_tac_toe_program: tic_tac_toe::ID,
+    };
+    let instruction = Instruction {
+        program_id: arena::ID,
+        accounts: accounts.to_account_metas(None),
+        data: arena::instruction::SettleMatch {}.data(),
+    };
+    process(context, instruction, &[]).await
+}
+
+// Plays a tile of the match.
+async fn play(context: &mut ProgramTestContext, id: u64, player: &Keypair, rival: Pubkey, (row, column): (u8, u8)) {
+    let game = composer::hosted_game_address(&host_address(), id);
+    let accounts = tic_tac_toe::accounts::Play {
+        global_state: composer::global_state_address(),
+        game,
+        escrow: composer::escrow_address(&game),
+        player: player.pubkey(),
+        rival,
+        player_balance: None,
+        rival_balance: None,
+        session: None,
+        session_key: None,
+    };
+    let mut instruction = Instruction {
+        program_id: tic_tac_toe::ID,
+        accounts: accounts.to_account_metas(None),
+        data: tic_tac_toe::instruction::Play { tile: Tile { row, column } }.data(),
+    };
+    // The player signs, as `play` also accepts a session key instead
+    instruction.accounts[3].is_signer = true;
+    process(context, instruction, &[player]).await.unwrap();
+}
+
+// Calls the arena hook of the ended game of the match, as anyone can.
+async fn call_hook(context: &mut ProgramTestContext, id: u64) -> Result<(), BanksClientError> {
+    let game = composer::hosted_game_address(&host_address(), id);
+    let accounts = tic_tac_toe::accounts::CallGameHook {
+        global_state: composer::global_state_address(),
+        game,
+    };
+    let mut instruction = Instruction {
+        program_id: tic_tac_toe::ID,
+        accounts: accounts.to_account_metas(None),
+        data: tic_tac_toe::instruction::CallGameHook {}.data(),
+    };
+    instruction.accounts.extend([
+        AccountMeta::new_readonly(arena::ID, false),
+        AccountMeta::new_readonly(composer::hook_authority_address(&arena::ID), false),
+        AccountMeta::new(match_address(id), false),
+    ]);
+    process(context, instruction, &[]).await
+}
+
+// Plays a game won by `winner` on the top row.
+async fn play_to_win(context: &mut ProgramTestContext, id: u64, winner: &Keypair, loser: &Keypair) {
+    for (index, tile) in [(0, 0), (1, 0), (0, 1), (1, 1), (0, 2)].into_iter().enumerate() {
+        let (player, rival) = if index % 2 == 0 { (winner, loser) } else { (loser, winner) };
+        play(context, id, player, rival.pubkey(), tile).await;
+    }
+}
+
+#[tokio::test]
+async fn arena_hosts_a_match_and_records_the_winner() {
+    let mut context = start().await;
+    let organizer = create_player(&mut context).await;
+    let player_one = create_player(&mut context).await;
+    let player_two = create_player(&mut context).await;
+
+    create_match(&mut context, &organizer, 1, [&player_one, &player_two]).await.unwrap();
+    let arena_match = fetch::<ArenaMatch>(&mut context, match_address(1)).await.unwrap();
+    let game = arena_match.game;
+    assert_eq!(game, composer::hosted_game_address(&host_address(), 1));
+    assert_eq!(context.banks_client.get_balance(host_address()).await.unwrap(), 0);
+
+    play_to_win(&mut context, 1, &player_one, &player_two).await;
+    let arena_match = fetch::<ArenaMatch>(&mut context, match_address(1)).await.unwrap();
+    assert_eq!(arena_match.winner, None);
+    // The hook records the winner once called after the game ends
+    call_hook(&mut context, 1).await.unwrap();
+    let arena_match = fetch::<ArenaMatch>(&mut context, match_address(1)).await.unwrap();
+    assert_eq!((arena_match.winner, arena_match.settled), (Some(player_one.pubkey()), false));
+    settle_match(&mut context, 1).await.unwrap();
+
+    let arena_match = fetch::<ArenaMatch>(&mut context, match_address(1)).await.unwrap();
+    assert!(arena_match.settled);
+    assert_eq!(arena_match.winner, Some(player_one.pubkey()));
+    assert!(context.banks_client.get_account(game).await.unwrap().is_none());
+    // The host keeps the rent for the next match
+    assert!(context.banks_client.get_balance(host_address()).await.unwrap() > 0);
+}
+
+#[tokio::test]
+async fn next_match_reuses_the_host_rent() {
+    let mut context = start().await;
+    let organizer = create_player(&mut context).await;
+    let player_one = create_player(&mut context).await;
+    let player_two = create_player(&mut context).await;
+    create_match(&mut context, &organizer, 1, [&player_one, &player_two]).await.unwrap();
+    play_to_win(&mut context, 1, &player_one, &player_two).await;
+    settle_match(&mut context, 1).await.unwrap();
+    let organizer_before = context.banks_client.get_balance(organizer.pubkey()).await.unwrap();
+
+    create_match(&mut context, &organizer, 2, [&player_two, &player_one]).await.unwrap();
+
+    // Only the match account rent is paid by the organizer
+    let rent = context.banks_client.get_rent().await.unwrap().minimum_balance(ArenaMatch::MAXIMUM_SIZE);
+    assert_eq!(context.banks_client.get_balance(organizer.pubkey()).await.unwrap(), organizer_before - rent);
+}
+
+#[tokio::test]
+async fn unfinished_or_settled_matches_cannot_be_settled() {
+    let mut context = start().await;
+    let organizer = create_player(&mut context).await;
+    let player_one = create_player(&mut context).await;
+    let player_two = create_player(&mut context).await;
+    create_match(&mut context, &organizer, 1, [&player_one, &player_two]).await.unwrap();
+
+    let error = settle_match(&mut context, 1).await.unwrap_err().unwrap();
+    assert_eq!(error, TransactionError::InstructionError(0, InstructionError::Custom(u32::from(tic_tac_toe::ErrorCode::GameNotFinished))));
+
+    play_to_win(&mut context, 1, &player_one, &player_two).await;
+    settle_match(&mut context, 1).await.unwrap();
+    let error = settle_match(&mut context, 1).await.unwrap_err().unwrap();
+    assert_eq!(error, TransactionError::InstructionError(0, InstructionError::Custom(u32::from(arena::ErrorCode::MatchAlreadySettled))));
+}
@@ -0,0 +1,55 @@
+// Interface for programs that compose tic-tac-toe games through CPI, e.g. tournaments or quests.
+//
+// With the `cpi` feature, `tic_tac_toe::cpi` has a function for every instruction and
+// `tic_tac_toe::cpi::accounts` its accounts. The instructions meant for composing programs are:
+//
+// - `create_hosted_game(id, hook)`: starts a game between two users, signed by the host, a PDA of
+//   the composing program, and by both players, who take a slot in the players mapping. The host
+//   pays the rent of the game and its escrow, so it must be a PDA without data, owned by the
+//   system program, holding enough lamports. Hosted games carry no wager; the host handles any
+//   entry fee or prize.
+// - `game_result()`: returns the `GameResult` of any game as return data.
+// - `settle_hosted_game(id)`: returns the `GameResult` of a finished hosted game and closes it,
+//   sending the rent back to the host, after settling the side bets placed on it. Players cannot
+//   close hosted games themselves.
+//
+// The users play hosted games with `play` and `cancel_game` as usual, including through session
+// keys and relayers. Resigning costs nothing, so users can always leave a game they were put in.
+//
+// Games can also call back a hook program allowed by the owner (`set_hook`) when they end, given
+// as `hook` to `setup_game` or `create_hosted_game`. Once the game is paid out, anyone calls the
+// hook with the permissionless `call_game_hook`, in its own transaction so a failing hook never
+// reverts the move ending the game. The hook gets an `on_game_end(result: GameResult)` Anchor
+// instruction with the game, its hook authority as signer and the accounts passed after the hook
+// program and the hook authority. Each hook program has its own hook authority, so a hook can
+// only be signed for by tic-tac-toe calling it. Player one cannot close the game until the hook
+// is called, but hooks must not rely on being called: the crank closes the game after its grace
+// period either way.
+//
+// The functions below derive the accounts the instructions need.
+use anchor_lang::prelude::Pubkey;
+
+use crate::{ESCROW_SEED, GAME_SEED, GLOBAL_STATE_SEED, HOOK_AUTHORITY_SEED, SIDE_POOL_SEED};
+
+pub fn global_state_address() -> Pubkey {
+    Pubkey::find_program_address(&[GLOBAL_STATE_SEED], &crate::ID).0
+}
+
+// Game `id` of `host`.
+pub fn hosted_game_address(host: &Pubkey, id: u64) -> Pubkey {
+    Pubkey::find_program_address(&[GAME_SEED, host.as_ref(), &id.to_le_bytes()], &crate::ID).0
+}
+
+pub fn escrow_address(game: &Pubkey) -> Pubkey {
+    Pubkey::find_program_address(&[ESCROW_SEED, game.as_ref()], &crate::ID).0
+}
+
+// Side bet pool of `game`, which may not exist.
+pub fn side_pool_address(game: &Pubkey) -> Pubkey {
+    Pubkey::find_program_address(&[SIDE_POOL_SEED, game.as_ref()], &crate::ID).0
+}
+
+// Signer of the calls to the game hook `hook`.
+pub fn hook_authority_address(hook: &Pubkey) -> Pubkey {
+    Pubkey::find_program_address(&[HOOK_AUTHORITY_SEED, hook.as_ref()], &crate::ID).0
+}
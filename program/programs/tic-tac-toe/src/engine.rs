@@ -0,0 +1,189 @@
+// Tic-tac-toe rules with no Solana dependencies, shared by the program and off-chain clients.
+
+// Number of rows and columns of the board.
+pub const BOARD_SIZE: usize = 3;
+
+// Rows, columns and diagonals that win the game.
+pub const LINES: [[(usize, usize); 3]; 8] = [
+    [(0, 0), (0, 1), (0, 2)],
+    [(1, 0), (1, 1), (1, 2)],
+    [(2, 0), (2, 1), (2, 2)],
+    [(0, 0), (1, 0), (2, 0)],
+    [(0, 1), (1, 1), (2, 1)],
+    [(0, 2), (1, 2), (2, 2)],
+    [(0, 0), (1, 1), (2, 2)],
+    [(0, 2), (1, 1), (2, 0)],
+];
+
+// Mark placed by each player, X always moves first.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum Mark {
+    X,
+    O,
+}
+
+impl Mark {
+    // Mark of the player at `index` (0 or 1) in the game.
+    pub fn from_player_index(index: usize) -> Option<Mark> {
+        match index {
+            0 => Some(Mark::X),
+            1 => Some(Mark::O),
+            _ => None,
+        }
+    }
+
+    pub fn player_index(self) -> usize {
+        match self {
+            Mark::X => 0,
+            Mark::O => 1,
+        }
+    }
+
+    pub fn opponent(self) -> Mark {
+        match self {
+            Mark::X => Mark::O,
+            Mark::O => Mark::X,
+        }
+    }
+}
+
+// Board cells indexed by row and column.
+pub type Board = [[Option<Mark>; BOARD_SIZE]; BOARD_SIZE];
+
+// Position of a cell on the board.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct Position {
+    pub row: u8,
+    pub column: u8,
+}
+
+impl Position {
+    pub fn new(row: u8, column: u8) -> Self {
+        Position { row, column }
+    }
+
+    pub fn is_on_board(&self) -> bool {
+        (self.row as usize) < BOARD_SIZE && (self.column as usize) < BOARD_SIZE
+    }
+}
+
+// State of the board after a move.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum Outcome {
+    InProgress,
+    Won(Mark),
+    Tie,
+}
+
+// Reasons a move is rejected.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum MoveError {
+    GameOver,
+    NotYourTurn,
+    OutOfBounds,
+    Occupied,
+}
+
+// Returns the mark that moves next, based on the marks already on the board.
+// `is_multiple_of` is newer than the Solana toolchain.
+#[allow(clippy::manual_is_multiple_of)]
+pub fn next_mark(board: &Board) -> Mark {
+    let marks = board.iter().flatten().flatten().count();
+    if marks % 2 == 0 { Mark::X } else { Mark::O }
+}
+
+// Places `mark` on `position` and returns the resulting outcome.
+pub fn apply_move(board: &mut Board, mark: Mark, position: Position) -> Result<Outcome, MoveError> {
+    if outcome(board) != Outcome::InProgress {
+        return Err(MoveError::GameOver);
+    }
+    if mark != next_mark(board) {
+        return Err(MoveError::NotYourTurn);
+    }
+    if !position.is_on_board() {
+        return Err(MoveError::OutOfBounds);
+    }
+    let cell = &mut board[position.row as usize][position.column as usize];
+    if cell.is_some() {
+        return Err(MoveError::Occupied);
+    }
+    *cell = Some(mark);
+    Ok(outcome(board))
+}
+
+// Returns the empty positions, in row order, or none once the game is over.
+pub fn legal_moves(board: &Board) -> Vec<Position> {
+    if outcome(board) != Outcome::InProgress {
+        return Vec::new();
+    }
+    (0..BOARD_SIZE as u8)
+        .flat_map(|row| (0..BOARD_SIZE as u8).map(move |column| Position::new(row, column)))
+        .filter(|position| board[position.row as usize][position.column as usize].is_none())
+        .collect()
+}
+
+// Returns the winner if a line is complete, a tie if the board is full, or in progress otherwise.
+pub fn outcome(board: &Board) -> Outcome {
+    for line in LINES {
+        let [first, second, third] = line.map(|(row, column)| board[row][column]);
+        if let Some(mark) = first {
+            if second == first && third == first {
+                return Outcome::Won(mark);
+            }
+        }
+    }
+    if board.iter().flatten().all(Option::is_some) {
+        Outcome::Tie
+    } else {
+        Outcome::InProgress
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn play(positions: &[(u8, u8)]) -> (Board, Outcome) {
+        let mut board = Board::default();
+        let mut result = Outcome::InProgress;
+        for &(row, column) in positions {
+            let mark = next_mark(&board);
+            result = apply_move(&mut board, mark, Position::new(row, column)).unwrap();
+        }
+        (board, result)
+    }
+
+    #[test]
+    fn detects_rows_columns_and_diagonals() {
+        assert_eq!(play(&[(0, 0), (1, 0), (0, 1), (1, 1), (0, 2)]).1, Outcome::Won(Mark::X));
+        assert_eq!(play(&[(0, 0), (0, 1), (1, 0), (1, 1), (2, 2), (2, 1)]).1, Outcome::Won(Mark::O));
+        assert_eq!(play(&[(0, 2), (0, 0), (1, 1), (1, 0), (2, 0)]).1, Outcome::Won(Mark::X));
+    }
+
+    #[test]
+    fn detects_tie() {
+        let (board, result) = play(&[(0, 0), (0, 1), (0, 2), (1, 1), (1, 0), (1, 2), (2, 1), (2, 0), (2, 2)]);
+        assert_eq!(result, Outcome::Tie);
+        assert!(legal_moves(&board).is_empty());
+    }
+
+    #[test]
+    fn rejects_invalid_moves() {
+        let (mut board, _) = play(&[(1, 1)]);
+        assert_eq!(apply_move(&mut board, Mark::X, Position::new(0, 0)), Err(MoveError::NotYourTurn));
+        assert_eq!(apply_move(&mut board, Mark::O, Position::new(1, 1)), Err(MoveError::Occupied));
+        assert_eq!(apply_move(&mut board, Mark::O, Position::new(3, 0)), Err(MoveError::OutOfBounds));
+
+        let (mut board, _) = play(&[(0, 0), (1, 0), (0, 1), (1, 1), (0, 2)]);
+        assert_eq!(apply_move(&mut board, Mark::O, Position::new(2, 2)), Err(MoveError::GameOver));
+    }
+
+    #[test]
+    fn lists_empty_positions() {
+        let (board, _) = play(&[(0, 0), (1, 1)]);
+        let moves = legal_moves(&board);
+        assert_eq!(moves.len(), 7);
+        assert!(!moves.contains(&Position::new(0, 0)));
+        assert!(!moves.contains(&Position::new(1, 1)));
+    }
+}
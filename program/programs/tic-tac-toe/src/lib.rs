@@ -1,14 +1,46 @@
 use anchor_lang::prelude::*;
+use anchor_lang::solana_program::ed25519_program;
+use anchor_lang::solana_program::hash::hash;
+use anchor_lang::solana_program::instruction::{AccountMeta, Instruction};
+use anchor_lang::solana_program::program::invoke_signed;
+use anchor_lang::solana_program::sysvar::instructions::{load_current_index_checked, load_instruction_at_checked};
 use anchor_lang::system_program;
-use num_derive::*;
-use num_traits::*;
+use anchor_lang::Discriminator;
+use engine::{Board, Mark, MoveError, Outcome, Position};
+
+pub mod composer;
+pub mod engine;
 
 // Declare the program ID.
 declare_id!("AmzgW58Wn82iRhxKTFSMkjKDKw2B1ojLEi1bVykLzRyS");
 
-const GLOBAL_STATE_SEED: &[u8] = b"global_state";
-const GAME_SEED: &[u8] = b"game";
-const OWNER_WALLET: Pubkey = pubkey!("8fq9CbrmsctvZRkXoKMoiCCeZiJCLgCbbrvtJ6fLL4ZT");
+pub const GLOBAL_STATE_SEED: &[u8] = b"global_state";
+pub const GAME_SEED: &[u8] = b"game";
+pub const LEAGUE_SEED: &[u8] = b"league";
+pub const SIDE_POOL_SEED: &[u8] = b"side_pool";
+pub const SIDE_BET_SEED: &[u8] = b"side_bet";
+pub const ESCROW_SEED: &[u8] = b"escrow";
+pub const BALANCE_SEED: &[u8] = b"balance";
+pub const ADMIN_SET_SEED: &[u8] = b"admin_set";
+pub const PROPOSAL_SEED: &[u8] = b"proposal";
+pub const SESSION_SEED: &[u8] = b"session";
+pub const RELAY_NONCE_SEED: &[u8] = b"relay_nonce";
+pub const HOOK_AUTHORITY_SEED: &[u8] = b"hook_authority";
+pub const OWNER_WALLET: Pubkey = pubkey!("8fq9CbrmsctvZRkXoKMoiCCeZiJCLgCbbrvtJ6fLL4ZT");
+// Seconds after settlement before anyone can close a game, left for side bet claims and league results.
+pub const CLOSE_GRACE_PERIOD: i64 = 24 * 60 * 60;
+// Longest a session key can stay valid, in seconds.
+pub const MAX_SESSION_DURATION: i64 = 7 * 24 * 60 * 60;
+// Most lamports a relayer can be reimbursed from the pot for one move, about two signature fees.
+pub const MAX_RELAYER_FEE: u64 = 10_000;
+// Side bets are accepted before this turn, so pools are opened and bets placed early in the game.
+pub const SIDE_BET_CUTOFF_TURN: u8 = 3;
+// Seconds a league has to record every fixture once started, after which anyone can refund it.
+pub const LEAGUE_SEASON_DURATION: i64 = 30 * 24 * 60 * 60;
+const LEAGUE_MAX_PLAYERS: usize = 8;
+const ADMIN_MAX_SIGNERS: usize = 10;
+const LEAGUE_POINTS_WIN: u16 = 3;
+const LEAGUE_POINTS_TIE: u16 = 1;
 
 #[program]
 pub mod tic_tac_toe {
@@ -18,9 +50,11 @@ pub mod tic_tac_toe {
     // Method to initialize the global state
     pub fn initialize_global_state(ctx: Context<InitializeGlobalState>) -> Result<()> {
         let global_state = &mut ctx.accounts.global_state;
+        global_state.version = GlobalState::VERSION;
         global_state.game_count = 1; // Initializes the game counter to 1
         global_state.players_mapping = Vec::new(); // Initialize player mapping vector
         global_state.games_mapping = Vec::new(); // Initialize game mapping vector
+        global_state.hooks = Vec::new();
         global_state.fee = 5;   // % Percentage of the game pot
         global_state.bet = (0.1 * anchor_lang::solana_program::native_token::LAMPORTS_PER_SOL as f64) as u64;
         global_state.owner = OWNER_WALLET;
@@ -30,8 +64,107 @@ pub mod tic_tac_toe {
         Ok(())
     }
 
-    // Sets up the game and derives a unique PDA account using a global game counter.
-    pub fn setup_game<'info>(ctx: Context<'_, '_, 'info, 'info, SetupGame<'info>>) -> Result<()> {
+    // Upgrades the global state from an older layout and reallocs it to the current size. The
+    // payer covers any missing rent, fees already in the account are kept.
+    pub fn migrate_global_state(ctx: Context<MigrateGlobalState>) -> Result<()> {
+        let global_state_info = ctx.accounts.global_state.to_account_info();
+
+        let (from_version, global_state) = {
+            let data = global_state_info.try_borrow_data()?;
+            require!(data.starts_with(&GlobalState::DISCRIMINATOR), anchor_lang::error::ErrorCode::AccountDiscriminatorMismatch);
+            // The first layout had no version byte, it is told apart by its fixed size
+            let from_version = if data.len() == GlobalStateV1::SPACE { 1 } else { data[8] };
+            require!(from_version < GlobalState::VERSION, ErrorCode::AccountAlreadyMigrated);
+            let global_state = match from_version {
+                1 => GlobalState::from(GlobalStateV1::deserialize(&mut &data[8..])?),
+                _ => GlobalState::from(GlobalStateV2::deserialize(&mut &data[8..])?),
+            };
+            (from_version, global_state)
+        };
+
+        let rent = Rent::get()?.minimum_balance(GlobalState::MAXIMUM_SIZE);
+        let missing = rent.saturating_sub(global_state_info.lamports());
+        if missing > 0 {
+            let cpi_context = CpiContext::new(
+                ctx.accounts.system_program.to_account_info(),
+                system_program::Transfer {
+                    from: ctx.accounts.payer.to_account_info(),
+                    to: global_state_info.clone(),
+                },
+            );
+            system_program::transfer(cpi_context, missing)?;
+        }
+        global_state_info.realloc(GlobalState::MAXIMUM_SIZE, true)?;
+        global_state.try_serialize(&mut &mut global_state_info.try_borrow_mut_data()?[..])?;
+
+        msg!("Global state migrated from version {} to {}", from_version, GlobalState::VERSION);
+        emit!(GlobalStateMigrated {
+            from_version,
+            to_version: GlobalState::VERSION,
+            size: GlobalState::MAXIMUM_SIZE as u32,
+        });
+        Ok(())
+    }
+
+    // Refunds a game left running by the first program version and closes its account. Its bets
+    // are in the game account itself, which the current instructions cannot read, so its players
+    // would stay locked in the mappings. Anyone can call it, the bets only go back to the players.
+    pub fn refund_legacy_game(ctx: Context<RefundLegacyGame>) -> Result<()> {
+        let global_state = &mut ctx.accounts.global_state;
+        let game_info = ctx.accounts.game.to_account_info();
+
+        let game = {
+            let data = game_info.try_borrow_data()?;
+            require!(data.starts_with(&Game::DISCRIMINATOR), anchor_lang::error::ErrorCode::AccountDiscriminatorMismatch);
+            require!(data.len() == LegacyGame::SPACE, ErrorCode::NotALegacyGame);
+            LegacyGame::deserialize(&mut &data[8..])?
+        };
+        require!(game.state == GameState::Waiting || game.state == GameState::InProgress, ErrorCode::GameNotInProgress);
+        let player_one = game.players[0].ok_or(ErrorCode::PlayerNotFound)?;
+        require!(ctx.accounts.player_one.key() == player_one, ErrorCode::RivalIsNotPlayer);
+        if let Some(player_two) = game.players[1] {
+            require!(ctx.accounts.player_two.key() == player_two, ErrorCode::RivalIsNotPlayer);
+        }
+
+        // Both players bet the same amount, only player one did while waiting. Player one also
+        // paid the account rent.
+        let pot = game.pot;
+        let payouts = if game.players[1].is_some() { [pot - pot / 2, pot / 2] } else { [pot, 0] };
+        let rent = game_info.lamports() - pot;
+        game_info.sub_lamports(pot + rent)?;
+        ctx.accounts.player_one.add_lamports(payouts[0] + rent)?;
+        ctx.accounts.player_two.add_lamports(payouts[1])?;
+        game_info.assign(&system_program::ID);
+        game_info.realloc(0, false)?;
+
+        if game.state == GameState::Waiting {
+            global_state.advance_matchmaking(game_info.key());
+        }
+        global_state.remove_players_from_game(game_info.key());
+        msg!("Legacy game {} refunded: {:?}", game_info.key(), payouts);
+
+        emit!(GameFinished {
+            game: game_info.key(),
+            number: game.number,
+            player_one,
+            player_two: game.players[1].unwrap_or_default(),
+            winner: None,
+            reason: EndReason::EmergencyRefund,
+            board: game.board,
+            turns: game.turn,
+            pot,
+            fee: 0,
+            player_one_payout: payouts[0],
+            player_two_payout: payouts[1],
+            moves: Vec::new(),
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+        Ok(())
+    }
+
+    // Sets up the game and derives a unique PDA account using a global game counter. A new game
+    // calls the allowed `hook` program when it ends, the hook is ignored when joining a game.
+    pub fn setup_game<'info>(ctx: Context<'_, '_, 'info, 'info, SetupGame<'info>>, hook: Option<Pubkey>) -> Result<()> {
         // Extract player key and game account
         let player = &ctx.accounts.player;
         let player_key = player.key();
@@ -39,89 +172,77 @@ pub mod tic_tac_toe {
     
         // Extract global_state mutable access 
         let global_state = &mut ctx.accounts.global_state;
+        require!(!global_state.paused.new_games, ErrorCode::NewGamesPaused);
     
         // If player already has an active game, return error
         if global_state.find_game_from_player(player_key).is_ok() {
-            msg!("ERROR: GameAlreadyInProgress");
             return Err(ErrorCode::GameAlreadyInProgress.into());
         }
         
         // Initializing game paid variable
         game.paid = false;
 
-        msg!("Game State {:?}", game.state);
-
         if game.state == GameState::Uninitialized || game.state == GameState::Waiting {
-            // Check if the player has enough funds
-            let player_balance = player.to_account_info().lamports();
-            if player_balance < global_state.bet {
-                return Err(ErrorCode::InsufficientFunds.into());
+            // Player one opens the escrow, player two joins it
+            let escrow = &mut ctx.accounts.escrow;
+            let index = if game.state == GameState::Uninitialized { 0 } else { 1 };
+            if index == 0 {
+                escrow.open(game.key(), player_key);
             }
-
-            // Transfer bet from player to game pot
-            let cpi_context = CpiContext::new(
-                ctx.accounts.system_program.to_account_info(),
-                system_program::Transfer {
-                    from: player.to_account_info(),
-                    to: game.to_account_info(),
-                },
-            );
-
-            // Attempt to transfer the bet
-            system_program::transfer(cpi_context, global_state.bet)?;
+            // Transfer bet from the player's wallet or balance to the game escrow
+            stake(escrow, index, global_state.bet, player, ctx.accounts.balance.as_ref(), &ctx.accounts.system_program)?;
 
             // Game pot update
             game.pot += global_state.bet;
-
-            let game_pot_sol = game.pot as f64 / anchor_lang::solana_program::native_token::LAMPORTS_PER_SOL as f64;
-            let bet_sol = global_state.bet as f64 / anchor_lang::solana_program::native_token::LAMPORTS_PER_SOL as f64;
-
-            msg!("{} SOL has been added to the pot by player {}", bet_sol, player_key);
-            msg!("Pot value: {} SOL.", game_pot_sol);
         }
 
         // Check if there is a waiting game without accessing `global_state` mutably
         if game.state == GameState::Uninitialized {
             // Step 3: If no waiting game, set up a new one with mutable access to `global_state`
-            msg!("There is NOT a game WAITING.");
-            msg!("Setting up a new one.");
-            
+            global_state.check_hook(hook)?;
+            game.version = Game::VERSION;
             game.number = global_state.game_count;
             game.players[0] = Some(player_key);
             game.players[1] = None;
             game.turn = 0;
             game.board = [[None; 3]; 3];
+            game.moves = Vec::new();
             game.state = GameState::Waiting;
+            game.hook = hook;
+            game.hook_called = false;
+            game.relayer_fees = [0; 2];
         
             global_state.add_player(player_key, game.key())?;
-        
-            msg!("Game PDA PubKey: {}", game.key());
-            msg!("Game Number: {:?}", game.number);
-            msg!("Player 1: {:?}", game.players[0]);
-            msg!("Player 2: {:?}", game.players[1]);
-            msg!("Game State: {:?}", game.state);
 
-            return Ok(())
+            emit!(GameCreated {
+                game: game.key(),
+                number: game.number,
+                player_one: player_key,
+                league: None,
+                pot: game.pot,
+            });
+
+            Ok(())
         }
         else if game.state == GameState::Waiting {
-            msg!("There is a game WAITING.");
-
             game.players[1] = Some(player_key);
             game.state = GameState::InProgress;
             
             // Update global state
             global_state.game_count += 1;
             global_state.add_player(player_key, game.key())?;
-            
-            msg!("Game PDA PubKey: {}", game.key());
-            msg!("Player 1: {:?}", game.players[0]);
-            msg!("Player 2: {:?}", game.players[1]);
-            msg!("Game State: {:?}", game.state);
-    
-            return Ok(());
+
+            emit!(PlayerJoined {
+                game: game.key(),
+                number: game.number,
+                player_two: player_key,
+                pot: game.pot,
+            });
+
+            Ok(())
         }
         else {
-            return Err(ErrorCode::NoUninitializedOrWaitingGame.into());
+            Err(ErrorCode::NoUninitializedOrWaitingGame.into())
         }
     }
 
@@ -129,33 +250,91 @@ pub mod tic_tac_toe {
     pub fn play(ctx: Context<Play>, tile: Tile) -> Result<()> {
         let global_state = &mut ctx.accounts.global_state;
         let game_pda = &mut ctx.accounts.game;
-        let game_account_info = game_pda.to_account_info();
+        let escrow = &mut ctx.accounts.escrow;
         let player_account = &ctx.accounts.player;
         let rival_account = ctx.accounts.rival.clone();
+        require!(!global_state.paused.moves, ErrorCode::MovesPaused);
+        // A key that plays can throw the game, so its moves put the player's bet at stake
+        Session::authorize(player_account, ctx.accounts.session.as_mut(), ctx.accounts.session_key.as_ref(), game_pda.key(), game_pda.pot / 2)?;
+        let payees = escrow.payees(
+            game_pda,
+            (player_account.to_account_info(), ctx.accounts.player_balance.as_ref()),
+            (rival_account.clone(), ctx.accounts.rival_balance.as_ref()),
+        )?;
+
+        make_move(global_state, game_pda, escrow, player_account.key(), rival_account.key(), payees, &tile)
+    }
 
-        // Check if the player is in an active game
-        if let Some(game_address) = global_state.find_game_from_player(player_account.key()).ok() {
-            msg!("Game Address: {:?}", game_address);
-            // Check if the current game is NOT over
-            require!(!game_pda.is_over(), ErrorCode::GameAlreadyOver);
-            // Check if the current game is in progress
-            require!(game_pda.is_in_progress(), ErrorCode::GameNotInProgress);
-            // Make the move if it is the player's turn
-            return game_pda.play(global_state, game_account_info, player_account, rival_account, &tile);
-        }
-        else {
-            return Err(ErrorCode::PlayerHasNotAnActiveGame.into());
+    // Plays a move the player signed off-chain, submitted and paid for by any relayer. The
+    // instruction right before this one must be an Ed25519 program instruction verifying the
+    // player's signature of `relayed.message()`.
+    pub fn relay_move(ctx: Context<RelayMove>, relayed: RelayedMove) -> Result<()> {
+        let global_state = &mut ctx.accounts.global_state;
+        let game_pda = &mut ctx.accounts.game;
+        let escrow = &mut ctx.accounts.escrow;
+        let player_account = &ctx.accounts.player;
+        let rival_account = ctx.accounts.rival.clone();
+        require!(!global_state.paused.moves, ErrorCode::MovesPaused);
+        verify_signed_message(&ctx.accounts.instructions, &player_account.key(), &relayed.message())?;
+
+        // The game, the turn and the nonce keep a signed move from being played twice
+        require!(relayed.game == game_pda.key(), ErrorCode::InvalidRelayedMove);
+        require!(relayed.turn == game_pda.turn, ErrorCode::RelayedTurnMismatch);
+        let relay_nonce = &mut ctx.accounts.relay_nonce;
+        if relay_nonce.version == 0 {
+            relay_nonce.version = RelayNonce::VERSION;
+            relay_nonce.player = player_account.key();
         }
+        require!(relayed.nonce == relay_nonce.nonce, ErrorCode::InvalidNonce);
+        relay_nonce.nonce += 1;
+
+        // The reimbursement leaves the pot before the move, which may pay the pot out. It is
+        // charged to the player's own share, which a tie or a refund pays out net of it.
+        require!(game_pda.is_in_progress(), ErrorCode::GameNotInProgress);
+        let index = game_pda.players.iter().position(|&player| player == Some(player_account.key())).ok_or(ErrorCode::PlayerNotFound)?;
+        let relayer_fees = game_pda.relayer_fees[index] + relayed.relayer_fee;
+        require!(
+            relayed.relayer_fee <= MAX_RELAYER_FEE && relayer_fees <= game_pda.max_relayer_fees(global_state.fee),
+            ErrorCode::RelayerFeeTooHigh
+        );
+        escrow.sub_lamports(relayed.relayer_fee)?;
+        ctx.accounts.relayer.add_lamports(relayed.relayer_fee)?;
+        game_pda.pot -= relayed.relayer_fee;
+        game_pda.relayer_fees[index] = relayer_fees;
+
+        emit!(MoveRelayed {
+            game: game_pda.key(),
+            player: player_account.key(),
+            relayer: ctx.accounts.relayer.key(),
+            nonce: relayed.nonce,
+            relayer_fee: relayed.relayer_fee,
+        });
+
+        let payees = escrow.payees(
+            game_pda,
+            (player_account.to_account_info(), ctx.accounts.player_balance.as_ref()),
+            (rival_account.clone(), ctx.accounts.rival_balance.as_ref()),
+        )?;
+        make_move(global_state, game_pda, escrow, player_account.key(), rival_account.key(), payees, &relayed.tile)
     }
 
     pub fn cancel_game(ctx: Context<CancelGame>) -> Result<()> {
         let global_state = &mut ctx.accounts.global_state;
         let game_pda = &mut ctx.accounts.game;
-        let game_pda_account_info = game_pda.to_account_info();
-        let signer: &Signer = &ctx.accounts.signer;
+        let escrow = &mut ctx.accounts.escrow;
+        let signer: &AccountInfo = &ctx.accounts.signer;
         let rival: AccountInfo = ctx.accounts.rival.clone();
         let winner: Option<Pubkey>;
-        let mut canceled_waiting: bool = false;
+        let mut reason = EndReason::Resignation;
+        require!(!global_state.paused.moves, ErrorCode::MovesPaused);
+        // Canceling a waiting game costs the fee, resigning costs the signer's bet
+        let stake = if game_pda.state == GameState::Waiting { game_pda.pot * global_state.fee / 100 } else { game_pda.pot / 2 };
+        Session::authorize(signer, ctx.accounts.session.as_mut(), ctx.accounts.session_key.as_ref(), game_pda.key(), stake)?;
+        let payees = escrow.payees(
+            game_pda,
+            (signer.to_account_info(), ctx.accounts.signer_balance.as_ref()),
+            (rival.clone(), ctx.accounts.rival_balance.as_ref()),
+        )?;
 
         // Game must be InProgress or Waiting
         if game_pda.state != GameState::InProgress && game_pda.state != GameState::Waiting {
@@ -163,9 +342,13 @@ pub mod tic_tac_toe {
         }
         // Game Waiting
         if game_pda.state == GameState::Waiting {
+            // Only player one can cancel its waiting game
+            require!(game_pda.players[0] == Some(signer.key()), ErrorCode::SignerIsNotPlayer);
             game_pda.state = GameState::Canceled;
+            // Matchmaking moves on to a new game account, a league fixture leaves it alone
+            global_state.advance_matchmaking(game_pda.key());
             winner = None;
-            canceled_waiting = true;
+            reason = EndReason::Canceled;
         }
         // Game InProgress
         else {
@@ -179,6 +362,9 @@ pub mod tic_tac_toe {
                 None => return Err(ErrorCode::PlayerNotFound.into()),
             };
             if ctx.accounts.signer.key() == player_one || ctx.accounts.signer.key() == player_two {
+                // The rival account receives the pot
+                let expected_rival = if ctx.accounts.signer.key() == player_one { player_two } else { player_one };
+                require!(rival.key() == expected_rival, ErrorCode::RivalIsNotPlayer);
                 // If player one cancels, player two wins
                 if ctx.accounts.signer.key() == player_one {
                     game_pda.state = GameState::Won {
@@ -198,8 +384,15 @@ pub mod tic_tac_toe {
                 return Err(ErrorCode::SignerIsNotPlayer.into());
             }
         }
-        game_pda.end_game(global_state, game_pda_account_info, winner, signer, rival, canceled_waiting);
-        Ok(())
+
+        emit!(GameCanceled {
+            game: game_pda.key(),
+            canceled_by: signer.key(),
+            winner,
+        });
+
+        game_pda.end_game(global_state, escrow, winner, payees, reason);
+        escrow.settle()
     }
 
     pub fn close_game_account(ctx: Context<CloseGameAccount>) -> Result<()> {
@@ -212,17 +405,210 @@ pub mod tic_tac_toe {
             Some(pk) => pk,
             None => return Err(ErrorCode::PlayerNotFound.into()),
         };
+        // A game hosted by another program is closed by that program
+        require!(ctx.accounts.game.players.contains(&Some(ctx.accounts.escrow.rent_payer)), ErrorCode::GameIsHosted);
+        // A fixture set up again after closing could be replayed to erase its result
+        require_fixture_reported(&ctx.accounts.game, ctx.accounts.league.as_ref())?;
+        // The pending hook is called first, the crank still closes the game after the grace period
+        require!(ctx.accounts.game.hook.is_none() || ctx.accounts.game.hook_called, ErrorCode::HookPending);
+        settle_side_pool(&mut ctx.accounts.global_state, &ctx.accounts.game, &ctx.accounts.side_pool)?;
         if ctx.accounts.signer.key() == player_one {
+            let lamports = ctx.accounts.game.get_lamports() + ctx.accounts.escrow.get_lamports();
+            emit!(GameClosed {
+                game: ctx.accounts.game.key(),
+                number: ctx.accounts.game.number,
+                closed_by: player_one,
+                lamports,
+            });
+            emit!(ctx.accounts.game.archive(ctx.accounts.game.key(), &ctx.accounts.escrow, lamports));
             Ok(())
         }
         else {
-            return Err(ErrorCode::SignerDidNotOpenTheGameAccount.into());
+            Err(ErrorCode::SignerDidNotOpenTheGameAccount.into())
+        }
+    }
+
+    // Permissionless crank closing a settled game once its grace period is over. Any side pool
+    // of the game is settled first, and a league game must have its result recorded.
+    pub fn close_finished_game(ctx: Context<CloseFinishedGame>) -> Result<()> {
+        let game = &ctx.accounts.game;
+        let escrow = &ctx.accounts.escrow;
+        let global_state = &mut ctx.accounts.global_state;
+
+        require!(game.paid && escrow.settled_at != 0, ErrorCode::GameNotFinished);
+        require!(Clock::get()?.unix_timestamp >= escrow.settled_at + CLOSE_GRACE_PERIOD, ErrorCode::GracePeriodNotOver);
+        // The host of a hosted game settles it to read the result
+        require!(game.players.contains(&Some(escrow.rent_payer)), ErrorCode::GameIsHosted);
+
+        require_fixture_reported(game, ctx.accounts.league.as_ref())?;
+
+        settle_side_pool(global_state, game, &ctx.accounts.side_pool)?;
+
+        let lamports = game.get_lamports() + escrow.get_lamports();
+        emit!(game.archive(game.key(), escrow, lamports));
+        msg!("Game {} closed, {} lamports returned to {}.", game.key(), lamports, escrow.rent_payer);
+        Ok(())
+    }
+
+    // Starts a game between two users on behalf of a composing program, whose host PDA signs
+    // through CPI and pays the rent. Both players sign too, as they take a slot in the players
+    // mapping. Hosted games carry no wager, the host handles any prize.
+    pub fn create_hosted_game(ctx: Context<CreateHostedGame>, id: u64, hook: Option<Pubkey>) -> Result<()> {
+        let global_state = &mut ctx.accounts.global_state;
+        let game = &mut ctx.accounts.game;
+        let host = ctx.accounts.host.key();
+        let players = [ctx.accounts.player_one.key(), ctx.accounts.player_two.key()];
+        require!(!global_state.paused.new_games, ErrorCode::NewGamesPaused);
+        require!(players[0] != players[1] && !players.contains(&host), ErrorCode::InvalidHostedPlayers);
+        for player in players {
+            require!(global_state.find_game_from_player(player).is_err(), ErrorCode::GameAlreadyInProgress);
+        }
+        global_state.check_hook(hook)?;
+
+        game.version = Game::VERSION;
+        game.number = id;
+        game.players = [Some(players[0]), Some(players[1])];
+        game.turn = 0;
+        game.board = [[None; 3]; 3];
+        game.moves = Vec::new();
+        game.state = GameState::InProgress;
+        game.pot = 0;
+        game.paid = false;
+        game.league = None;
+        game.house = false;
+        game.hook = hook;
+        game.hook_called = false;
+        game.relayer_fees = [0; 2];
+        ctx.accounts.escrow.open(game.key(), host);
+        for player in players {
+            global_state.add_player(player, game.key())?;
         }
+
+        emit!(GameCreated {
+            game: game.key(),
+            number: id,
+            player_one: players[0],
+            league: None,
+            pot: 0,
+        });
+        emit!(PlayerJoined {
+            game: game.key(),
+            number: id,
+            player_two: players[1],
+            pot: 0,
+        });
+        msg!("Hosted game {} created by {}.", game.key(), host);
+        Ok(())
+    }
+
+    // Returns the result of any game, so composing programs can read it through CPI.
+    pub fn game_result(ctx: Context<ReadGameResult>) -> Result<GameResult> {
+        Ok(ctx.accounts.game.result(ctx.accounts.game.key()))
+    }
+
+    // Returns the result of a finished hosted game and closes it, sending the rent back to the host.
+    pub fn settle_hosted_game(ctx: Context<SettleHostedGame>, id: u64) -> Result<GameResult> {
+        let game = &ctx.accounts.game;
+        let escrow = &ctx.accounts.escrow;
+        require!(game.paid && escrow.settled_at != 0, ErrorCode::GameNotFinished);
+        settle_side_pool(&mut ctx.accounts.global_state, game, &ctx.accounts.side_pool)?;
+
+        let lamports = game.get_lamports() + escrow.get_lamports();
+        emit!(game.archive(game.key(), escrow, lamports));
+        msg!("Hosted game {} of {} settled, {} lamports returned.", id, ctx.accounts.host.key(), lamports);
+        Ok(game.result(game.key()))
+    }
+
+    // Moves lamports from the wallet to the player's balance, creating it on the first deposit.
+    pub fn deposit(ctx: Context<Deposit>, amount: u64) -> Result<()> {
+        let balance = &mut ctx.accounts.balance;
+        balance.version = PlayerBalance::VERSION;
+        balance.owner = ctx.accounts.owner.key();
+
+        let cpi_context = CpiContext::new(
+            ctx.accounts.system_program.to_account_info(),
+            system_program::Transfer {
+                from: ctx.accounts.owner.to_account_info(),
+                to: balance.to_account_info(),
+            },
+        );
+        system_program::transfer(cpi_context, amount)?;
+
+        emit!(BalanceChanged {
+            owner: balance.owner,
+            deposited: amount,
+            withdrawn: 0,
+            available: PlayerBalance::available(&balance.to_account_info())?,
+        });
+        Ok(())
+    }
+
+    // Moves lamports from the player's balance back to the wallet, the balance keeps its rent.
+    pub fn withdraw(ctx: Context<Withdraw>, amount: u64) -> Result<()> {
+        let balance = &ctx.accounts.balance;
+        require!(!ctx.accounts.global_state.paused.withdrawals, ErrorCode::WithdrawalsPaused);
+        require!(PlayerBalance::available(&balance.to_account_info())? >= amount, ErrorCode::InsufficientFunds);
+
+        balance.sub_lamports(amount)?;
+        ctx.accounts.owner.add_lamports(amount)?;
+
+        emit!(BalanceChanged {
+            owner: balance.owner,
+            deposited: 0,
+            withdrawn: amount,
+            available: PlayerBalance::available(&balance.to_account_info())?,
+        });
+        Ok(())
+    }
+
+    // Authorizes an ephemeral key to play and cancel for the player, until `expires_at` and
+    // only in `game` when given. A key can lose every game it plays, so the bets of the games it
+    // plays or resigns, and the fees of the games it cancels, may add up to `spending_cap` lamports.
+    pub fn create_session(ctx: Context<CreateSession>, key: Pubkey, game: Option<Pubkey>, expires_at: i64, spending_cap: u64) -> Result<()> {
+        let now = Clock::get()?.unix_timestamp;
+        require!(expires_at > now && expires_at <= now + MAX_SESSION_DURATION, ErrorCode::InvalidSessionExpiry);
+
+        let session = &mut ctx.accounts.session;
+        session.version = Session::VERSION;
+        session.authority = ctx.accounts.authority.key();
+        session.key = key;
+        session.game = game;
+        session.expires_at = expires_at;
+        session.spending_cap = spending_cap;
+        session.spent = 0;
+        session.charged_game = None;
+
+        emit!(SessionCreated {
+            session: session.key(),
+            authority: session.authority,
+            key,
+            game,
+            expires_at,
+            spending_cap,
+        });
+        Ok(())
+    }
+
+    // Closes a session, returning its rent to the player. Either the player or the session key
+    // can revoke it.
+    pub fn revoke_session(ctx: Context<RevokeSession>) -> Result<()> {
+        let session = &ctx.accounts.session;
+        let signer = ctx.accounts.signer.key();
+        require!(signer == session.authority || signer == session.key, ErrorCode::InvalidSession);
+
+        emit!(SessionRevoked {
+            session: session.key(),
+            authority: session.authority,
+            key: session.key,
+            spent: session.spent,
+        });
+        Ok(())
     }
 
     pub fn withdraw_fees(ctx: Context<WithdrawFees>, amount: u64) -> Result<()> {
         let global_state = &mut ctx.accounts.global_state;
         let owner = &mut ctx.accounts.owner;
+        require!(!global_state.paused.withdrawals, ErrorCode::WithdrawalsPaused);
     
         // Check if there are enough funds to transfer
         require!(**global_state.to_account_info().lamports.borrow() >= amount, ErrorCode::InsufficientFunds);
@@ -230,14 +616,596 @@ pub mod tic_tac_toe {
         // Transfer fees to the owner
         **global_state.to_account_info().lamports.borrow_mut() -= amount;
         **owner.to_account_info().lamports.borrow_mut() += amount;
-    
+
+        emit!(FeesWithdrawn {
+            owner: owner.key(),
+            amount,
+            remaining: global_state.get_lamports(),
+        });
+
+        Ok(())
+    }
+
+    // Sets the house bot key that plays against players with the fee treasury, or disables the house.
+    pub fn set_house(ctx: Context<SetHouse>, house: Option<Pubkey>) -> Result<()> {
+        ctx.accounts.global_state.house = house;
+        msg!("House set to {:?}", house);
+        emit!(HouseSet { house });
+        Ok(())
+    }
+
+    // Adds a hook program that new games can call when they end, or removes it. The pending
+    // hooks of a removed program can no longer be called.
+    pub fn set_hook(ctx: Context<SetHook>, hook: Pubkey, allowed: bool) -> Result<()> {
+        ctx.accounts.global_state.set_hook(hook, allowed)?;
+        msg!("Hook {} allowed: {}", hook, allowed);
+        emit!(HookSet { hook, allowed });
+        Ok(())
+    }
+
+    // Permissionless call of the hook of a settled game. Hooks run in their own transaction, apart
+    // from the instruction ending the game, so a failing hook never reverts the payout. The
+    // remaining accounts are those of `call_hook`, and the hook is called only once.
+    pub fn call_game_hook<'info>(ctx: Context<'_, '_, 'info, 'info, CallGameHook<'info>>) -> Result<()> {
+        let global_state = &ctx.accounts.global_state;
+        let game = &mut ctx.accounts.game;
+
+        let hook = game.hook.filter(|_| !game.hook_called).ok_or(ErrorCode::NoPendingHook)?;
+        require!(game.paid, ErrorCode::GameNotFinished);
+        require!(global_state.hooks.contains(&hook), ErrorCode::HookNotAllowed);
+        call_hook(game, hook, ctx.remaining_accounts)
+    }
+
+    // Starts a game against the house bot, which matches the player's bet from the fee treasury.
+    pub fn challenge_house(ctx: Context<ChallengeHouse>) -> Result<()> {
+        let player = &ctx.accounts.player;
+        let player_key = player.key();
+        let game = &mut ctx.accounts.game;
+        let global_state = &mut ctx.accounts.global_state;
+
+        require!(!global_state.paused.new_games, ErrorCode::NewGamesPaused);
+        let house = global_state.house.ok_or(ErrorCode::HouseNotSet)?;
+        require!(global_state.find_game_from_player(player_key).is_err(), ErrorCode::GameAlreadyInProgress);
+        // A waiting player is matched by `setup_game` instead
+        require!(game.state == GameState::Uninitialized, ErrorCode::PlayerWaitingForRival);
+        // The treasury keeps the global state rent exempt
+        let global_state_info = global_state.to_account_info();
+        let rent = Rent::get()?.minimum_balance(global_state_info.data_len());
+        require!(global_state_info.lamports() >= rent + global_state.bet, ErrorCode::TreasuryCannotCoverBet);
+
+        // Transfer the player's bet and the house bet to the game escrow
+        let escrow = &mut ctx.accounts.escrow;
+        escrow.open(game.key(), player_key);
+        stake(escrow, 0, global_state.bet, player, ctx.accounts.balance.as_ref(), &ctx.accounts.system_program)?;
+        global_state.sub_lamports(global_state.bet)?;
+        escrow.add_lamports(global_state.bet)?;
+
+        game.version = Game::VERSION;
+        game.number = global_state.game_count;
+        game.players = [Some(player_key), Some(house)];
+        game.turn = 0;
+        game.board = [[None; 3]; 3];
+        game.moves = Vec::new();
+        game.state = GameState::InProgress;
+        game.pot = 2 * global_state.bet;
+        game.paid = false;
+        game.house = true;
+        game.hook = None;
+        game.hook_called = false;
+        game.relayer_fees = [0; 2];
+
+        global_state.game_count += 1;
+        global_state.add_player(player_key, game.key())?;
+        global_state.add_player(house, game.key())?;
+
+        emit!(HouseChallenged {
+            game: game.key(),
+            number: game.number,
+            player: player_key,
+            house,
+            pot: game.pot,
+        });
         Ok(())
     }
-}
 
-impl Default for GameState {
-    fn default() -> Self {
-        GameState::Uninitialized // Change this if another variant makes more sense as the default value
+    // Pauses or resumes new games, moves and withdrawals.
+    pub fn set_paused(ctx: Context<SetPaused>, paused: Pause) -> Result<()> {
+        ctx.accounts.global_state.paused = paused;
+        msg!("Paused: {:?}", paused);
+        emit!(PauseSet { paused });
+        Ok(())
+    }
+
+    // Cancels a waiting or in progress game and returns each player's bet without fee.
+    pub fn emergency_refund(ctx: Context<EmergencyRefund>) -> Result<()> {
+        let accounts = ctx.accounts;
+        let players = [accounts.player_one.clone(), accounts.player_two.clone()];
+        let balances = [accounts.player_one_balance.as_ref(), accounts.player_two_balance.as_ref()];
+        refund_game(&mut accounts.global_state, &mut accounts.game, &mut accounts.escrow, players, balances)
+    }
+
+    // Sets the game fee (% of the pot) and the bet of new games.
+    pub fn set_config(ctx: Context<SetConfig>, fee: u64, bet: u64) -> Result<()> {
+        let global_state = &mut ctx.accounts.global_state;
+        global_state.set_config(fee, bet)?;
+        msg!("Fee set to {}%, bet to {} lamports", fee, bet);
+        emit!(ConfigSet { fee, bet });
+        Ok(())
+    }
+
+    // Hands the admin actions to an M-of-N admin set. The admin set PDA becomes the owner, so
+    // admin actions then go through `propose`, `approve_proposal` and `execute_proposal`.
+    pub fn create_admin_set(ctx: Context<CreateAdminSet>, signers: Vec<Pubkey>, threshold: u8, timelock: i64) -> Result<()> {
+        let admin_set = &mut ctx.accounts.admin_set;
+        admin_set.version = AdminSet::VERSION;
+        admin_set.configure(signers, threshold, timelock)?;
+        ctx.accounts.global_state.owner = admin_set.key();
+        msg!("Admin set: {} of {:?}, timelock {}s", threshold, admin_set.signers, timelock);
+
+        emit!(AdminSetChanged {
+            signers: admin_set.signers.clone(),
+            threshold,
+            timelock,
+        });
+        Ok(())
+    }
+
+    // Proposes an admin action, approved by the proposer.
+    pub fn propose(ctx: Context<Propose>, action: AdminAction) -> Result<()> {
+        let admin_set = &mut ctx.accounts.admin_set;
+        let proposal = &mut ctx.accounts.proposal;
+        let proposer = ctx.accounts.proposer.key();
+
+        require!(admin_set.signers.contains(&proposer), ErrorCode::NotAnAdmin);
+
+        proposal.version = Proposal::VERSION;
+        proposal.id = admin_set.proposal_count;
+        proposal.proposer = proposer;
+        proposal.action = action.clone();
+        proposal.approvals = Vec::new();
+        proposal.executed = false;
+        proposal.admins_version = admin_set.admins_version;
+        proposal.approve(proposer, admin_set)?;
+        admin_set.proposal_count += 1;
+        msg!("Proposal {}: {:?}", proposal.id, action);
+
+        emit!(ProposalCreated {
+            proposal: proposal.key(),
+            id: proposal.id,
+            proposer,
+            action,
+        });
+        Ok(())
+    }
+
+    pub fn approve_proposal(ctx: Context<ApproveProposal>) -> Result<()> {
+        let proposal = &mut ctx.accounts.proposal;
+        let signer = ctx.accounts.signer.key();
+
+        require!(ctx.accounts.admin_set.signers.contains(&signer), ErrorCode::NotAnAdmin);
+        require!(!proposal.executed, ErrorCode::ProposalAlreadyExecuted);
+        require!(proposal.admins_version == ctx.accounts.admin_set.admins_version, ErrorCode::StaleProposal);
+        require!(!proposal.approvals.contains(&signer), ErrorCode::ProposalAlreadyApproved);
+        proposal.approve(signer, &ctx.accounts.admin_set)?;
+        msg!("Proposal {} approved by {}", proposal.id, signer);
+
+        emit!(ProposalApproved {
+            proposal: proposal.key(),
+            id: proposal.id,
+            signer,
+            approvals: proposal.approvals.len() as u8,
+        });
+        Ok(())
+    }
+
+    // Executes an approved proposal once its timelock expired. Anyone can execute it.
+    pub fn execute_proposal(ctx: Context<ExecuteProposal>) -> Result<()> {
+        let global_state = &mut ctx.accounts.global_state;
+        let admin_set = &mut ctx.accounts.admin_set;
+        let proposal = &mut ctx.accounts.proposal;
+
+        require!(global_state.owner == admin_set.key(), ErrorCode::AdminSetNotOwner);
+        require!(!proposal.executed, ErrorCode::ProposalAlreadyExecuted);
+        // Approvals and timelocks given under other admins or thresholds do not count
+        require!(proposal.admins_version == admin_set.admins_version, ErrorCode::StaleProposal);
+        require!(proposal.approvals.len() >= admin_set.threshold as usize, ErrorCode::ProposalNotApproved);
+        let approved_at = proposal.approved_at.ok_or(ErrorCode::ProposalNotApproved)?;
+        require!(Clock::get()?.unix_timestamp >= approved_at + admin_set.timelock, ErrorCode::TimelockNotExpired);
+
+        match proposal.action.clone() {
+            AdminAction::WithdrawFees { amount, recipient } => {
+                let recipient_info = ctx.accounts.recipient.as_ref().ok_or(ErrorCode::InvalidRecipient)?;
+                require!(recipient_info.key() == recipient, ErrorCode::InvalidRecipient);
+                // The global state keeps its rent-exempt reserve
+                let rent = Rent::get()?.minimum_balance(global_state.to_account_info().data_len());
+                require!(global_state.get_lamports().saturating_sub(rent) >= amount, ErrorCode::InsufficientFunds);
+                global_state.sub_lamports(amount)?;
+                recipient_info.add_lamports(amount)?;
+            }
+            AdminAction::SetConfig { fee, bet } => global_state.set_config(fee, bet)?,
+            AdminAction::SetPaused(paused) => global_state.paused = paused,
+            AdminAction::SetHouse(house) => global_state.house = house,
+            AdminAction::SetHook { hook, allowed } => global_state.set_hook(hook, allowed)?,
+            AdminAction::SetOwner(owner) => global_state.owner = owner,
+            AdminAction::SetAdmins { signers, threshold, timelock } => admin_set.configure(signers, threshold, timelock)?,
+            AdminAction::EmergencyRefund { game } => {
+                let accounts = (ctx.accounts.game.as_mut(), ctx.accounts.escrow.as_mut(), ctx.accounts.player_one.as_ref(), ctx.accounts.player_two.as_ref());
+                let (Some(game_account), Some(escrow), Some(player_one), Some(player_two)) = accounts else {
+                    return err!(ErrorCode::InvalidRefundAccounts);
+                };
+                require!(game_account.key() == game && escrow.game == game, ErrorCode::InvalidRefundAccounts);
+                let players = [player_one.to_account_info(), player_two.to_account_info()];
+                let balances = [ctx.accounts.player_one_balance.as_ref(), ctx.accounts.player_two_balance.as_ref()];
+                refund_game(global_state, game_account, escrow, players, balances)?;
+            }
+        }
+        proposal.executed = true;
+        msg!("Proposal {} executed: {:?}", proposal.id, proposal.action);
+
+        emit!(ProposalExecuted {
+            proposal: proposal.key(),
+            id: proposal.id,
+            action: proposal.action.clone(),
+        });
+        Ok(())
+    }
+
+    // Creates a league that players can register to by paying the entry fee.
+    pub fn create_league(ctx: Context<CreateLeague>, id: u64, entry_fee: u64, prize_shares: Vec<u8>) -> Result<()> {
+        // Prize shares are percentages of the pool, from first to last paid position
+        require!(
+            !prize_shares.is_empty()
+                && prize_shares.len() <= LEAGUE_MAX_PLAYERS
+                && prize_shares.iter().map(|&share| share as u16).sum::<u16>() == 100,
+            ErrorCode::InvalidPrizeShares
+        );
+
+        let league = &mut ctx.accounts.league;
+        league.version = League::VERSION;
+        league.authority = ctx.accounts.authority.key();
+        league.id = id;
+        league.state = LeagueState::Registering;
+        league.entry_fee = entry_fee;
+        league.pool = 0;
+        league.prize_shares = prize_shares;
+        league.players = Vec::new();
+        league.standings = Vec::new();
+        league.fixtures = Vec::new();
+        league.paid = false;
+        league.deadline = 0;
+
+        msg!("League PDA PubKey: {}", league.key());
+        msg!("League Id: {}", league.id);
+        msg!("Entry fee: {} lamports", league.entry_fee);
+        Ok(())
+    }
+
+    // Registers the signer in a league, adding the entry fee to the season's pool.
+    pub fn join_league(ctx: Context<JoinLeague>) -> Result<()> {
+        let player = &ctx.accounts.player;
+        let league = &mut ctx.accounts.league;
+
+        require!(league.state == LeagueState::Registering, ErrorCode::LeagueNotRegistering);
+        require!(league.players.len() < LEAGUE_MAX_PLAYERS, ErrorCode::LeagueFull);
+        require!(!league.players.contains(&player.key()), ErrorCode::PlayerAlreadyInLeague);
+
+        // Transfer entry fee from player to league pool
+        let cpi_context = CpiContext::new(
+            ctx.accounts.system_program.to_account_info(),
+            system_program::Transfer {
+                from: player.to_account_info(),
+                to: league.to_account_info(),
+            },
+        );
+        system_program::transfer(cpi_context, league.entry_fee)?;
+
+        league.pool += league.entry_fee;
+        league.players.push(player.key());
+        league.standings.push(Standing::default());
+
+        msg!("Player {} joined league {}", player.key(), league.id);
+        msg!("League pool: {} lamports", league.pool);
+        Ok(())
+    }
+
+    // Closes registration and schedules every registered player against each other.
+    pub fn start_league(ctx: Context<StartLeague>) -> Result<()> {
+        let league = &mut ctx.accounts.league;
+
+        require!(league.state == LeagueState::Registering, ErrorCode::LeagueNotRegistering);
+        require!(
+            league.players.len() >= 2 && league.players.len() >= league.prize_shares.len(),
+            ErrorCode::NotEnoughLeaguePlayers
+        );
+
+        league.schedule_round_robin();
+        league.state = LeagueState::InProgress;
+        league.deadline = Clock::get()?.unix_timestamp + LEAGUE_SEASON_DURATION;
+
+        msg!("League {} started with {} fixtures", league.id, league.fixtures.len());
+        Ok(())
+    }
+
+    // Sets up (or joins) the game of a league fixture. League games carry no wager,
+    // players compete for standings points and the season's pool instead.
+    pub fn setup_league_game(ctx: Context<SetupLeagueGame>, fixture_index: u16) -> Result<()> {
+        let player_key = ctx.accounts.player.key();
+        let league = &mut ctx.accounts.league;
+        let league_key = league.key();
+        let game = &mut ctx.accounts.game;
+        let global_state = &mut ctx.accounts.global_state;
+
+        require!(!global_state.paused.new_games, ErrorCode::NewGamesPaused);
+        require!(league.state == LeagueState::InProgress, ErrorCode::LeagueNotInProgress);
+
+        let fixture = league.fixtures.get(fixture_index as usize).ok_or(ErrorCode::FixtureNotFound)?;
+        require!(!fixture.reported, ErrorCode::FixtureAlreadyReported);
+        let fixture_players = [league.players[fixture.player_one as usize], league.players[fixture.player_two as usize]];
+        require!(fixture_players.contains(&player_key), ErrorCode::SignerNotInFixture);
+
+        // If player already has an active game, return error
+        if global_state.find_game_from_player(player_key).is_ok() {
+            return Err(ErrorCode::GameAlreadyInProgress.into());
+        }
+
+        // A fixture game canceled before the rival joined can be set up again
+        if game.state == GameState::Canceled {
+            game.state = GameState::Uninitialized;
+        }
+
+        if game.state == GameState::Uninitialized {
+            game.version = Game::VERSION;
+            game.number = fixture_index as u64;
+            game.players[0] = Some(player_key);
+            game.players[1] = None;
+            game.turn = 0;
+            game.board = [[None; 3]; 3];
+            game.moves = Vec::new();
+            game.state = GameState::Waiting;
+            game.pot = 0;
+            game.paid = false;
+            game.league = Some(league_key);
+            game.hook = None;
+            game.hook_called = false;
+            game.relayer_fees = [0; 2];
+            ctx.accounts.escrow.open(game.key(), player_key);
+
+            league.fixtures[fixture_index as usize].game = Some(game.key());
+            global_state.add_player(player_key, game.key())?;
+
+            emit!(GameCreated {
+                game: game.key(),
+                number: game.number,
+                player_one: player_key,
+                league: Some(league_key),
+                pot: game.pot,
+            });
+            Ok(())
+        }
+        else if game.state == GameState::Waiting {
+            game.players[1] = Some(player_key);
+            game.state = GameState::InProgress;
+
+            global_state.add_player(player_key, game.key())?;
+
+            emit!(PlayerJoined {
+                game: game.key(),
+                number: game.number,
+                player_two: player_key,
+                pot: game.pot,
+            });
+            Ok(())
+        }
+        else {
+            Err(ErrorCode::NoUninitializedOrWaitingGame.into())
+        }
+    }
+
+    // Updates the league standings with the result of a finished fixture game.
+    pub fn record_league_result(ctx: Context<RecordLeagueResult>, fixture_index: u16) -> Result<()> {
+        let league = &mut ctx.accounts.league;
+        let game = &ctx.accounts.game;
+
+        require!(league.state == LeagueState::InProgress, ErrorCode::LeagueNotInProgress);
+
+        let fixture = league.fixtures.get(fixture_index as usize).ok_or(ErrorCode::FixtureNotFound)?.clone();
+        require!(!fixture.reported, ErrorCode::FixtureAlreadyReported);
+        require!(fixture.game == Some(game.key()), ErrorCode::GameNotFound);
+
+        let (one, two) = (fixture.player_one as usize, fixture.player_two as usize);
+        match game.state {
+            GameState::Won { winner } => {
+                let (winner_index, loser_index) = if winner == league.players[one] { (one, two) } else { (two, one) };
+                league.standings[winner_index].points += LEAGUE_POINTS_WIN;
+                league.standings[winner_index].wins += 1;
+                league.standings[loser_index].losses += 1;
+                msg!("Fixture {}: player {} won", fixture_index, winner);
+            }
+            GameState::Tie => {
+                for index in [one, two] {
+                    league.standings[index].points += LEAGUE_POINTS_TIE;
+                    league.standings[index].ties += 1;
+                }
+                msg!("Fixture {}: tie", fixture_index);
+            }
+            _ => return Err(ErrorCode::GameNotFinished.into()),
+        }
+
+        league.fixtures[fixture_index as usize].reported = true;
+        Ok(())
+    }
+
+    // Pays out the season's pool by final ranking once every fixture has been recorded.
+    // The paid players' accounts must be passed as remaining accounts in ranking order.
+    pub fn finalize_league<'info>(ctx: Context<'_, '_, 'info, 'info, FinalizeLeague<'info>>) -> Result<()> {
+        let league = &mut ctx.accounts.league;
+
+        require!(league.state == LeagueState::InProgress, ErrorCode::LeagueNotInProgress);
+        require!(league.fixtures.iter().all(|fixture| fixture.reported), ErrorCode::LeagueFixturesPending);
+
+        let ranking = league.ranking();
+        let paid_positions = league.prize_shares.len();
+        require!(ctx.remaining_accounts.len() >= paid_positions, ErrorCode::RankingMismatch);
+
+        // Every position but the first gets its share, the first one also takes the rounding dust
+        let pool = league.pool;
+        let mut payouts: Vec<u64> = league.prize_shares.iter().map(|&share| pool * share as u64 / 100).collect();
+        payouts[0] = pool - payouts[1..].iter().sum::<u64>();
+
+        for (position, payout) in payouts.iter().enumerate() {
+            let player_account_info = &ctx.remaining_accounts[position];
+            require!(player_account_info.key() == league.players[ranking[position]], ErrorCode::RankingMismatch);
+            league.sub_lamports(*payout)?;
+            player_account_info.add_lamports(*payout)?;
+            msg!("Position {}: {} lamports payed out to player {}", position + 1, payout, player_account_info.key());
+        }
+
+        league.pool = 0;
+        league.paid = true;
+        league.state = LeagueState::Finished;
+
+        emit!(LeagueFinished {
+            league: league.key(),
+            ranking: ranking.iter().map(|&index| league.players[index]).collect(),
+            payouts,
+        });
+        Ok(())
+    }
+
+    // Permissionless: refunds the entry fee to every player of a league whose fixtures were not
+    // all recorded by the end of the season, so a fixture never played cannot lock the pool.
+    // Remaining accounts are the players' accounts in registration order.
+    pub fn refund_league<'info>(ctx: Context<'_, '_, 'info, 'info, RefundLeague<'info>>) -> Result<()> {
+        let league = &mut ctx.accounts.league;
+
+        require!(league.state == LeagueState::InProgress, ErrorCode::LeagueNotInProgress);
+        require!(Clock::get()?.unix_timestamp >= league.deadline, ErrorCode::LeagueSeasonNotOver);
+        require!(ctx.remaining_accounts.len() == league.players.len(), ErrorCode::RankingMismatch);
+
+        for (index, player_account_info) in ctx.remaining_accounts.iter().enumerate() {
+            require!(player_account_info.key() == league.players[index], ErrorCode::RankingMismatch);
+            league.sub_lamports(league.entry_fee)?;
+            player_account_info.add_lamports(league.entry_fee)?;
+            msg!("{} lamports refunded to player {}", league.entry_fee, player_account_info.key());
+        }
+
+        league.pool = 0;
+        league.paid = true;
+        league.state = LeagueState::Refunded;
+        Ok(())
+    }
+
+    // Opens a parimutuel side-bet pool on a game in progress. Bets are accepted until
+    // the game reaches the cutoff turn, the same for every game.
+    pub fn open_side_pool(ctx: Context<OpenSidePool>) -> Result<()> {
+        let game = &ctx.accounts.game;
+        let side_pool = &mut ctx.accounts.side_pool;
+
+        require!(game.is_in_progress(), ErrorCode::GameNotInProgress);
+        require!(game.turn < SIDE_BET_CUTOFF_TURN, ErrorCode::InvalidCutoffTurn);
+
+        side_pool.version = SideBetPool::VERSION;
+        side_pool.game = game.key();
+        side_pool.payer = ctx.accounts.payer.key();
+        side_pool.cutoff_turn = SIDE_BET_CUTOFF_TURN;
+        side_pool.totals = [0; 3];
+        side_pool.outcome = None;
+        side_pool.fee = 0;
+        side_pool.settled = false;
+        side_pool.open_bets = 0;
+
+        msg!("Side pool PDA PubKey: {}", side_pool.key());
+        msg!("Game PDA PubKey: {}", side_pool.game);
+        msg!("Cutoff turn: {}", side_pool.cutoff_turn);
+        Ok(())
+    }
+
+    // Places a spectator bet on player one, player two or a tie.
+    pub fn place_side_bet(ctx: Context<PlaceSideBet>, outcome: SideBetOutcome, amount: u64) -> Result<()> {
+        let game = &ctx.accounts.game;
+        let side_pool = &mut ctx.accounts.side_pool;
+        let side_bet = &mut ctx.accounts.side_bet;
+        let bettor = &ctx.accounts.bettor;
+
+        require!(game.is_in_progress(), ErrorCode::GameNotInProgress);
+        require!(game.turn < side_pool.cutoff_turn, ErrorCode::SideBetsClosed);
+        require!(!game.players.contains(&Some(bettor.key())), ErrorCode::SignerIsPlayer);
+        require!(amount > 0, ErrorCode::InvalidSideBetAmount);
+
+        // Transfer bet from bettor to the side pool
+        let cpi_context = CpiContext::new(
+            ctx.accounts.system_program.to_account_info(),
+            system_program::Transfer {
+                from: bettor.to_account_info(),
+                to: side_pool.to_account_info(),
+            },
+        );
+        system_program::transfer(cpi_context, amount)?;
+
+        side_pool.totals[outcome as usize] += amount;
+        side_pool.open_bets += 1;
+        side_bet.version = SideBet::VERSION;
+        side_bet.pool = side_pool.key();
+        side_bet.bettor = bettor.key();
+        side_bet.outcome = outcome;
+        side_bet.amount = amount;
+
+        msg!("{} lamports bet on {:?} by {}", amount, outcome, bettor.key());
+        msg!("Side pool totals: {:?}", side_pool.totals);
+        Ok(())
+    }
+
+    // Pays out a side bet once the game is over and closes the bet account. The first
+    // claim settles the pool from the game's final state and takes the protocol fee.
+    pub fn claim_side_bet(ctx: Context<ClaimSideBet>) -> Result<()> {
+        let global_state = &mut ctx.accounts.global_state;
+        let side_pool = &mut ctx.accounts.side_pool;
+        let side_bet = &ctx.accounts.side_bet;
+        let bettor = &ctx.accounts.bettor;
+        require!(!global_state.paused.withdrawals, ErrorCode::WithdrawalsPaused);
+
+        settle_side_pool_from(global_state, side_pool, &ctx.accounts.game)?;
+
+        let payout = side_pool.payout(side_bet);
+        if payout > 0 {
+            side_pool.sub_lamports(payout)?;
+            bettor.add_lamports(payout)?;
+        }
+        side_pool.open_bets -= 1;
+        msg!("{} lamports payed out as side bet to {}.", payout, bettor.key());
+        Ok(())
+    }
+
+    // Permissionless: pays out the unclaimed bets of a finished game and closes them with the
+    // pool, returning the pool rent to whoever opened it. Remaining accounts are pairs of
+    // [side bet, bettor] covering every unclaimed bet.
+    pub fn close_side_pool<'info>(ctx: Context<'_, '_, 'info, 'info, CloseSidePool<'info>>) -> Result<()> {
+        let global_state = &mut ctx.accounts.global_state;
+        let side_pool = &mut ctx.accounts.side_pool;
+        require!(!global_state.paused.withdrawals, ErrorCode::WithdrawalsPaused);
+
+        settle_side_pool_from(global_state, side_pool, &ctx.accounts.game)?;
+
+        for accounts in ctx.remaining_accounts.chunks_exact(2) {
+            let side_bet = Account::<SideBet>::try_from(&accounts[0])?;
+            let bettor = &accounts[1];
+            require!(side_bet.pool == side_pool.key(), ErrorCode::AccountNotFound);
+            require!(side_bet.bettor == bettor.key(), ErrorCode::AccountNotFound);
+
+            let payout = side_pool.payout(&side_bet);
+            if payout > 0 {
+                side_pool.sub_lamports(payout)?;
+                bettor.add_lamports(payout)?;
+            }
+            side_bet.close(bettor.clone())?;
+            side_pool.open_bets -= 1;
+            msg!("{} lamports payed out as side bet to {}.", payout, bettor.key());
+        }
+
+        // The side pool is closed with its rounding dust once no bet can claim from it
+        require!(side_pool.open_bets == 0, ErrorCode::SideBetsOutstanding);
+        msg!("Side pool {} closed.", side_pool.key());
+        Ok(())
     }
 }
 
@@ -283,8 +1251,23 @@ impl GlobalState {
 
 // Implementation of the game structure.
 impl Game {
+    pub const VERSION: u8 = 4;
     // Maximum size of the game account.
-    pub const MAXIMUM_SIZE: usize = 8 + (32 * 2) + 1 + (9 * (1 + 1)) + (32 + 1) + 8 + 8 + 8;
+    pub const MAXIMUM_SIZE: usize = 8 + 1 + (32 * 2) + 1 + (9 * (1 + 1)) + (32 + 1) + 8 + 8 + 8 + (32 + 1) + (4 + 9 * Move::SIZE) + 1 + (32 + 1) + 1 + (8 * 2);
+
+    // Splits `amount` of the pot evenly as if no relayer fee had left it, then takes each
+    // player's relayer fees from their own half. Player 1 gets the odd lamport if any.
+    pub fn split(&self, amount: u64) -> [u64; 2] {
+        let amount = amount + self.relayer_fees.iter().sum::<u64>();
+        [amount - amount / 2 - self.relayer_fees[0], amount / 2 - self.relayer_fees[1]]
+    }
+
+    // Most relayer fees a player can pay in total, their half of the pot net of the game fee,
+    // so `split` never owes a player less than their fees.
+    pub fn max_relayer_fees(&self, fee_percentage: u64) -> u64 {
+        let pot = self.pot + self.relayer_fees.iter().sum::<u64>();
+        (pot - pot * fee_percentage / 100) / 2
+    }
 
     // Checks if the game is still active.
     pub fn is_in_progress(&self) -> bool {
@@ -301,27 +1284,15 @@ impl Game {
 
     fn current_signer_index(&self, player: Option<Pubkey>) -> usize {
         match player {
-            Some(player_pubkey) => {
-                // Here `player_pubkey` is of type `Pubkey`.
-                println!("The value of the public key is: {:?}", player_pubkey);
-                
-                if let Some(index) = self.players.iter().position(|&p| p == player) {
-                    return index // Return the found index as `usize`
-                }
-                
-                else {
-                    return 2;
-                }
-            }
-            None => {
-                return 2;
-            }
+            // Return the found index as `usize`, or 2 if the key is not a player
+            Some(_) => self.players.iter().position(|&p| p == player).unwrap_or(2),
+            None => 2,
         }
     }
     
-    // Returns the index of the player whose turn it is.
-    fn current_turn_index(&self) -> usize {
-        ((self.turn) % 2) as usize
+    // Returns the board in the engine representation.
+    pub fn engine_board(&self) -> Board {
+        self.board.map(|row| row.map(|cell| cell.map(Mark::from)))
     }
 
     // Returns the public key of the current player.
@@ -330,101 +1301,98 @@ impl Game {
             .expect("Current player should be set")
     }
 
-    // Makes a move on the board.
-    pub fn play<'info>(&mut self, global_state: &mut Account<GlobalState>, game_account_info: AccountInfo<'info>, player_account: &Signer<'info>, rival_account: AccountInfo<'info>, tile: &Tile) -> Result<()> {
-        msg!("Current Player address: {:?}", player_account.key());
-
-        let current_turn_index: usize = self.current_turn_index();
-        let current_signer_index: usize = self.current_signer_index(Some(player_account.key()));
-        if current_turn_index != current_signer_index {
-            return Err(ErrorCode::NotPlayersTurn.into());
+    // Result of the game, returned to composing programs.
+    fn result(&self, game: Pubkey) -> GameResult {
+        GameResult {
+            game,
+            number: self.number,
+            players: self.players,
+            state: self.state.clone(),
+            turns: self.turn,
         }
+    }
 
-        // Check if the board position is valid and empty.
-        match tile {
-            tile @ Tile { row: 0..=2, column: 0..=2 } => match self.board[tile.row as usize][tile.column as usize] {
-                Some(_) => return Err(ErrorCode::TileAlreadySet.into()), // Tile already occupied.
-                None => {
-                    // Assign the current player's sign to the empty tile.
-                    self.board[tile.row as usize][tile.column as usize] =
-                        Some(Sign::from_usize(self.current_signer_index(Some(player_account.key()))).unwrap());
-                }
-            },
-            _ => return Err(ErrorCode::TileOutOfBounds.into()), // Out of board bounds.
+    // Final state of the game, emitted when its account is closed.
+    fn archive(&self, game: Pubkey, escrow: &Escrow, lamports: u64) -> GameArchived {
+        GameArchived {
+            game,
+            number: self.number,
+            players: self.players,
+            state: self.state.clone(),
+            board: self.board,
+            moves: self.moves.clone(),
+            league: self.league,
+            house: self.house,
+            settled_at: escrow.settled_at,
+            rent_payer: escrow.rent_payer,
+            lamports,
         }
+    }
+
+    // Makes a move on the board, `payees` receive each player's share of the pot if it ends the game.
+    pub fn play<'info>(&mut self, global_state: &mut Account<'info, GlobalState>, escrow: &Account<'info, Escrow>, player: Pubkey, rival: Pubkey, payees: [AccountInfo<'info>; 2], tile: &Tile) -> Result<()> {
+        // Signers that are not players of the game never have the turn.
+        let current_signer_index: usize = self.current_signer_index(Some(player));
+        let mark = Mark::from_player_index(current_signer_index).ok_or(ErrorCode::NotPlayersTurn)?;
+        // The rival account receives its share of the pot on a tie
+        require!(self.players[1 - current_signer_index] == Some(rival), ErrorCode::RivalIsNotPlayer);
+
+        // Check the turn and that the board position is valid and empty with the game engine.
+        let mut board = self.engine_board();
+        let outcome = engine::apply_move(&mut board, mark, Position::new(tile.row, tile.column)).map_err(ErrorCode::from)?;
+        let sign = Sign::from(mark);
+        self.board[tile.row as usize][tile.column as usize] = Some(sign);
 
         if GameState::InProgress == self.state {
             self.turn += 1;
         }
 
-        msg!("Player {} - Turn {}", current_signer_index + 1, self.turn);
-        msg!("Moved to {:?}", tile);
+        // Record the move in the game history.
+        self.moves.push(Move {
+            tile: tile.clone(),
+            player: current_signer_index as u8,
+            slot: Clock::get()?.slot,
+        });
+
+        emit!(MovePlayed {
+            game: escrow.game,
+            player,
+            tile: tile.clone(),
+            sign,
+            turn: self.turn,
+        });
 
         // Update the game state after the move.
-        self.update_state(global_state, game_account_info, player_account, rival_account);
+        self.update_state(global_state, escrow, player, payees, outcome);
 
         Ok(())
     }
 
-    // Function to check if three tiles form a winning line.
-    fn is_winning_trio(&self, trio: [(usize, usize); 3]) -> bool {
-        let [first, second, third] = trio;
-        self.board[first.0][first.1].is_some()
-            && self.board[first.0][first.1] == self.board[second.0][second.1]
-            && self.board[first.0][first.1] == self.board[third.0][third.1]
-    }
-
     // Function to update the game state (if there's a winner or tie).
-    fn update_state<'info>(&mut self, global_state: &mut Account<GlobalState>, game_account_info: AccountInfo<'info>, player_account: &Signer<'info>, rival_account: AccountInfo<'info>) {
-        // Check all row and column combinations.
-        for i in 0..=2 {
-            if self.is_winning_trio([(i, 0), (i, 1), (i, 2)]) {
-                msg!("Player {:?} won the game!", player_account.key());
-                self.end_game(global_state, game_account_info, Some(self.current_player(Some(player_account.key()))), player_account, rival_account, false);
-                return;
-            }
-            if self.is_winning_trio([(0, i), (1, i), (2, i)]) {
-                msg!("Player {:?} won the game!", player_account.key());
-                self.end_game(global_state, game_account_info, Some(self.current_player(Some(player_account.key()))), player_account, rival_account, false);
-                return;
+    fn update_state<'info>(&mut self, global_state: &mut Account<'info, GlobalState>, escrow: &Account<'info, Escrow>, player: Pubkey, payees: [AccountInfo<'info>; 2], outcome: Outcome) {
+        match outcome {
+            // The player who just moved completed a line.
+            Outcome::Won(_) => {
+                self.end_game(global_state, escrow, Some(self.current_player(Some(player))), payees, EndReason::LineWin);
             }
-        }
-
-        // Check diagonals.
-        if self.is_winning_trio([(0, 0), (1, 1), (2, 2)])
-            || self.is_winning_trio([(0, 2), (1, 1), (2, 0)])
-        {
-            msg!("Player {:?} won the game!", player_account.key());
-            self.end_game(global_state, game_account_info, Some(self.current_player(Some(player_account.key()))), player_account, rival_account, false);
-            return;
-        }
-
-        // If empty tiles remain, the game remains active.
-        for row in 0..=2 {
-            for column in 0..=2 {
-                if self.board[row][column].is_none() {
-                    return;
-                }
+            // If no empty tiles remain and no one has won, the game ends in a tie.
+            Outcome::Tie => {
+                self.state = GameState::Tie;
+                self.end_game(global_state, escrow, None, payees, EndReason::Tie);
             }
+            // If empty tiles remain, the game remains active.
+            Outcome::InProgress => {}
         }
-        // If no empty tiles remain and no one has won, the game ends in a tie.
-        self.state = GameState::Tie;
-        msg!("Game ends in a tie!");
-        self.end_game(global_state, game_account_info, None, player_account, rival_account, false);
     }
 
-    fn end_game<'info>(&mut self, global_state: &mut Account<GlobalState>, game_account_info: AccountInfo<'info>, winner: Option<Pubkey>, player_account: &Signer<'info>, rival_account: AccountInfo<'info>, canceled_waiting: bool) {
-        msg!("Game POT: {}.", self.pot);
-
+    // Pays the pot out of the escrow to the payees of both players and records the result.
+    fn end_game<'info>(&mut self, global_state: &mut Account<'info, GlobalState>, escrow: &Account<'info, Escrow>, winner: Option<Pubkey>, payees: [AccountInfo<'info>; 2], reason: EndReason) {
+        let pot = self.pot;
         let fee = self.pot * global_state.fee / 100;
-        let _ = game_account_info.sub_lamports(fee);
+        let _ = escrow.sub_lamports(fee);
         let _ = global_state.add_lamports(fee);
         
-        let fee_sol = fee as f64 / anchor_lang::solana_program::native_token::LAMPORTS_PER_SOL as f64;
-        msg!("{} SOL payed out as fee to the global_state account.", fee_sol);
-        
         let payout_amount = self.pot - fee;
-        let payout_amount_sol = payout_amount as f64 / anchor_lang::solana_program::native_token::LAMPORTS_PER_SOL as f64;
         self.pot -= fee;
 
         let winner_account_info;
@@ -433,40 +1401,42 @@ impl Game {
             Some(pk) => pk,
             None => return,
         };
+        // A game canceled while waiting has no player two
         let player_two = match self.players[1] {
             Some(pk) => pk,
+            None if reason == EndReason::Canceled => Pubkey::default(),
             None => return,
         };
 
-        let (player_one_account_info, player_two_account_info) = if player_one == player_account.key() {
-            (player_account.to_account_info(), rival_account.to_account_info())
-        } else {
-            (rival_account.to_account_info(), player_account.to_account_info())
-        };
+        let [player_one_account_info, mut player_two_account_info] = payees;
+        // The house plays with the treasury's lamports, so its share goes back to the global state
+        if self.house {
+            player_two_account_info = global_state.to_account_info();
+        }
 
         // Remove players from global mapping
-        global_state.remove_players_from_game(game_account_info.key());
+        global_state.remove_players_from_game(escrow.game);
 
-        // Payout
-        if canceled_waiting {
+        // Payout, keeping the amount paid to each player
+        let payouts: [u64; 2];
+        if reason == EndReason::Canceled {
             // Player 1 canceled while waiting
             self.state = GameState::Canceled;
             // Payout to player 1
-            let _ = game_account_info.sub_lamports(payout_amount);
+            let _ = escrow.sub_lamports(payout_amount);
             let _ = player_one_account_info.add_lamports(payout_amount);
-            msg!("{} SOL payed out as cancel game to player {}.", payout_amount_sol, player_one_account_info.key());
+            payouts = [payout_amount, 0];
             self.pot -= payout_amount;
             self.paid = true;
         }
-        else if winner == None {
+        else if winner.is_none() {
             // Tie
             self.state = GameState::Tie;
-            // Split payout
-            let _ = game_account_info.sub_lamports(payout_amount);
-            let _ = player_one_account_info.add_lamports(payout_amount / 2);
-            let _ = player_two_account_info.add_lamports(payout_amount / 2);
-            msg!("{} SOL payed out as tie to player {}.", payout_amount_sol / 2.0, player_one_account_info.key());
-            msg!("{} SOL payed out as tie to player {}.", payout_amount_sol / 2.0, player_two_account_info.key());
+            // Split payout, each player paying their own relayer fees
+            payouts = self.split(payout_amount);
+            let _ = escrow.sub_lamports(payout_amount);
+            let _ = player_one_account_info.add_lamports(payouts[0]);
+            let _ = player_two_account_info.add_lamports(payouts[1]);
             self.pot -= payout_amount;
             self.paid = true;
         }
@@ -474,54 +1444,815 @@ impl Game {
             // There is a winner
             if winner == Some(player_one) {
                 winner_account_info = player_one_account_info;
+                payouts = [payout_amount, 0];
             }
             else {
                 winner_account_info = player_two_account_info;
+                payouts = [0, payout_amount];
             }
             self.state = GameState::Won { winner: winner.unwrap() };
             // Payout to the winner
-            let _ = game_account_info.sub_lamports(payout_amount);
+            let _ = escrow.sub_lamports(payout_amount);
             let _ = winner_account_info.add_lamports(payout_amount);
-            msg!("{} SOL payed out as winner to player {}.", payout_amount_sol, winner_account_info.key());
             self.pot -= payout_amount;
             self.paid = true;
         }
 
         // Emit an event to log the game details before closing the account
         emit!(GameFinished {
-            player_one: player_one,
-            player_two: player_two,
-            winner: winner,
+            game: escrow.game,
+            number: self.number,
+            player_one,
+            player_two,
+            winner,
+            reason,
+            board: self.board,
+            turns: self.turn,
+            pot,
+            fee,
+            player_one_payout: payouts[0],
+            player_two_payout: payouts[1],
+            moves: self.moves.clone(),
+            timestamp: Clock::get().map(|clock| clock.unix_timestamp).unwrap_or_default(),
+        });
+    }
+}
+
+impl League {
+    pub const VERSION: u8 = 3;
+    // Maximum size of the league account.
+    pub const MAXIMUM_SIZE: usize = 8 + 1 + 32 + 8 + 1 + 8 + 8 + 1
+        + (4 + LEAGUE_MAX_PLAYERS)
+        + (4 + 32 * LEAGUE_MAX_PLAYERS)
+        + (4 + Standing::SIZE * LEAGUE_MAX_PLAYERS)
+        + (4 + Fixture::SIZE * LEAGUE_MAX_PLAYERS * (LEAGUE_MAX_PLAYERS - 1) / 2)
+        + 8;
+
+    // Schedules every player against each other once using the circle method.
+    pub fn schedule_round_robin(&mut self) {
+        let mut slots: Vec<Option<u8>> = (0..self.players.len() as u8).map(Some).collect();
+        // An odd number of players gets a bye slot
+        if slots.len() % 2 == 1 {
+            slots.push(None);
+        }
+        let slots_len = slots.len();
+
+        self.fixtures = Vec::new();
+        for round in 0..(slots_len - 1) as u8 {
+            for i in 0..slots_len / 2 {
+                if let (Some(player_one), Some(player_two)) = (slots[i], slots[slots_len - 1 - i]) {
+                    self.fixtures.push(Fixture {
+                        round,
+                        player_one,
+                        player_two,
+                        game: None,
+                        reported: false,
+                    });
+                }
+            }
+            // Keep the first slot fixed and rotate the rest
+            let last = slots.pop().unwrap();
+            slots.insert(1, last);
+        }
+    }
+
+    // Returns the player indices sorted by points, then wins, then registration order.
+    pub fn ranking(&self) -> Vec<usize> {
+        let mut ranking: Vec<usize> = (0..self.players.len()).collect();
+        ranking.sort_by(|&a, &b| {
+            let (a_standing, b_standing) = (&self.standings[a], &self.standings[b]);
+            b_standing.points.cmp(&a_standing.points).then(b_standing.wins.cmp(&a_standing.wins))
         });
+        ranking
+    }
+}
+
+impl SideBetPool {
+    pub const VERSION: u8 = 3;
+    // Size of the side pool account.
+    pub const MAXIMUM_SIZE: usize = 8 + 1 + 32 + 32 + 1 + (8 * 3) + (1 + 1) + 8 + 1 + 4;
+
+    // Total lamports bet on every outcome.
+    pub fn total(&self) -> u64 {
+        self.totals.iter().sum()
+    }
+
+    // Sets the winning outcome from the game's final state and returns the fee to collect.
+    // Without a winning outcome (or nobody backing it) every bet is refunded without fee.
+    pub fn settle(&mut self, game: Option<&Game>, fee_percentage: u64) -> Result<u64> {
+        let outcome = match game.map(|game| (&game.state, game.players[0])) {
+            Some((GameState::Won { winner }, player_one)) => {
+                if Some(*winner) == player_one { Some(SideBetOutcome::PlayerOne) } else { Some(SideBetOutcome::PlayerTwo) }
+            }
+            Some((GameState::Tie, _)) => Some(SideBetOutcome::Tie),
+            Some((GameState::Canceled, _)) | None => None,
+            _ => return Err(ErrorCode::GameNotFinished.into()),
+        };
+
+        self.outcome = outcome.filter(|&outcome| self.totals[outcome as usize] > 0);
+        self.fee = if self.outcome.is_some() { self.total() * fee_percentage / 100 } else { 0 };
+        self.settled = true;
+        Ok(self.fee)
+    }
+
+    // Returns the amount owed to a bet on a settled pool.
+    pub fn payout(&self, side_bet: &SideBet) -> u64 {
+        match self.outcome {
+            Some(outcome) if side_bet.outcome == outcome => {
+                (side_bet.amount as u128 * (self.total() - self.fee) as u128 / self.totals[outcome as usize] as u128) as u64
+            }
+            Some(_) => 0,
+            None => side_bet.amount,
+        }
     }
 }
 
 // Structure representing the global state.
 #[account]
 pub struct GlobalState {
+    pub version: u8,                    // Layout version, see `migrate_global_state`
     pub owner: Pubkey,                  // Game owner
     pub game_count: u64,                // Global game counter to ensure unique accounts.
     pub players_mapping: Vec<Pubkey>,   // Vector of player public keys
     pub games_mapping: Vec<Pubkey>,     // Vector of game PDAs
     pub fee: u64,                       // Game fee
     pub bet: u64,                       // Game bet
+    pub house: Option<Pubkey>,          // House bot key, plays against players with the fee treasury
+    pub paused: Pause,                  // Emergency switches set by the owner
+    pub hooks: Vec<Pubkey>,             // Hook programs games can call when they end
+}
+
+impl GlobalState {
+    pub const VERSION: u8 = 3;
+    // Active players the mappings can hold.
+    pub const MAX_ACTIVE_PLAYERS: usize = 32;
+    // Hook programs the allowlist can hold.
+    pub const MAX_HOOKS: usize = 8;
+    // Size of the global state account.
+    pub const MAXIMUM_SIZE: usize = 8 + 1 + 32 + 8
+        + (4 + 32 * GlobalState::MAX_ACTIVE_PLAYERS)
+        + (4 + 32 * GlobalState::MAX_ACTIVE_PLAYERS)
+        + 8 + 8 + (32 + 1) + 3
+        + (4 + 32 * GlobalState::MAX_HOOKS);
+}
+
+// Version 1 of the global state, before the version byte. Only read by `migrate_global_state`.
+#[derive(AnchorSerialize, AnchorDeserialize)]
+pub struct GlobalStateV1 {
+    pub owner: Pubkey,
+    pub game_count: u64,
+    pub players_mapping: Vec<Pubkey>,
+    pub games_mapping: Vec<Pubkey>,
+    pub fee: u64,
+    pub bet: u64,
+}
+
+impl GlobalStateV1 {
+    // Fixed size version 1 accounts were allocated with.
+    pub const SPACE: usize = 32 + 8 + 32 + 4 + 1024 + 1;
+}
+
+impl From<GlobalStateV1> for GlobalState {
+    fn from(legacy: GlobalStateV1) -> Self {
+        GlobalState {
+            version: GlobalState::VERSION,
+            owner: legacy.owner,
+            game_count: legacy.game_count,
+            players_mapping: legacy.players_mapping,
+            games_mapping: legacy.games_mapping,
+            fee: legacy.fee,
+            bet: legacy.bet,
+            house: None,
+            paused: Pause::default(),
+            hooks: Vec::new(),
+        }
+    }
+}
+
+// Version 2 of the global state, before the hook allowlist. Only read by `migrate_global_state`.
+#[derive(AnchorSerialize, AnchorDeserialize)]
+pub struct GlobalStateV2 {
+    pub version: u8,
+    pub owner: Pubkey,
+    pub game_count: u64,
+    pub players_mapping: Vec<Pubkey>,
+    pub games_mapping: Vec<Pubkey>,
+    pub fee: u64,
+    pub bet: u64,
+    pub house: Option<Pubkey>,
+    pub paused: Pause,
+}
+
+impl GlobalStateV2 {
+    // Size version 2 accounts were allocated with.
+    pub const SPACE: usize = 8 + 1 + 32 + 8
+        + (4 + 32 * GlobalState::MAX_ACTIVE_PLAYERS)
+        + (4 + 32 * GlobalState::MAX_ACTIVE_PLAYERS)
+        + 8 + 8 + (32 + 1) + 3;
+}
+
+// Game of the first program version, which held its bets itself. Only read by
+// `refund_legacy_game`.
+#[derive(AnchorSerialize, AnchorDeserialize)]
+pub struct LegacyGame {
+    pub number: u64,
+    pub players: [Option<Pubkey>; 2],
+    pub turn: u8,
+    pub board: [[Option<Sign>; 3]; 3],
+    pub state: GameState,
+    pub pot: u64,
+    pub paid: bool,
+}
+
+impl LegacyGame {
+    // Fixed size first version games were allocated with.
+    pub const SPACE: usize = 8 + (32 * 2) + 1 + (9 * (1 + 1)) + (32 + 1) + 8 + 8 + 8;
+}
+
+impl From<GlobalStateV2> for GlobalState {
+    fn from(legacy: GlobalStateV2) -> Self {
+        GlobalState {
+            version: GlobalState::VERSION,
+            owner: legacy.owner,
+            game_count: legacy.game_count,
+            players_mapping: legacy.players_mapping,
+            games_mapping: legacy.games_mapping,
+            fee: legacy.fee,
+            bet: legacy.bet,
+            house: legacy.house,
+            paused: legacy.paused,
+            hooks: Vec::new(),
+        }
+    }
+}
+
+impl GlobalState {
+    pub fn set_config(&mut self, fee: u64, bet: u64) -> Result<()> {
+        require!(fee <= 100, ErrorCode::InvalidFee);
+        self.fee = fee;
+        self.bet = bet;
+        Ok(())
+    }
+
+    pub fn set_hook(&mut self, hook: Pubkey, allowed: bool) -> Result<()> {
+        if !allowed {
+            self.hooks.retain(|allowed_hook| *allowed_hook != hook);
+        } else if !self.hooks.contains(&hook) {
+            require!(self.hooks.len() < GlobalState::MAX_HOOKS, ErrorCode::HookListFull);
+            self.hooks.push(hook);
+        }
+        Ok(())
+    }
+
+    // Moves matchmaking on to a new game account when `game` is its current one. League and
+    // hosted games have their own accounts and leave matchmaking alone.
+    fn advance_matchmaking(&mut self, game: Pubkey) {
+        let (current, _) = Pubkey::find_program_address(&[GAME_SEED, &self.game_count.to_le_bytes()], &crate::ID);
+        if game == current {
+            self.game_count += 1;
+        }
+    }
+
+    // Checks that a new game may call `hook`.
+    fn check_hook(&self, hook: Option<Pubkey>) -> Result<()> {
+        if let Some(hook) = hook {
+            require!(self.hooks.contains(&hook), ErrorCode::HookNotAllowed);
+        }
+        Ok(())
+    }
+}
+
+// Emergency switches, each one stops a group of instructions.
+#[derive(AnchorSerialize, AnchorDeserialize, Copy, Clone, Default, PartialEq, Eq, Debug)]
+pub struct Pause {
+    pub new_games: bool,        // setup_game, setup_league_game and challenge_house
+    pub moves: bool,            // play and cancel_game
+    pub withdrawals: bool,      // withdraw_fees, claim_side_bet and close_side_pool
 }
 
 // Structure representing each game's state.
 #[account]
 pub struct Game {
-    number: u64,
-    players: [Option<Pubkey>; 2],   // Public keys of the players (64 bytes).
-    turn: u8,                       // Current turn number (1 byte).
-    board: [[Option<Sign>; 3]; 3],  // Board state (9 positions with 2 bytes per cell).
-    state: GameState,               // Current game state (won, tie, active).
-    pot: u64,                       // Game pot
-    paid: bool,                     // Game is paid
+    pub version: u8,                    // Layout version
+    pub number: u64,
+    pub players: [Option<Pubkey>; 2],   // Public keys of the players (64 bytes).
+    pub turn: u8,                       // Current turn number (1 byte).
+    pub board: [[Option<Sign>; 3]; 3],  // Board state (9 positions with 2 bytes per cell).
+    pub state: GameState,               // Current game state (won, tie, active).
+    pub pot: u64,                       // Game pot
+    pub paid: bool,                     // Game is paid
+    pub league: Option<Pubkey>,         // League the game belongs to, if any
+    pub moves: Vec<Move>,               // Ordered move history (up to 9 moves).
+    pub house: bool,                    // Player two is the house, its share goes to the treasury.
+    pub hook: Option<Pubkey>,           // Program called when the game ends, see `call_hook`
+    pub hook_called: bool,              // The hook was called by `call_game_hook`
+    pub relayer_fees: [u64; 2],         // Relayer fees each player paid from the pot
 }
 
-// Enum for possible game states.
+// Structure representing the vault holding a game's wagers, kept apart from the game rent.
+#[account]
+pub struct Escrow {
+    pub version: u8,                // Layout version
+    pub game: Pubkey,               // Game the wagers belong to
+    pub rent_payer: Pubkey,         // Funded the game and escrow rent, refunded when the game is closed
+    pub from_balance: [bool; 2],    // Whether each player bet from their balance, which then gets the payout
+    pub settled_at: i64,            // Unix timestamp of the settlement, 0 while the game runs
+}
+
+impl Escrow {
+    pub const VERSION: u8 = 2;
+    // Size of the escrow account.
+    pub const MAXIMUM_SIZE: usize = 8 + 1 + 32 + 32 + 2 + 8;
+
+    // Records the rent payer of a new escrow. A league game set up again after a cancel keeps
+    // the escrow, and the rent payer, of its first setup.
+    fn open(&mut self, game: Pubkey, rent_payer: Pubkey) {
+        if self.version == 0 {
+            self.version = Escrow::VERSION;
+            self.game = game;
+            self.rent_payer = rent_payer;
+        }
+        self.from_balance = [false; 2];
+        self.settled_at = 0;
+    }
+
+    fn settle(&mut self) -> Result<()> {
+        self.settled_at = Clock::get()?.unix_timestamp;
+        Ok(())
+    }
+
+    // Account paid for the player at `index`: their balance when they bet from it, else `wallet`.
+    fn payee<'info>(&self, index: usize, wallet: AccountInfo<'info>, balance: Option<&Account<'info, PlayerBalance>>) -> Result<AccountInfo<'info>> {
+        if !self.from_balance[index] {
+            return Ok(wallet);
+        }
+        Ok(balance.ok_or(ErrorCode::BalanceRequired)?.to_account_info())
+    }
+
+    // Payees of both players, in game order, from the signer and the rival of an instruction.
+    fn payees<'info>(&self, game: &Game, signer: (AccountInfo<'info>, Option<&Account<'info, PlayerBalance>>), rival: (AccountInfo<'info>, Option<&Account<'info, PlayerBalance>>)) -> Result<[AccountInfo<'info>; 2]> {
+        // Signers that are not players fail later, nothing is paid to them
+        let Some(index) = game.players.iter().position(|player| *player == Some(signer.0.key())) else {
+            return Ok([signer.0, rival.0]);
+        };
+        let signer_payee = self.payee(index, signer.0, signer.1)?;
+        let rival_payee = self.payee(1 - index, rival.0, rival.1)?;
+        Ok(if index == 0 { [signer_payee, rival_payee] } else { [rival_payee, signer_payee] })
+    }
+}
+
+// Moves a player's bet into the escrow, from their balance when given, and records where it came
+// from so the payout goes back the same way.
+fn stake<'info>(escrow: &mut Account<'info, Escrow>, index: usize, bet: u64, player: &Signer<'info>, balance: Option<&Account<'info, PlayerBalance>>, system: &Program<'info, System>) -> Result<()> {
+    match balance {
+        Some(balance) => {
+            require!(PlayerBalance::available(&balance.to_account_info())? >= bet, ErrorCode::InsufficientFunds);
+            balance.sub_lamports(bet)?;
+            escrow.add_lamports(bet)?;
+        }
+        None => {
+            require!(player.lamports() >= bet, ErrorCode::InsufficientFunds);
+            let cpi_context = CpiContext::new(
+                system.to_account_info(),
+                system_program::Transfer {
+                    from: player.to_account_info(),
+                    to: escrow.to_account_info(),
+                },
+            );
+            system_program::transfer(cpi_context, bet)?;
+        }
+    }
+    escrow.from_balance[index] = balance.is_some();
+    Ok(())
+}
+
+// Plays a tile for a player of an active game, and settles the escrow when the move ends the game.
+fn make_move<'info>(global_state: &mut Account<'info, GlobalState>, game: &mut Account<'info, Game>, escrow: &mut Account<'info, Escrow>, player: Pubkey, rival: Pubkey, payees: [AccountInfo<'info>; 2], tile: &Tile) -> Result<()> {
+    // Check if the player is in an active game
+    require!(global_state.find_game_from_player(player).is_ok(), ErrorCode::PlayerHasNotAnActiveGame);
+    // Check if the current game is NOT over
+    require!(!game.is_over(), ErrorCode::GameAlreadyOver);
+    // Check if the current game is in progress
+    require!(game.is_in_progress(), ErrorCode::GameNotInProgress);
+    // Make the move if it is the player's turn
+    game.play(global_state, escrow, player, rival, payees, tile)?;
+    if game.is_over() {
+        escrow.settle()?;
+    }
+    Ok(())
+}
+
+// Cancels a waiting or in progress game and returns each player's bet without fee, to their
+// balance when they bet from it. A waiting game has no player two, any account can stand in for it.
+fn refund_game<'info>(global_state: &mut Account<'info, GlobalState>, game: &mut Account<'info, Game>, escrow: &mut Account<'info, Escrow>, players: [AccountInfo<'info>; 2], balances: [Option<&Account<'info, PlayerBalance>>; 2]) -> Result<()> {
+    require!(game.is_waiting() || game.is_in_progress(), ErrorCode::GameNotInProgress);
+    let player_one = game.players[0].ok_or(ErrorCode::PlayerNotFound)?;
+    require!(players[0].key() == player_one, ErrorCode::RivalIsNotPlayer);
+    if let Some(player_two) = game.players[1] {
+        require!(players[1].key() == player_two, ErrorCode::RivalIsNotPlayer);
+    }
+    // Proposals pass the balances unchecked
+    for (player, balance) in players.iter().zip(balances) {
+        if let Some(balance) = balance {
+            require!(balance.owner == player.key(), ErrorCode::InvalidRefundAccounts);
+        }
+    }
+
+    // Both players bet the same amount, only player one did while waiting
+    let pot = game.pot;
+    let payouts = if game.players[1].is_some() { game.split(pot) } else { [pot, 0] };
+    let [player_one_info, player_two_info] = players;
+    escrow.sub_lamports(pot)?;
+    escrow.payee(0, player_one_info, balances[0])?.add_lamports(payouts[0])?;
+    // The house bet goes back to the treasury
+    if game.house {
+        global_state.add_lamports(payouts[1])?;
+    } else {
+        escrow.payee(1, player_two_info, balances[1])?.add_lamports(payouts[1])?;
+    }
+
+    game.pot = 0;
+    game.paid = true;
+    // A refunded waiting game can no longer be joined
+    if game.is_waiting() {
+        global_state.advance_matchmaking(game.key());
+    }
+    game.state = GameState::Canceled;
+    escrow.settle()?;
+    global_state.remove_players_from_game(game.key());
+    msg!("Game {} refunded: {:?}", game.key(), payouts);
+
+    emit!(GameFinished {
+        game: game.key(),
+        number: game.number,
+        player_one,
+        player_two: game.players[1].unwrap_or_default(),
+        winner: None,
+        reason: EndReason::EmergencyRefund,
+        board: game.board,
+        turns: game.turn,
+        pot,
+        fee: 0,
+        player_one_payout: payouts[0],
+        player_two_payout: payouts[1],
+        moves: game.moves.clone(),
+        timestamp: Clock::get()?.unix_timestamp,
+    });
+    Ok(())
+}
+
+// Settles the side pool of a game about to be closed, if it has an open one. Its bets would be
+// refunded once the game is gone.
+fn settle_side_pool<'info>(global_state: &mut Account<'info, GlobalState>, game: &Game, side_pool: &UncheckedAccount<'info>) -> Result<()> {
+    let side_pool_info = side_pool.to_account_info();
+    if side_pool_info.data_is_empty() {
+        return Ok(());
+    }
+    require!(side_pool_info.owner == &crate::ID, ErrorCode::AccountNotFound);
+    let mut side_pool = SideBetPool::try_deserialize(&mut &side_pool_info.data.borrow()[..])?;
+    if !side_pool.settled {
+        let fee = side_pool.settle(Some(game), global_state.fee)?;
+        side_pool.try_serialize(&mut &mut side_pool_info.data.borrow_mut()[..])?;
+        side_pool_info.sub_lamports(fee)?;
+        global_state.add_lamports(fee)?;
+        msg!("Side pool settled as {:?}, {} lamports payed out as fee.", side_pool.outcome, fee);
+    }
+    Ok(())
+}
+
+// Settles a side pool from the game it was opened on, if not settled yet. A game account closed
+// before settlement refunds every bet.
+fn settle_side_pool_from<'info>(global_state: &mut Account<'info, GlobalState>, side_pool: &mut Account<'info, SideBetPool>, game: &UncheckedAccount<'info>) -> Result<()> {
+    if side_pool.settled {
+        return Ok(());
+    }
+    let game_account_info = game.to_account_info();
+    let game = if game_account_info.data_is_empty() {
+        None
+    } else {
+        require!(game_account_info.owner == &crate::ID, ErrorCode::AccountNotFound);
+        Some(Game::try_deserialize(&mut &game_account_info.data.borrow()[..])?)
+    };
+
+    let fee = side_pool.settle(game.as_ref(), global_state.fee)?;
+    side_pool.sub_lamports(fee)?;
+    global_state.add_lamports(fee)?;
+    msg!("Side pool settled as {:?}, {} lamports payed out as fee.", side_pool.outcome, fee);
+    Ok(())
+}
+
+// Checks that the result of a league game is recorded before the game is closed. A league fixture
+// canceled before it started is never recorded, nor are the fixtures of a refunded league.
+fn require_fixture_reported(game: &Game, league: Option<&Account<League>>) -> Result<()> {
+    if let (Some(league_key), false) = (game.league, game.state == GameState::Canceled) {
+        let league = league.filter(|league| league.key() == league_key).ok_or(ErrorCode::FixtureNotReported)?;
+        let fixture = league.fixtures.get(game.number as usize).ok_or(ErrorCode::FixtureNotFound)?;
+        require!(fixture.reported || league.state == LeagueState::Refunded, ErrorCode::FixtureNotReported);
+    }
+    Ok(())
+}
+
+// Calls `hook` with the `GameResult` of a game that ended and was paid out. The remaining
+// accounts of the instruction are the hook program, the hook authority and then the accounts the
+// hook needs, passed on without their signatures. The hook authority is a PDA of the hook
+// program key and signs the call, so a hook can tell the call comes from this program for one
+// of its own games, and no hook can sign a call to another.
+fn call_hook<'info>(game: &mut Account<'info, Game>, hook: Pubkey, accounts: &[AccountInfo<'info>]) -> Result<()> {
+    let [program, authority, hook_accounts @ ..] = accounts else {
+        return Err(ErrorCode::InvalidHookProgram.into());
+    };
+    require!(program.key() == hook, ErrorCode::InvalidHookProgram);
+    let (authority_key, bump) = Pubkey::find_program_address(&[HOOK_AUTHORITY_SEED, hook.as_ref()], &crate::ID);
+    require!(authority.key() == authority_key, ErrorCode::InvalidHookAuthority);
+
+    // The hook reads the game as settled
+    game.hook_called = true;
+    game.exit(&crate::ID)?;
+    let mut data = hash(b"global:on_game_end").to_bytes()[..8].to_vec();
+    game.result(game.key()).serialize(&mut data)?;
+    let mut metas = vec![AccountMeta::new_readonly(game.key(), false), AccountMeta::new_readonly(authority_key, true)];
+    metas.extend(hook_accounts.iter().map(|account| AccountMeta { pubkey: account.key(), is_signer: false, is_writable: account.is_writable }));
+    let mut infos = vec![game.to_account_info(), authority.clone(), program.clone()];
+    infos.extend_from_slice(hook_accounts);
+    invoke_signed(&Instruction { program_id: hook, accounts: metas, data }, &infos, &[&[HOOK_AUTHORITY_SEED, hook.as_ref(), &[bump]]])?;
+
+    emit!(HookCalled { game: game.key(), hook });
+    Ok(())
+}
+
+// Checks that the instruction before the current one is an Ed25519 program instruction verifying
+// one signature of `message` by `signer`, with the key, the signature and the message all in its
+// own data.
+fn verify_signed_message(instructions: &AccountInfo, signer: &Pubkey, message: &[u8]) -> Result<()> {
+    let current = load_current_index_checked(instructions)?;
+    require!(current > 0, ErrorCode::InvalidRelayedMove);
+    let verify = load_instruction_at_checked(current as usize - 1, instructions)?;
+    require!(verify.program_id == ed25519_program::ID, ErrorCode::InvalidRelayedMove);
+
+    // A signature count and a padding byte, then the offsets of the first signature
+    let data = &verify.data;
+    require!(data.len() >= 16 && data[0] == 1, ErrorCode::InvalidRelayedMove);
+    let read = |at: usize| u16::from_le_bytes([data[at], data[at + 1]]);
+    let (key_offset, message_offset, message_size) = (read(6) as usize, read(10) as usize, read(12) as usize);
+    // Offsets into other instructions would let the verified data differ from what is checked here
+    require!([read(4), read(8), read(14)].iter().all(|index| *index == u16::MAX), ErrorCode::InvalidRelayedMove);
+    let key = data.get(key_offset..key_offset + 32).ok_or(ErrorCode::InvalidRelayedMove)?;
+    let signed = data.get(message_offset..message_offset + message_size).ok_or(ErrorCode::InvalidRelayedMove)?;
+    require!(key == signer.as_ref() && signed == message, ErrorCode::InvalidRelayedMove);
+    Ok(())
+}
+
+// Structure representing a player's internal balance. Bets and payouts move lamports between the
+// balance and the game escrows, without transfers from or to the wallet.
+#[account]
+pub struct PlayerBalance {
+    pub version: u8,                // Layout version
+    pub owner: Pubkey,              // Wallet that deposits and withdraws
+}
+
+impl PlayerBalance {
+    pub const VERSION: u8 = 2;
+    // Size of the balance account.
+    pub const MAXIMUM_SIZE: usize = 8 + 1 + 32;
+
+    // Lamports of a balance account above its rent-exempt reserve.
+    pub fn available(info: &AccountInfo) -> Result<u64> {
+        Ok(info.lamports().saturating_sub(Rent::get()?.minimum_balance(info.data_len())))
+    }
+}
+
+// Structure representing a session key, an ephemeral key the player authorized to sign their
+// moves so that the wallet is not prompted on every tile.
+#[account]
+pub struct Session {
+    pub version: u8,                // Layout version
+    pub authority: Pubkey,          // Player the key signs for
+    pub key: Pubkey,                // Ephemeral key
+    pub game: Option<Pubkey>,       // Only game the key can act in, any game when None
+    pub expires_at: i64,            // Unix timestamp after which the key is rejected
+    pub spending_cap: u64,          // Lamports the key can put at stake in total
+    pub spent: u64,                 // Lamports put at stake through the key so far
+    pub charged_game: Option<Pubkey>, // Last game whose stake was charged to the key
+}
+
+impl Session {
+    pub const VERSION: u8 = 2;
+    // Size of the session account.
+    pub const MAXIMUM_SIZE: usize = 8 + 1 + 32 + 32 + (32 + 1) + 8 + 8 + 8 + (32 + 1);
+
+    // Checks that the player signed, either directly or through a live session key scoped to
+    // the game, and charges `stake`, the lamports the player can lose in the game, to the
+    // session. Each game is charged once, by the first move or cancel the key signs in it.
+    // `is_none_or` is newer than the Solana toolchain.
+    #[allow(clippy::unnecessary_map_or)]
+    pub fn authorize(player: &AccountInfo, session: Option<&mut Account<Session>>, session_key: Option<&Signer>, game: Pubkey, stake: u64) -> Result<()> {
+        if player.is_signer {
+            return Ok(());
+        }
+        let (session, session_key) = match (session, session_key) {
+            (Some(session), Some(session_key)) => (session, session_key),
+            _ => return Err(ErrorCode::InvalidSession.into()),
+        };
+        require!(session.authority == player.key() && session.key == session_key.key(), ErrorCode::InvalidSession);
+        require!(session.game.map_or(true, |scope| scope == game), ErrorCode::InvalidSession);
+        require!(Clock::get()?.unix_timestamp < session.expires_at, ErrorCode::SessionExpired);
+        // Players are in one game at a time, so the key moves from game to game
+        if session.charged_game != Some(game) {
+            let spent = session.spent.saturating_add(stake);
+            require!(spent <= session.spending_cap, ErrorCode::SessionCapExceeded);
+            session.spent = spent;
+            session.charged_game = Some(game);
+        }
+        Ok(())
+    }
+}
+
+// Structure representing the next nonce of a player's relayed moves.
+#[account]
+pub struct RelayNonce {
+    pub version: u8,                // Layout version
+    pub player: Pubkey,             // Player signing the moves
+    pub nonce: u64,                 // Nonce the next relayed move must carry
+}
+
+impl RelayNonce {
+    pub const VERSION: u8 = 2;
+    // Size of the relay nonce account.
+    pub const MAXIMUM_SIZE: usize = 8 + 1 + 32 + 8;
+}
+
+// Structure representing a round-robin league.
+#[account]
+pub struct League {
+    pub version: u8,                // Layout version
+    pub authority: Pubkey,          // League organizer
+    pub id: u64,                    // League id chosen by the organizer
+    pub state: LeagueState,         // Current league state
+    pub entry_fee: u64,             // Lamports each player pays to join
+    pub pool: u64,                  // Season's pool
+    pub paid: bool,                 // Pool is paid
+    pub prize_shares: Vec<u8>,      // % of the pool for each final position
+    pub players: Vec<Pubkey>,       // Registered players
+    pub standings: Vec<Standing>,   // Standings, indexed as `players`
+    pub fixtures: Vec<Fixture>,     // Round-robin schedule
+    pub deadline: i64,              // End of the season, set when the league starts
+}
+
+// Enum for possible league states.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, PartialEq, Eq, Debug, Default)]
+pub enum LeagueState {
+    #[default]
+    Registering,                // Players can join
+    InProgress,                 // Fixtures are being played
+    Finished,                   // Pool payed out
+    Refunded,                   // Season over with fixtures pending, entry fees refunded
+}
+
+// Structure representing a player's league record.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Default, Debug)]
+pub struct Standing {
+    pub points: u16,
+    pub wins: u8,
+    pub ties: u8,
+    pub losses: u8,
+}
+
+impl Standing {
+    pub const SIZE: usize = 2 + 1 + 1 + 1;
+}
+
+// Structure representing a scheduled league game.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug)]
+pub struct Fixture {
+    pub round: u8,
+    pub player_one: u8,             // Index in the league players
+    pub player_two: u8,             // Index in the league players
+    pub game: Option<Pubkey>,       // Game PDA once set up
+    pub reported: bool,             // Result added to the standings
+}
+
+impl Fixture {
+    pub const SIZE: usize = 1 + 1 + 1 + (32 + 1) + 1;
+}
+
+// Structure representing the spectators' parimutuel pool of a game.
+#[account]
+pub struct SideBetPool {
+    pub version: u8,                        // Layout version
+    pub game: Pubkey,                       // Game the bets are placed on
+    pub payer: Pubkey,                      // Paid the pool rent, refunded when it is closed
+    pub cutoff_turn: u8,                    // Bets are accepted before this turn
+    pub totals: [u64; 3],                   // Lamports bet on each outcome
+    pub outcome: Option<SideBetOutcome>,    // Winning outcome, None refunds every bet
+    pub fee: u64,                           // Fee taken at settlement
+    pub settled: bool,                      // Pool is settled
+    pub open_bets: u32,                     // Bets placed and not paid out yet
+}
+
+// Structure representing a spectator's bet.
+#[account]
+pub struct SideBet {
+    pub version: u8,                // Layout version
+    pub pool: Pubkey,               // Side pool the bet belongs to
+    pub bettor: Pubkey,             // Spectator
+    pub outcome: SideBetOutcome,    // Backed outcome
+    pub amount: u64,                // Lamports bet
+}
+
+impl SideBet {
+    pub const VERSION: u8 = 2;
+    // Size of the side bet account.
+    pub const MAXIMUM_SIZE: usize = 8 + 1 + 32 + 32 + 1 + 8;
+}
+
+// Enum for the outcomes spectators can bet on.
+#[derive(AnchorSerialize, AnchorDeserialize, Copy, Clone, PartialEq, Eq, Debug)]
+pub enum SideBetOutcome {
+    PlayerOne,
+    PlayerTwo,
+    Tie,
+}
+
+// Structure representing the M-of-N admin set owning the global state.
+#[account]
+pub struct AdminSet {
+    pub version: u8,                // Layout version
+    pub signers: Vec<Pubkey>,       // Admins allowed to propose and approve
+    pub threshold: u8,              // Approvals needed to execute a proposal
+    pub timelock: i64,              // Seconds between the last needed approval and the execution
+    pub proposal_count: u64,        // Proposal counter to ensure unique accounts
+    pub admins_version: u64,        // Bumped whenever the signers, threshold or timelock change
+}
+
+impl AdminSet {
+    pub const VERSION: u8 = 3;
+    // Size of the admin set account.
+    pub const MAXIMUM_SIZE: usize = 8 + 1 + (4 + 32 * ADMIN_MAX_SIGNERS) + 1 + 8 + 8 + 8;
+
+    fn configure(&mut self, signers: Vec<Pubkey>, threshold: u8, timelock: i64) -> Result<()> {
+        require!(signers.len() <= ADMIN_MAX_SIGNERS, ErrorCode::InvalidAdminSet);
+        require!(threshold >= 1 && threshold as usize <= signers.len(), ErrorCode::InvalidAdminSet);
+        require!(timelock >= 0, ErrorCode::InvalidAdminSet);
+        let duplicates = signers.iter().enumerate().any(|(index, signer)| signers[..index].contains(signer));
+        require!(!duplicates, ErrorCode::InvalidAdminSet);
+
+        self.signers = signers;
+        self.threshold = threshold;
+        self.timelock = timelock;
+        self.admins_version += 1;
+        Ok(())
+    }
+}
+
+// Structure representing a proposed admin action.
+#[account]
+pub struct Proposal {
+    pub version: u8,                // Layout version
+    pub id: u64,                    // Proposal number in the admin set
+    pub proposer: Pubkey,           // Admin who proposed the action
+    pub action: AdminAction,        // Action run on execution
+    pub approvals: Vec<Pubkey>,     // Admins who approved, the proposer included
+    pub approved_at: Option<i64>,   // Time the threshold was reached, the timelock starts then
+    pub executed: bool,             // Action already run
+    pub admins_version: u64,        // Admin set version it was proposed under, see `AdminSet`
+}
+
+impl Proposal {
+    pub const VERSION: u8 = 3;
+    // Size of the proposal account.
+    pub const MAXIMUM_SIZE: usize = 8 + 1 + 8 + 32 + AdminAction::SIZE + (4 + 32 * ADMIN_MAX_SIGNERS) + (1 + 8) + 1 + 8;
+
+    fn approve(&mut self, signer: Pubkey, admin_set: &AdminSet) -> Result<()> {
+        self.approvals.push(signer);
+        if self.approved_at.is_none() && self.approvals.len() >= admin_set.threshold as usize {
+            self.approved_at = Some(Clock::get()?.unix_timestamp);
+        }
+        Ok(())
+    }
+}
+
+// Enum for the admin actions run through proposals.
 #[derive(AnchorSerialize, AnchorDeserialize, Clone, PartialEq, Eq, Debug)]
+pub enum AdminAction {
+    WithdrawFees { amount: u64, recipient: Pubkey },
+    SetConfig { fee: u64, bet: u64 },
+    SetPaused(Pause),
+    SetHouse(Option<Pubkey>),
+    SetOwner(Pubkey),                       // Hands the global state back to a single key
+    SetAdmins { signers: Vec<Pubkey>, threshold: u8, timelock: i64 },
+    SetHook { hook: Pubkey, allowed: bool },
+    EmergencyRefund { game: Pubkey },       // See `emergency_refund`
+}
+
+impl AdminAction {
+    // Size of the largest action, `SetAdmins`.
+    pub const SIZE: usize = 1 + (4 + 32 * ADMIN_MAX_SIGNERS) + 1 + 8;
+}
+
+// Enum for possible game states.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, PartialEq, Eq, Debug, Default)]
 pub enum GameState {
+    #[default]
     Uninitialized,              // The game is not initialized yet
     Waiting,                    // The game is waiting for player 2.
     InProgress,                 // The game is in progress.
@@ -530,30 +2261,129 @@ pub enum GameState {
     Canceled,                   // Canceled
 }
 
+// Enum for the reasons a game can end.
+#[derive(AnchorSerialize, AnchorDeserialize, Copy, Clone, PartialEq, Eq, Debug)]
+pub enum EndReason {
+    LineWin,                    // A player completed a row, column or diagonal.
+    Tie,                        // The board is full without a winner.
+    Resignation,                // A player canceled the game in progress.
+    Timeout,                    // A player ran out of time (reserved, no timeouts are enforced yet).
+    Canceled,                   // Player 1 canceled while waiting for player 2.
+    EmergencyRefund,            // The owner refunded both bets without fee.
+}
+
 // Enum representing player signs (X or O).
-#[derive(AnchorSerialize, AnchorDeserialize, FromPrimitive, ToPrimitive, Copy, Clone, PartialEq, Eq)]
+#[derive(AnchorSerialize, AnchorDeserialize, Copy, Clone, PartialEq, Eq, Debug)]
 pub enum Sign {
     X,
     O,
 }
 
+impl From<Mark> for Sign {
+    fn from(mark: Mark) -> Self {
+        match mark {
+            Mark::X => Sign::X,
+            Mark::O => Sign::O,
+        }
+    }
+}
+
+impl From<Sign> for Mark {
+    fn from(sign: Sign) -> Self {
+        match sign {
+            Sign::X => Mark::X,
+            Sign::O => Mark::O,
+        }
+    }
+}
+
 // Structure representing a tile on the board.
-#[derive(AnchorSerialize, AnchorDeserialize, Debug)]
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug)]
 pub struct Tile {
-    row: u8,        // Tile row (0-2).
-    column: u8,     // Tile column (0-2).
+    pub row: u8,        // Tile row (0-2).
+    pub column: u8,     // Tile column (0-2).
+}
+
+// Structure representing the result of a game, returned by `game_result` and `settle_hosted_game`.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug, PartialEq, Eq)]
+pub struct GameResult {
+    pub game: Pubkey,                   // Game account
+    pub number: u64,                    // Game number, or the host's id of a hosted game
+    pub players: [Option<Pubkey>; 2],   // Players, X first
+    pub state: GameState,               // State of the game, with the winner of a won game
+    pub turns: u8,                      // Number of turns played
+}
+
+// Structure representing a move signed off-chain by a player for a relayer to submit.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug)]
+pub struct RelayedMove {
+    pub game: Pubkey,           // Game the move is played in
+    pub turn: u8,               // Turn of the game the move is played at
+    pub tile: Tile,             // Tile played
+    pub nonce: u64,             // Next nonce of the player
+    pub relayer_fee: u64,       // Lamports the relayer is reimbursed from the pot
+}
+
+impl RelayedMove {
+    // Prefix of the signed messages, so they cannot be mistaken for other signed data.
+    pub const MESSAGE_PREFIX: &'static [u8] = b"tic-tac-toe relayed move:";
+
+    // Message the player signs: the prefix followed by the serialized move.
+    pub fn message(&self) -> Vec<u8> {
+        let mut message = Self::MESSAGE_PREFIX.to_vec();
+        // Serializing into a vector cannot fail
+        let _ = self.serialize(&mut message);
+        message
+    }
+}
+
+// Structure representing a move in the game history.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug)]
+pub struct Move {
+    pub tile: Tile,     // Tile played.
+    pub player: u8,     // Index of the player who moved (0 or 1).
+    pub slot: u64,      // Slot the move was played at.
+}
+
+impl Move {
+    pub const SIZE: usize = 2 + 1 + 8;
 }
 
 // Account setup for `initialize_global_state` instruction.
 #[derive(Accounts)]
 pub struct InitializeGlobalState<'info> {
-    #[account(init, payer = payer, space = 32 + 8 + 32 + 4 + 1024 + 1, seeds = [GLOBAL_STATE_SEED], bump)]
+    #[account(init, payer = payer, space = GlobalState::MAXIMUM_SIZE, seeds = [GLOBAL_STATE_SEED], bump)]
     pub global_state: Account<'info, GlobalState>,
     #[account(mut)]
     pub payer: Signer<'info>,
     pub system_program: Program<'info, System>,
 }
 
+#[derive(Accounts)]
+pub struct MigrateGlobalState<'info> {
+    /// CHECK: Read with the layout of its version in the logic
+    #[account(mut, seeds = [GLOBAL_STATE_SEED], bump, owner = crate::ID)]
+    pub global_state: UncheckedAccount<'info>,
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct RefundLegacyGame<'info> {
+    #[account(mut)]
+    pub global_state: Account<'info, GlobalState>,
+    /// CHECK: Read with the first game layout in the logic
+    #[account(mut, owner = crate::ID)]
+    pub game: UncheckedAccount<'info>,
+    /// CHECK: Checked against the game players in the logic
+    #[account(mut)]
+    pub player_one: AccountInfo<'info>,
+    /// CHECK: Checked against the game players in the logic
+    #[account(mut)]
+    pub player_two: AccountInfo<'info>,
+}
+
 // Account setup for `setup_game` instruction.
 #[derive(Accounts)]
 pub struct SetupGame<'info> {
@@ -561,8 +2391,12 @@ pub struct SetupGame<'info> {
     pub global_state: Account<'info, GlobalState>,      // Global state containing the game counter.
     #[account(init_if_needed, payer = player, space = Game::MAXIMUM_SIZE, seeds = [GAME_SEED, &global_state.game_count.to_le_bytes()], bump)]
     pub game: Account<'info, Game>,                     // New PDA account for the game.
+    #[account(init_if_needed, payer = player, space = Escrow::MAXIMUM_SIZE, seeds = [ESCROW_SEED, game.key().as_ref()], bump)]
+    pub escrow: Account<'info, Escrow>,                 // Vault holding the game wagers.
     #[account(mut)]
     pub player: Signer<'info>,                          // Player.
+    #[account(mut, seeds = [BALANCE_SEED, player.key().as_ref()], bump)]
+    pub balance: Option<Account<'info, PlayerBalance>>, // Balance funding the bet instead of the wallet.
     pub system_program: Program<'info, System>,         // Use of the system program.
 }
 
@@ -572,23 +2406,62 @@ pub struct CancelGame<'info> {
     pub global_state: Account<'info, GlobalState>,      // Global state containing the game counter.
     #[account(mut)]
     pub game: Account<'info, Game>,
+    #[account(mut, seeds = [ESCROW_SEED, game.key().as_ref()], bump)]
+    pub escrow: Account<'info, Escrow>,                 // Vault holding the game wagers.
+    /// CHECK: Signs, or is signed for by `session_key`, checked in the logic
     #[account(mut)]
-    pub signer: Signer<'info>,
+    pub signer: AccountInfo<'info>,                     // Player canceling or resigning.
     /// CHECK: The player is checked in the logic
     #[account(mut)]
     pub rival: AccountInfo<'info>,                      // Rival player.
+    #[account(mut, seeds = [BALANCE_SEED, signer.key().as_ref()], bump)]
+    pub signer_balance: Option<Account<'info, PlayerBalance>>,
+    #[account(mut, seeds = [BALANCE_SEED, rival.key().as_ref()], bump)]
+    pub rival_balance: Option<Account<'info, PlayerBalance>>,
+    #[account(mut)]
+    pub session: Option<Account<'info, Session>>,       // Session of the player, when the key signs.
+    pub session_key: Option<Signer<'info>>,
     // pub system_program: Program<'info, System>,
 }
 
 #[derive(Accounts)]
 pub struct CloseGameAccount<'info> {
-    #[account(mut, close = signer)]
+    #[account(mut)]
+    pub global_state: Account<'info, GlobalState>,      // Global state collecting the side pool fee.
+    #[account(mut, close = rent_payer)]
     pub game: Account<'info, Game>,
+    // The pot is paid out at settlement, so only the escrow rent is left
+    #[account(mut, seeds = [ESCROW_SEED, game.key().as_ref()], bump, has_one = rent_payer, close = rent_payer)]
+    pub escrow: Account<'info, Escrow>,
     #[account(mut)]
-    pub signer: Signer<'info>,
+    pub signer: Signer<'info>,                          // Player one.
+    /// CHECK: Checked against the escrow rent payer
+    #[account(mut)]
+    pub rent_payer: AccountInfo<'info>,
+    /// CHECK: Side pool address of the game, settled in the logic if it exists
+    #[account(mut, seeds = [SIDE_POOL_SEED, game.key().as_ref()], bump)]
+    pub side_pool: UncheckedAccount<'info>,
+    pub league: Option<Account<'info, League>>,         // League of a league game.
     // pub system_program: Program<'info, System>,
 }
 
+#[derive(Accounts)]
+pub struct CloseFinishedGame<'info> {
+    #[account(mut)]
+    pub global_state: Account<'info, GlobalState>,      // Global state collecting the side pool fee.
+    #[account(mut, close = rent_payer)]
+    pub game: Account<'info, Game>,
+    #[account(mut, seeds = [ESCROW_SEED, game.key().as_ref()], bump, has_one = rent_payer, close = rent_payer)]
+    pub escrow: Account<'info, Escrow>,
+    /// CHECK: Checked against the escrow rent payer
+    #[account(mut)]
+    pub rent_payer: AccountInfo<'info>,
+    /// CHECK: Side pool address of the game, settled in the logic if it exists
+    #[account(mut, seeds = [SIDE_POOL_SEED, game.key().as_ref()], bump)]
+    pub side_pool: UncheckedAccount<'info>,
+    pub league: Option<Account<'info, League>>,         // League of a league game.
+}
+
 // Account setup for `play` instruction.
 #[derive(Accounts)]
 pub struct Play<'info> {
@@ -596,14 +2469,127 @@ pub struct Play<'info> {
     pub global_state: Account<'info, GlobalState>,      // Global state containing the game counter.
     #[account(mut)]
     pub game: Account<'info, Game>,                     // New PDA account for the game.
+    #[account(mut, seeds = [ESCROW_SEED, game.key().as_ref()], bump)]
+    pub escrow: Account<'info, Escrow>,                 // Vault holding the game wagers.
+    /// CHECK: Signs, or is signed for by `session_key`, checked in the logic
     #[account(mut)]
-    pub player: Signer<'info>,                          // Player making the move.
+    pub player: AccountInfo<'info>,                     // Player making the move.
     #[account(mut)]
     /// CHECK: The player is checked in the logic
     pub rival: AccountInfo<'info>,                      // Rival player.
+    #[account(mut, seeds = [BALANCE_SEED, player.key().as_ref()], bump)]
+    pub player_balance: Option<Account<'info, PlayerBalance>>,
+    #[account(mut, seeds = [BALANCE_SEED, rival.key().as_ref()], bump)]
+    pub rival_balance: Option<Account<'info, PlayerBalance>>,
+    #[account(mut)]
+    pub session: Option<Account<'info, Session>>,       // Session of the player, when the key signs.
+    pub session_key: Option<Signer<'info>>,
     // pub system_program: Program<'info, System>,
 }
 
+#[derive(Accounts)]
+#[instruction(id: u64)]
+pub struct CreateHostedGame<'info> {
+    #[account(mut)]
+    pub global_state: Account<'info, GlobalState>,      // Global state tracking the active players.
+    #[account(init, payer = host, space = Game::MAXIMUM_SIZE, seeds = [GAME_SEED, host.key().as_ref(), &id.to_le_bytes()], bump)]
+    pub game: Account<'info, Game>,
+    #[account(init, payer = host, space = Escrow::MAXIMUM_SIZE, seeds = [ESCROW_SEED, game.key().as_ref()], bump)]
+    pub escrow: Account<'info, Escrow>,                 // Vault holding the game wagers.
+    #[account(mut)]
+    pub host: Signer<'info>,                            // PDA of the composing program, without data.
+    pub player_one: Signer<'info>,                      // Plays X.
+    pub player_two: Signer<'info>,                      // Plays O.
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct ReadGameResult<'info> {
+    pub game: Account<'info, Game>,
+}
+
+#[derive(Accounts)]
+#[instruction(id: u64)]
+pub struct SettleHostedGame<'info> {
+    #[account(mut)]
+    pub global_state: Account<'info, GlobalState>,      // Global state collecting the side pool fee.
+    #[account(mut, seeds = [GAME_SEED, host.key().as_ref(), &id.to_le_bytes()], bump, close = host)]
+    pub game: Account<'info, Game>,
+    #[account(mut, seeds = [ESCROW_SEED, game.key().as_ref()], bump, constraint = escrow.rent_payer == host.key() @ ErrorCode::GameIsHosted, close = host)]
+    pub escrow: Account<'info, Escrow>,
+    #[account(mut)]
+    pub host: Signer<'info>,                            // Host that created the game.
+    /// CHECK: Side pool address of the game, settled in the logic if it exists
+    #[account(mut, seeds = [SIDE_POOL_SEED, game.key().as_ref()], bump)]
+    pub side_pool: UncheckedAccount<'info>,
+}
+
+#[derive(Accounts)]
+pub struct RelayMove<'info> {
+    #[account(mut)]
+    pub global_state: Account<'info, GlobalState>,      // Global state containing the game counter.
+    #[account(mut)]
+    pub game: Account<'info, Game>,
+    #[account(mut, seeds = [ESCROW_SEED, game.key().as_ref()], bump)]
+    pub escrow: Account<'info, Escrow>,                 // Vault holding the game wagers.
+    /// CHECK: Signed the move off-chain, checked against the Ed25519 instruction
+    #[account(mut)]
+    pub player: AccountInfo<'info>,                     // Player making the move.
+    /// CHECK: The player is checked in the logic
+    #[account(mut)]
+    pub rival: AccountInfo<'info>,                      // Rival player.
+    #[account(mut, seeds = [BALANCE_SEED, player.key().as_ref()], bump)]
+    pub player_balance: Option<Account<'info, PlayerBalance>>,
+    #[account(mut, seeds = [BALANCE_SEED, rival.key().as_ref()], bump)]
+    pub rival_balance: Option<Account<'info, PlayerBalance>>,
+    #[account(init_if_needed, payer = relayer, space = RelayNonce::MAXIMUM_SIZE, seeds = [RELAY_NONCE_SEED, player.key().as_ref()], bump)]
+    pub relay_nonce: Account<'info, RelayNonce>,        // Next nonce of the player's relayed moves.
+    #[account(mut)]
+    pub relayer: Signer<'info>,                         // Submits and pays for the transaction.
+    /// CHECK: The instructions sysvar, holding the Ed25519 instruction
+    #[account(address = anchor_lang::solana_program::sysvar::instructions::ID)]
+    pub instructions: AccountInfo<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct Deposit<'info> {
+    #[account(init_if_needed, payer = owner, space = PlayerBalance::MAXIMUM_SIZE, seeds = [BALANCE_SEED, owner.key().as_ref()], bump)]
+    pub balance: Account<'info, PlayerBalance>,
+    #[account(mut)]
+    pub owner: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct Withdraw<'info> {
+    pub global_state: Account<'info, GlobalState>,      // Global state holding the pause switches.
+    #[account(mut, seeds = [BALANCE_SEED, owner.key().as_ref()], bump, has_one = owner)]
+    pub balance: Account<'info, PlayerBalance>,
+    #[account(mut)]
+    pub owner: Signer<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(key: Pubkey)]
+pub struct CreateSession<'info> {
+    #[account(init, payer = authority, space = Session::MAXIMUM_SIZE, seeds = [SESSION_SEED, authority.key().as_ref(), key.as_ref()], bump)]
+    pub session: Account<'info, Session>,
+    #[account(mut)]
+    pub authority: Signer<'info>,                       // Player authorizing the key.
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct RevokeSession<'info> {
+    #[account(mut, has_one = authority, close = authority)]
+    pub session: Account<'info, Session>,
+    /// CHECK: Checked against the session authority
+    #[account(mut)]
+    pub authority: AccountInfo<'info>,                  // Player receiving the rent back.
+    pub signer: Signer<'info>,                          // Player or session key.
+}
+
 #[derive(Accounts)]
 pub struct WithdrawFees<'info> {
     #[account(mut, has_one = owner)]
@@ -613,11 +2599,455 @@ pub struct WithdrawFees<'info> {
     pub system_program: Program<'info, System>,
 }
 
+#[derive(Accounts)]
+pub struct SetHouse<'info> {
+    #[account(mut, has_one = owner)]
+    pub global_state: Account<'info, GlobalState>,
+    pub owner: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct SetHook<'info> {
+    #[account(mut, has_one = owner)]
+    pub global_state: Account<'info, GlobalState>,
+    pub owner: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct CallGameHook<'info> {
+    pub global_state: Account<'info, GlobalState>,      // Global state holding the hook allowlist.
+    #[account(mut)]
+    pub game: Account<'info, Game>,
+}
+
+#[derive(Accounts)]
+pub struct SetPaused<'info> {
+    #[account(mut, has_one = owner)]
+    pub global_state: Account<'info, GlobalState>,
+    pub owner: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct SetConfig<'info> {
+    #[account(mut, has_one = owner)]
+    pub global_state: Account<'info, GlobalState>,
+    pub owner: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct CreateAdminSet<'info> {
+    #[account(mut, has_one = owner)]
+    pub global_state: Account<'info, GlobalState>,
+    // Reused when the admin set handed ownership back and is created again
+    #[account(init_if_needed, payer = owner, space = AdminSet::MAXIMUM_SIZE, seeds = [ADMIN_SET_SEED], bump)]
+    pub admin_set: Account<'info, AdminSet>,
+    #[account(mut)]
+    pub owner: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct Propose<'info> {
+    #[account(mut, seeds = [ADMIN_SET_SEED], bump)]
+    pub admin_set: Account<'info, AdminSet>,
+    #[account(init, payer = proposer, space = Proposal::MAXIMUM_SIZE, seeds = [PROPOSAL_SEED, &admin_set.proposal_count.to_le_bytes()], bump)]
+    pub proposal: Account<'info, Proposal>,
+    #[account(mut)]
+    pub proposer: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct ApproveProposal<'info> {
+    #[account(seeds = [ADMIN_SET_SEED], bump)]
+    pub admin_set: Account<'info, AdminSet>,
+    #[account(mut)]
+    pub proposal: Account<'info, Proposal>,
+    pub signer: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct ExecuteProposal<'info> {
+    #[account(mut)]
+    pub global_state: Account<'info, GlobalState>,
+    #[account(mut, seeds = [ADMIN_SET_SEED], bump)]
+    pub admin_set: Account<'info, AdminSet>,
+    #[account(mut)]
+    pub proposal: Account<'info, Proposal>,
+    /// CHECK: Checked against the withdrawal recipient in the logic, only needed to withdraw fees
+    #[account(mut)]
+    pub recipient: Option<UncheckedAccount<'info>>,
+    // The accounts below are only needed to refund a game, and checked against it in the logic
+    #[account(mut)]
+    pub game: Option<Account<'info, Game>>,
+    #[account(mut)]
+    pub escrow: Option<Account<'info, Escrow>>,
+    /// CHECK: Checked against the game players in the logic
+    #[account(mut)]
+    pub player_one: Option<UncheckedAccount<'info>>,
+    /// CHECK: Checked against the game players in the logic
+    #[account(mut)]
+    pub player_two: Option<UncheckedAccount<'info>>,
+    #[account(mut)]
+    pub player_one_balance: Option<Account<'info, PlayerBalance>>,
+    #[account(mut)]
+    pub player_two_balance: Option<Account<'info, PlayerBalance>>,
+}
+
+#[derive(Accounts)]
+pub struct EmergencyRefund<'info> {
+    #[account(mut, has_one = owner)]
+    pub global_state: Account<'info, GlobalState>,
+    pub owner: Signer<'info>,
+    #[account(mut)]
+    pub game: Account<'info, Game>,
+    #[account(mut, seeds = [ESCROW_SEED, game.key().as_ref()], bump)]
+    pub escrow: Account<'info, Escrow>,
+    /// CHECK: Checked against the game players in the logic
+    #[account(mut)]
+    pub player_one: AccountInfo<'info>,
+    /// CHECK: Checked against the game players in the logic
+    #[account(mut)]
+    pub player_two: AccountInfo<'info>,
+    #[account(mut, seeds = [BALANCE_SEED, player_one.key().as_ref()], bump)]
+    pub player_one_balance: Option<Account<'info, PlayerBalance>>,
+    #[account(mut, seeds = [BALANCE_SEED, player_two.key().as_ref()], bump)]
+    pub player_two_balance: Option<Account<'info, PlayerBalance>>,
+}
+
+#[derive(Accounts)]
+pub struct ChallengeHouse<'info> {
+    #[account(mut)]
+    pub global_state: Account<'info, GlobalState>,      // Global state holding the fee treasury.
+    #[account(init_if_needed, payer = player, space = Game::MAXIMUM_SIZE, seeds = [GAME_SEED, &global_state.game_count.to_le_bytes()], bump)]
+    pub game: Account<'info, Game>,                     // Next matchmaking game.
+    #[account(init_if_needed, payer = player, space = Escrow::MAXIMUM_SIZE, seeds = [ESCROW_SEED, game.key().as_ref()], bump)]
+    pub escrow: Account<'info, Escrow>,                 // Vault holding the game wagers.
+    #[account(mut)]
+    pub player: Signer<'info>,                          // Player challenging the house.
+    #[account(mut, seeds = [BALANCE_SEED, player.key().as_ref()], bump)]
+    pub balance: Option<Account<'info, PlayerBalance>>, // Balance funding the bet instead of the wallet.
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(id: u64)]
+pub struct CreateLeague<'info> {
+    #[account(init, payer = authority, space = League::MAXIMUM_SIZE, seeds = [LEAGUE_SEED, authority.key().as_ref(), &id.to_le_bytes()], bump)]
+    pub league: Account<'info, League>,
+    #[account(mut)]
+    pub authority: Signer<'info>,                       // League organizer.
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct JoinLeague<'info> {
+    #[account(mut)]
+    pub league: Account<'info, League>,
+    #[account(mut)]
+    pub player: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct StartLeague<'info> {
+    #[account(mut, has_one = authority)]
+    pub league: Account<'info, League>,
+    pub authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(fixture_index: u16)]
+pub struct SetupLeagueGame<'info> {
+    #[account(mut)]
+    pub global_state: Account<'info, GlobalState>,      // Global state containing the players mapping.
+    #[account(mut)]
+    pub league: Account<'info, League>,
+    #[account(init_if_needed, payer = player, space = Game::MAXIMUM_SIZE, seeds = [GAME_SEED, league.key().as_ref(), &fixture_index.to_le_bytes()], bump)]
+    pub game: Account<'info, Game>,                     // PDA account for the fixture game.
+    #[account(init_if_needed, payer = player, space = Escrow::MAXIMUM_SIZE, seeds = [ESCROW_SEED, game.key().as_ref()], bump)]
+    pub escrow: Account<'info, Escrow>,                 // Vault holding the game wagers.
+    #[account(mut)]
+    pub player: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct RecordLeagueResult<'info> {
+    #[account(mut)]
+    pub league: Account<'info, League>,
+    pub game: Account<'info, Game>,
+}
+
+#[derive(Accounts)]
+pub struct FinalizeLeague<'info> {
+    #[account(mut)]
+    pub league: Account<'info, League>,
+}
+
+#[derive(Accounts)]
+pub struct RefundLeague<'info> {
+    #[account(mut)]
+    pub league: Account<'info, League>,
+}
+
+#[derive(Accounts)]
+pub struct OpenSidePool<'info> {
+    #[account(init, payer = payer, space = SideBetPool::MAXIMUM_SIZE, seeds = [SIDE_POOL_SEED, game.key().as_ref()], bump)]
+    pub side_pool: Account<'info, SideBetPool>,
+    pub game: Account<'info, Game>,
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct PlaceSideBet<'info> {
+    #[account(mut, has_one = game)]
+    pub side_pool: Account<'info, SideBetPool>,
+    pub game: Account<'info, Game>,
+    #[account(init, payer = bettor, space = SideBet::MAXIMUM_SIZE, seeds = [SIDE_BET_SEED, side_pool.key().as_ref(), bettor.key().as_ref()], bump)]
+    pub side_bet: Account<'info, SideBet>,
+    #[account(mut)]
+    pub bettor: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct ClaimSideBet<'info> {
+    #[account(mut)]
+    pub global_state: Account<'info, GlobalState>,      // Global state collecting the fee.
+    #[account(mut)]
+    pub side_pool: Account<'info, SideBetPool>,
+    /// CHECK: Only read to settle the pool, it may have been closed already
+    #[account(address = side_pool.game)]
+    pub game: UncheckedAccount<'info>,
+    #[account(mut, close = bettor, has_one = bettor, constraint = side_bet.pool == side_pool.key())]
+    pub side_bet: Account<'info, SideBet>,
+    #[account(mut)]
+    pub bettor: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct CloseSidePool<'info> {
+    #[account(mut)]
+    pub global_state: Account<'info, GlobalState>,      // Global state collecting the fee.
+    #[account(mut, close = payer, has_one = payer)]
+    pub side_pool: Account<'info, SideBetPool>,
+    /// CHECK: Only read to settle the pool, it may have been closed already
+    #[account(address = side_pool.game)]
+    pub game: UncheckedAccount<'info>,
+    /// CHECK: Receives the pool rent, checked against the pool.
+    #[account(mut)]
+    pub payer: UncheckedAccount<'info>,
+}
+
+#[event]
+pub struct GameCreated {
+    pub game: Pubkey,
+    pub number: u64,
+    pub player_one: Pubkey,
+    pub league: Option<Pubkey>,
+    pub pot: u64,
+}
+
+#[event]
+pub struct PlayerJoined {
+    pub game: Pubkey,
+    pub number: u64,
+    pub player_two: Pubkey,
+    pub pot: u64,
+}
+
+#[event]
+pub struct MovePlayed {
+    pub game: Pubkey,
+    pub player: Pubkey,
+    pub tile: Tile,
+    pub sign: Sign,
+    pub turn: u8,
+}
+
+#[event]
+pub struct GameCanceled {
+    pub game: Pubkey,
+    pub canceled_by: Pubkey,
+    pub winner: Option<Pubkey>,
+}
+
+#[event]
+pub struct BalanceChanged {
+    pub owner: Pubkey,
+    pub deposited: u64,
+    pub withdrawn: u64,
+    pub available: u64,
+}
+
+#[event]
+pub struct SessionCreated {
+    pub session: Pubkey,
+    pub authority: Pubkey,
+    pub key: Pubkey,
+    pub game: Option<Pubkey>,
+    pub expires_at: i64,
+    pub spending_cap: u64,
+}
+
+#[event]
+pub struct SessionRevoked {
+    pub session: Pubkey,
+    pub authority: Pubkey,
+    pub key: Pubkey,
+    pub spent: u64,
+}
+
+#[event]
+pub struct MoveRelayed {
+    pub game: Pubkey,
+    pub player: Pubkey,
+    pub relayer: Pubkey,
+    pub nonce: u64,
+    pub relayer_fee: u64,
+}
+
+#[event]
+pub struct GameArchived {
+    pub game: Pubkey,
+    pub number: u64,
+    pub players: [Option<Pubkey>; 2],
+    pub state: GameState,
+    pub board: [[Option<Sign>; 3]; 3],
+    pub moves: Vec<Move>,
+    pub league: Option<Pubkey>,
+    pub house: bool,
+    pub settled_at: i64,
+    pub rent_payer: Pubkey,
+    pub lamports: u64,
+}
+
+#[event]
+pub struct GameClosed {
+    pub game: Pubkey,
+    pub number: u64,
+    pub closed_by: Pubkey,
+    pub lamports: u64,
+}
+
+#[event]
+pub struct FeesWithdrawn {
+    pub owner: Pubkey,
+    pub amount: u64,
+    pub remaining: u64,
+}
+
+#[event]
+pub struct HouseSet {
+    pub house: Option<Pubkey>,
+}
+
+#[event]
+pub struct HookSet {
+    pub hook: Pubkey,
+    pub allowed: bool,
+}
+
+#[event]
+pub struct HookCalled {
+    pub game: Pubkey,
+    pub hook: Pubkey,
+}
+
+#[event]
+pub struct GlobalStateMigrated {
+    pub from_version: u8,
+    pub to_version: u8,
+    pub size: u32,
+}
+
+#[event]
+pub struct ConfigSet {
+    pub fee: u64,
+    pub bet: u64,
+}
+
+#[event]
+pub struct AdminSetChanged {
+    pub signers: Vec<Pubkey>,
+    pub threshold: u8,
+    pub timelock: i64,
+}
+
+#[event]
+pub struct ProposalCreated {
+    pub proposal: Pubkey,
+    pub id: u64,
+    pub proposer: Pubkey,
+    pub action: AdminAction,
+}
+
+#[event]
+pub struct ProposalApproved {
+    pub proposal: Pubkey,
+    pub id: u64,
+    pub signer: Pubkey,
+    pub approvals: u8,
+}
+
+#[event]
+pub struct ProposalExecuted {
+    pub proposal: Pubkey,
+    pub id: u64,
+    pub action: AdminAction,
+}
+
+#[event]
+pub struct PauseSet {
+    pub paused: Pause,
+}
+
+#[event]
+pub struct HouseChallenged {
+    pub game: Pubkey,
+    pub number: u64,
+    pub player: Pubkey,
+    pub house: Pubkey,
+    pub pot: u64,
+}
+
 #[event]
 pub struct GameFinished {
+    pub game: Pubkey,
+    pub number: u64,
     pub player_one: Pubkey,
     pub player_two: Pubkey,
     pub winner: Option<Pubkey>,
+    pub reason: EndReason,
+    pub board: [[Option<Sign>; 3]; 3],
+    pub turns: u8,
+    pub pot: u64,                   // Pot before settlement
+    pub fee: u64,                   // Fee sent to the global state
+    pub player_one_payout: u64,
+    pub player_two_payout: u64,
+    pub moves: Vec<Move>,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct LeagueFinished {
+    pub league: Pubkey,
+    pub ranking: Vec<Pubkey>,
+    pub payouts: Vec<u64>,
+}
+
+impl From<MoveError> for ErrorCode {
+    fn from(error: MoveError) -> Self {
+        match error {
+            MoveError::GameOver => ErrorCode::GameAlreadyOver,
+            MoveError::NotYourTurn => ErrorCode::NotPlayersTurn,
+            MoveError::OutOfBounds => ErrorCode::TileOutOfBounds,
+            MoveError::Occupied => ErrorCode::TileAlreadySet,
+        }
+    }
 }
 
 // Definition of possible errors.
@@ -653,4 +3083,118 @@ pub enum ErrorCode {
     PlayerHasNotAnActiveGame,
     #[msg("Player has not enough funds to join the game.")]
     InsufficientFunds,
+    #[msg("Prize shares must add up to 100 and not exceed the maximum number of players.")]
+    InvalidPrizeShares,
+    #[msg("League is not open for registration.")]
+    LeagueNotRegistering,
+    #[msg("League is full.")]
+    LeagueFull,
+    #[msg("Player already joined the league.")]
+    PlayerAlreadyInLeague,
+    #[msg("Not enough players to start the league.")]
+    NotEnoughLeaguePlayers,
+    #[msg("League not in progress.")]
+    LeagueNotInProgress,
+    #[msg("Fixture not found in the league schedule.")]
+    FixtureNotFound,
+    #[msg("Fixture result already recorded.")]
+    FixtureAlreadyReported,
+    #[msg("The signer is not a player of the fixture.")]
+    SignerNotInFixture,
+    #[msg("Game has not finished yet.")]
+    GameNotFinished,
+    #[msg("League fixtures pending.")]
+    LeagueFixturesPending,
+    #[msg("Player accounts do not match the league ranking.")]
+    RankingMismatch,
+    #[msg("The game is past the side bet cutoff turn.")]
+    InvalidCutoffTurn,
+    #[msg("Side bets are closed for this game.")]
+    SideBetsClosed,
+    #[msg("The signer is a player of the game.")]
+    SignerIsPlayer,
+    #[msg("Side bet amount must be greater than zero.")]
+    InvalidSideBetAmount,
+    #[msg("The rival is not the other player of the game.")]
+    RivalIsNotPlayer,
+    #[msg("No house bot is set.")]
+    HouseNotSet,
+    #[msg("A player is waiting for a rival, join their game instead.")]
+    PlayerWaitingForRival,
+    #[msg("The fee treasury cannot cover the house bet.")]
+    TreasuryCannotCoverBet,
+    #[msg("New games are paused.")]
+    NewGamesPaused,
+    #[msg("Moves are paused.")]
+    MovesPaused,
+    #[msg("Withdrawals are paused.")]
+    WithdrawalsPaused,
+    #[msg("The account already has the current layout.")]
+    AccountAlreadyMigrated,
+    #[msg("The fee must be a percentage between 0 and 100.")]
+    InvalidFee,
+    #[msg("The admin set needs distinct signers, a threshold between 1 and the signers, and a non-negative timelock.")]
+    InvalidAdminSet,
+    #[msg("The signer is not in the admin set.")]
+    NotAnAdmin,
+    #[msg("The signer already approved the proposal.")]
+    ProposalAlreadyApproved,
+    #[msg("The proposal was already executed.")]
+    ProposalAlreadyExecuted,
+    #[msg("The proposal does not have enough approvals.")]
+    ProposalNotApproved,
+    #[msg("The proposal timelock has not expired yet.")]
+    TimelockNotExpired,
+    #[msg("The admin set does not own the global state.")]
+    AdminSetNotOwner,
+    #[msg("The recipient does not match the proposal.")]
+    InvalidRecipient,
+    #[msg("The game grace period is not over yet.")]
+    GracePeriodNotOver,
+    #[msg("The league fixture of the game must be recorded before it is closed.")]
+    FixtureNotReported,
+    #[msg("The player bet from their balance, which must receive the payout.")]
+    BalanceRequired,
+    #[msg("Neither the player nor a session key of the player for this game signed.")]
+    InvalidSession,
+    #[msg("The session key has expired.")]
+    SessionExpired,
+    #[msg("The session key has reached its spending cap.")]
+    SessionCapExceeded,
+    #[msg("The session must expire in the future and within the maximum session duration.")]
+    InvalidSessionExpiry,
+    #[msg("The move is not signed by the player in the preceding Ed25519 instruction.")]
+    InvalidRelayedMove,
+    #[msg("The relayed move was signed for another turn.")]
+    RelayedTurnMismatch,
+    #[msg("The relayed move nonce is not the player's next nonce.")]
+    InvalidNonce,
+    #[msg("The relayer fee is above the maximum or the player's share of the pot.")]
+    RelayerFeeTooHigh,
+    #[msg("The game is hosted by another program, which closes it.")]
+    GameIsHosted,
+    #[msg("The players of a hosted game must be two different users other than the host.")]
+    InvalidHostedPlayers,
+    #[msg("The hook program is not allowed by the global state.")]
+    HookNotAllowed,
+    #[msg("The hook allowlist is full.")]
+    HookListFull,
+    #[msg("The hook authority account is not the program's hook authority.")]
+    InvalidHookAuthority,
+    #[msg("The game has no hook left to call.")]
+    NoPendingHook,
+    #[msg("The first remaining account is not the hook program of the game.")]
+    InvalidHookProgram,
+    #[msg("The hook of the game must be called before it is closed.")]
+    HookPending,
+    #[msg("The accounts do not match the game of the refund.")]
+    InvalidRefundAccounts,
+    #[msg("The game account does not have the first version layout.")]
+    NotALegacyGame,
+    #[msg("Every side bet of the pool must be paid out before it is closed.")]
+    SideBetsOutstanding,
+    #[msg("The league season is not over yet.")]
+    LeagueSeasonNotOver,
+    #[msg("The admin set changed since the proposal was made, it must be proposed again.")]
+    StaleProposal,
 }
\ No newline at end of file
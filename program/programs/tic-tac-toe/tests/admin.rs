@@ -0,0 +1,339 @@
+mod common;
+
+use common::*;
+use solana_program_test::BanksClientError;
+use solana_sdk::{
+    instruction::InstructionError,
+    native_token::LAMPORTS_PER_SOL,
+    pubkey::Pubkey,
+    signature::{Keypair, Signer},
+    system_program,
+    transaction::TransactionError,
+};
+use tic_tac_toe::{AdminAction, AdminSet, ErrorCode, GameState, GlobalState, Pause, Proposal};
+
+fn admin_set_address() -> Pubkey {
+    Pubkey::find_program_address(&[b"admin_set"], &tic_tac_toe::ID).0
+}
+
+fn proposal_address(id: u64) -> Pubkey {
+    Pubkey::find_program_address(&[b"proposal", &id.to_le_bytes()], &tic_tac_toe::ID).0
+}
+
+fn paused() -> Pause {
+    Pause {
+        new_games: true,
+        ..Pause::default()
+    }
+}
+
+// Hands the global state to a test wallet, as the owner wallet cannot sign here.
+async fn owner(test: &mut TestContext) -> Keypair {
+    let owner = test.create_funded_player().await;
+    test.update_global_state(|global_state| global_state.owner = owner.pubkey()).await;
+    owner
+}
+
+async fn set_config(test: &mut TestContext, owner: &Keypair, fee: u64, bet: u64) -> Result<(), BanksClientError> {
+    let accounts = tic_tac_toe::accounts::SetConfig {
+        global_state: test.global_state,
+        owner: owner.pubkey(),
+    };
+    test.process(&[instruction(accounts, tic_tac_toe::instruction::SetConfig { fee, bet })], &[owner])
+        .await
+}
+
+async fn create_admin_set(test: &mut TestContext, owner: &Keypair, signers: &[&Keypair], threshold: u8, timelock: i64) -> Result<(), BanksClientError> {
+    let accounts = tic_tac_toe::accounts::CreateAdminSet {
+        global_state: test.global_state,
+        admin_set: admin_set_address(),
+        owner: owner.pubkey(),
+        system_program: system_program::ID,
+    };
+    let signers = signers.iter().map(|signer| signer.pubkey()).collect();
+    let data = tic_tac_toe::instruction::CreateAdminSet { signers, threshold, timelock };
+    test.process(&[instruction(accounts, data)], &[owner]).await
+}
+
+// Owner and admins of a new admin set.
+async fn start_admin_set(test: &mut TestContext, admins: usize, threshold: u8, timelock: i64) -> (Keypair, Vec<Keypair>) {
+    let owner = owner(test).await;
+    let mut signers = Vec::new();
+    for _ in 0..admins {
+        signers.push(test.create_funded_player().await);
+    }
+    create_admin_set(test, &owner, &signers.iter().collect::<Vec<_>>(), threshold, timelock).await.unwrap();
+    (owner, signers)
+}
+
+async fn propose(test: &mut TestContext, proposer: &Keypair, action: AdminAction) -> Result<u64, BanksClientError> {
+    let id = test.fetch::<AdminSet>(admin_set_address()).await.unwrap().proposal_count;
+    let accounts = tic_tac_toe::accounts::Propose {
+        admin_set: admin_set_address(),
+        proposal: proposal_address(id),
+        proposer: proposer.pubkey(),
+        system_program: system_program::ID,
+    };
+    test.process(&[instruction(accounts, tic_tac_toe::instruction::Propose { action })], &[proposer])
+        .await?;
+    Ok(id)
+}
+
+async fn approve(test: &mut TestContext, signer: &Keypair, id: u64) -> Result<(), BanksClientError> {
+    let accounts = tic_tac_toe::accounts::ApproveProposal {
+        admin_set: admin_set_address(),
+        proposal: proposal_address(id),
+        signer: signer.pubkey(),
+    };
+    test.process(&[instruction(accounts, tic_tac_toe::instruction::ApproveProposal {})], &[signer])
+        .await
+}
+
+async fn execute(test: &mut TestContext, id: u64, recipient: Option<Pubkey>) -> Result<(), BanksClientError> {
+    let accounts = tic_tac_toe::accounts::ExecuteProposal {
+        global_state: test.global_state,
+        admin_set: admin_set_address(),
+        proposal: proposal_address(id),
+        recipient,
+        game: None,
+        escrow: None,
+        player_one: None,
+        player_two: None,
+        player_one_balance: None,
+        player_two_balance: None,
+    };
+    test.process(&[instruction(accounts, tic_tac_toe::instruction::ExecuteProposal {})], &[])
+        .await
+}
+
+// Executes a proposal refunding `game`, passing its escrow and the wallets of its players.
+async fn execute_refund(test: &mut TestContext, id: u64, game: Pubkey, [player_one, player_two]: [Pubkey; 2]) -> Result<(), BanksClientError> {
+    let accounts = tic_tac_toe::accounts::ExecuteProposal {
+        global_state: test.global_state,
+        admin_set: admin_set_address(),
+        proposal: proposal_address(id),
+        recipient: None,
+        game: Some(game),
+        escrow: Some(escrow_address(&game)),
+        player_one: Some(player_one),
+        player_two: Some(player_two),
+        player_one_balance: None,
+        player_two_balance: None,
+    };
+    test.process(&[instruction(accounts, tic_tac_toe::instruction::ExecuteProposal {})], &[])
+        .await
+}
+
+fn has_one_error() -> TransactionError {
+    TransactionError::InstructionError(0, InstructionError::Custom(anchor_lang::error::ErrorCode::ConstraintHasOne.into()))
+}
+
+#[tokio::test]
+async fn owner_sets_the_fee_and_the_bet() {
+    let mut test = start().await;
+    let owner = owner(&mut test).await;
+
+    set_config(&mut test, &owner, 10, LAMPORTS_PER_SOL).await.unwrap();
+
+    let global_state = test.global_state().await;
+    assert_eq!((global_state.fee, global_state.bet), (10, LAMPORTS_PER_SOL));
+    assert_error(set_config(&mut test, &owner, 101, LAMPORTS_PER_SOL).await, ErrorCode::InvalidFee);
+}
+
+#[tokio::test]
+async fn admin_set_takes_over_the_owner_actions() {
+    let mut test = start().await;
+    let (owner, admins) = start_admin_set(&mut test, 3, 2, 0).await;
+
+    assert_eq!(test.global_state().await.owner, admin_set_address());
+    let admin_set = test.fetch::<AdminSet>(admin_set_address()).await.unwrap();
+    assert_eq!(admin_set.signers, admins.iter().map(|admin| admin.pubkey()).collect::<Vec<_>>());
+    assert_eq!(admin_set.threshold, 2);
+    // Neither the former owner nor a single admin can act alone anymore
+    let result = test.set_paused(&owner, paused()).await;
+    assert_eq!(result.unwrap_err().unwrap(), has_one_error());
+    let result = set_config(&mut test, &admins[0], 10, LAMPORTS_PER_SOL).await;
+    assert_eq!(result.unwrap_err().unwrap(), has_one_error());
+}
+
+#[tokio::test]
+async fn proposal_executes_once_the_threshold_is_reached() {
+    let mut test = start().await;
+    let (_, admins) = start_admin_set(&mut test, 3, 2, 0).await;
+
+    let id = propose(&mut test, &admins[0], AdminAction::SetPaused(paused())).await.unwrap();
+    assert_error(execute(&mut test, id, None).await, ErrorCode::ProposalNotApproved);
+    assert_error(approve(&mut test, &admins[0], id).await, ErrorCode::ProposalAlreadyApproved);
+
+    approve(&mut test, &admins[2], id).await.unwrap();
+    execute(&mut test, id, None).await.unwrap();
+
+    assert_eq!(test.global_state().await.paused, paused());
+    let proposal = test.fetch::<Proposal>(proposal_address(id)).await.unwrap();
+    assert_eq!(proposal.approvals, vec![admins[0].pubkey(), admins[2].pubkey()]);
+    assert!(proposal.executed);
+    assert_error(execute(&mut test, id, None).await, ErrorCode::ProposalAlreadyExecuted);
+    assert_error(approve(&mut test, &admins[1], id).await, ErrorCode::ProposalAlreadyExecuted);
+}
+
+#[tokio::test]
+async fn proposal_waits_for_the_timelock() {
+    let mut test = start().await;
+    let (_, admins) = start_admin_set(&mut test, 2, 1, 3600).await;
+    let action = AdminAction::SetConfig {
+        fee: 3,
+        bet: LAMPORTS_PER_SOL,
+    };
+
+    let id = propose(&mut test, &admins[1], action).await.unwrap();
+    assert_error(execute(&mut test, id, None).await, ErrorCode::TimelockNotExpired);
+
+    test.advance_clock(3600).await;
+    execute(&mut test, id, None).await.unwrap();
+
+    let global_state = test.global_state().await;
+    assert_eq!((global_state.fee, global_state.bet), (3, LAMPORTS_PER_SOL));
+}
+
+#[tokio::test]
+async fn approved_withdrawal_pays_the_recipient() {
+    let mut test = start().await;
+    let (_, admins) = start_admin_set(&mut test, 1, 1, 0).await;
+    let (game, player_one, player_two) = test.start_game().await;
+    test.play_moves(game, &player_one, &player_two, &PLAYER_ONE_WINS).await;
+    let recipient = Pubkey::new_unique();
+    let fees = test.balance(test.global_state).await - test.minimum_balance(GlobalState::MAXIMUM_SIZE).await;
+
+    // The global state keeps its rent-exempt reserve
+    let id = propose(&mut test, &admins[0], AdminAction::WithdrawFees { amount: fees + 1, recipient }).await.unwrap();
+    assert_error(execute(&mut test, id, Some(recipient)).await, ErrorCode::InsufficientFunds);
+
+    let id = propose(&mut test, &admins[0], AdminAction::WithdrawFees { amount: fees, recipient }).await.unwrap();
+    assert_error(execute(&mut test, id, None).await, ErrorCode::InvalidRecipient);
+    assert_error(execute(&mut test, id, Some(admins[0].pubkey())).await, ErrorCode::InvalidRecipient);
+    execute(&mut test, id, Some(recipient)).await.unwrap();
+
+    assert_eq!(test.balance(recipient).await, fees);
+}
+
+#[tokio::test]
+async fn approved_emergency_refund_returns_both_bets() {
+    let mut test = start().await;
+    let (_, admins) = start_admin_set(&mut test, 1, 1, 0).await;
+    let (game, player_one, player_two) = test.start_game().await;
+    let (other_game, ..) = test.start_game().await;
+    let players = [player_one.pubkey(), player_two.pubkey()];
+    let bet = test.global_state().await.bet;
+    let player_one_before = test.balance(player_one.pubkey()).await;
+    let player_two_before = test.balance(player_two.pubkey()).await;
+
+    let id = propose(&mut test, &admins[0], AdminAction::EmergencyRefund { game }).await.unwrap();
+    assert_error(execute(&mut test, id, None).await, ErrorCode::InvalidRefundAccounts);
+    assert_error(execute_refund(&mut test, id, other_game, players).await, ErrorCode::InvalidRefundAccounts);
+    assert_error(execute_refund(&mut test, id, game, [player_two.pubkey(), player_one.pubkey()]).await, ErrorCode::RivalIsNotPlayer);
+    execute_refund(&mut test, id, game, players).await.unwrap();
+
+    assert_eq!(test.game(game).await.state, GameState::Canceled);
+    assert_eq!(test.balance(player_one.pubkey()).await, player_one_before + bet);
+    assert_eq!(test.balance(player_two.pubkey()).await, player_two_before + bet);
+    assert_eq!(test.global_state().await.find_game_from_player(player_one.pubkey()).ok(), None);
+    assert!(test.fetch::<Proposal>(proposal_address(id)).await.unwrap().executed);
+}
+
+#[tokio::test]
+async fn only_admins_propose_and_approve() {
+    let mut test = start().await;
+    let (owner, admins) = start_admin_set(&mut test, 2, 2, 0).await;
+
+    assert_error(propose(&mut test, &owner, AdminAction::SetHouse(None)).await, ErrorCode::NotAnAdmin);
+    let id = propose(&mut test, &admins[0], AdminAction::SetHouse(None)).await.unwrap();
+    assert_error(approve(&mut test, &owner, id).await, ErrorCode::NotAnAdmin);
+}
+
+#[tokio::test]
+async fn invalid_admin_sets_fail() {
+    let mut test = start().await;
+    let owner = owner(&mut test).await;
+    let admin = test.create_funded_player().await;
+    let other = test.create_funded_player().await;
+
+    let cases: [(&[&Keypair], u8, i64); 5] = [
+        (&[], 0, 0),
+        (&[&admin], 0, 0),
+        (&[&admin, &other], 3, 0),
+        (&[&admin, &admin], 1, 0),
+        (&[&admin], 1, -1),
+    ];
+    for (signers, threshold, timelock) in cases {
+        assert_error(create_admin_set(&mut test, &owner, signers, threshold, timelock).await, ErrorCode::InvalidAdminSet);
+    }
+}
+
+#[tokio::test]
+async fn set_owner_hands_the_global_state_back() {
+    let mut test = start().await;
+    let (_, admins) = start_admin_set(&mut test, 1, 1, 0).await;
+    let wallet = test.create_funded_player().await;
+    let stale = propose(&mut test, &admins[0], AdminAction::SetHouse(None)).await.unwrap();
+
+    let id = propose(&mut test, &admins[0], AdminAction::SetOwner(wallet.pubkey())).await.unwrap();
+    execute(&mut test, id, None).await.unwrap();
+
+    assert_eq!(test.global_state().await.owner, wallet.pubkey());
+    test.set_paused(&wallet, paused()).await.unwrap();
+    assert_error(execute(&mut test, stale, None).await, ErrorCode::AdminSetNotOwner);
+}
+
+#[tokio::test]
+async fn pending_proposals_expire_when_the_admins_change() {
+    let mut test = start().await;
+    let (_, admins) = start_admin_set(&mut test, 3, 2, 0).await;
+    let pending = propose(&mut test, &admins[0], AdminAction::SetPaused(paused())).await.unwrap();
+    approve(&mut test, &admins[1], pending).await.unwrap();
+
+    // Replace the first admin before the pending proposal is executed
+    let replacement = test.create_funded_player().await;
+    let action = AdminAction::SetAdmins {
+        signers: vec![admins[1].pubkey(), admins[2].pubkey(), replacement.pubkey()],
+        threshold: 2,
+        timelock: 0,
+    };
+    let id = propose(&mut test, &admins[1], action).await.unwrap();
+    approve(&mut test, &admins[2], id).await.unwrap();
+    execute(&mut test, id, None).await.unwrap();
+
+    // The approval of the removed admin no longer counts, nor can the pending proposal be
+    // approved again under the new admins
+    assert_error(execute(&mut test, pending, None).await, ErrorCode::StaleProposal);
+    assert_error(approve(&mut test, &replacement, pending).await, ErrorCode::StaleProposal);
+    let admin_set = test.fetch::<AdminSet>(admin_set_address()).await.unwrap();
+    assert_ne!(test.fetch::<Proposal>(proposal_address(pending)).await.unwrap().admins_version, admin_set.admins_version);
+
+    let id = propose(&mut test, &replacement, AdminAction::SetPaused(paused())).await.unwrap();
+    approve(&mut test, &admins[2], id).await.unwrap();
+    execute(&mut test, id, None).await.unwrap();
+    assert_eq!(test.global_state().await.paused, paused());
+}
+
+#[tokio::test]
+async fn approvals_expire_when_the_threshold_changes() {
+    let mut test = start().await;
+    let (_, admins) = start_admin_set(&mut test, 3, 3, 0).await;
+    // Approved by everyone under the old threshold, without being executed yet
+    let pending = propose(&mut test, &admins[0], AdminAction::SetPaused(paused())).await.unwrap();
+    approve(&mut test, &admins[1], pending).await.unwrap();
+
+    // Same admins, lower threshold
+    let action = AdminAction::SetAdmins {
+        signers: admins.iter().map(|admin| admin.pubkey()).collect(),
+        threshold: 2,
+        timelock: 0,
+    };
+    let id = propose(&mut test, &admins[0], action).await.unwrap();
+    approve(&mut test, &admins[1], id).await.unwrap();
+    approve(&mut test, &admins[2], id).await.unwrap();
+    execute(&mut test, id, None).await.unwrap();
+
+    assert_error(execute(&mut test, pending, None).await, ErrorCode::StaleProposal);
+    assert_eq!(test.global_state().await.paused, Pause::default());
+}
@@ -0,0 +1,131 @@
+mod common;
+
+use common::*;
+use solana_sdk::signature::{Keypair, Signer};
+use tic_tac_toe::{Escrow, ErrorCode, Game, GameState, Pause, PlayerBalance};
+
+// Creates a player with `amount` lamports deposited in the balance.
+async fn player_with_balance(test: &mut TestContext, amount: u64) -> Keypair {
+    let player = test.create_funded_player().await;
+    test.deposit(&player, amount).await.unwrap();
+    player
+}
+
+// Lamports of the balance above its rent exemption.
+async fn available(test: &mut TestContext, owner: &Keypair) -> u64 {
+    let rent = test.minimum_balance(PlayerBalance::MAXIMUM_SIZE).await;
+    test.balance(balance_address(&owner.pubkey())).await - rent
+}
+
+#[tokio::test]
+async fn deposits_and_withdraws() {
+    let mut test = start().await;
+    let bet = test.global_state().await.bet;
+    let player = player_with_balance(&mut test, 3 * bet).await;
+
+    let balance = test.fetch::<PlayerBalance>(balance_address(&player.pubkey())).await.unwrap();
+    assert_eq!((balance.version, balance.owner), (PlayerBalance::VERSION, player.pubkey()));
+    test.deposit(&player, bet).await.unwrap();
+    assert_eq!(available(&mut test, &player).await, 4 * bet);
+
+    let wallet_before = test.balance(player.pubkey()).await;
+    test.withdraw(&player, bet).await.unwrap();
+
+    assert_eq!(test.balance(player.pubkey()).await, wallet_before + bet);
+    assert_eq!(available(&mut test, &player).await, 3 * bet);
+    // The rent exemption stays in the balance
+    assert_error(test.withdraw(&player, 3 * bet + 1).await, ErrorCode::InsufficientFunds);
+}
+
+#[tokio::test]
+async fn bets_from_the_balance_only_take_rent_from_the_wallet() {
+    let mut test = start().await;
+    let bet = test.global_state().await.bet;
+    let player_one = player_with_balance(&mut test, bet).await;
+    let player_two = test.create_funded_player().await;
+    let wallet_before = test.balance(player_one.pubkey()).await;
+
+    let game = test.setup_game_from(&player_one, true).await.unwrap();
+    test.setup_game(&player_two).await.unwrap();
+
+    // Only the rent of the accounts it opened left the wallet
+    let rent = test.minimum_balance(Game::MAXIMUM_SIZE).await + test.minimum_balance(Escrow::MAXIMUM_SIZE).await;
+    assert_eq!(test.balance(player_one.pubkey()).await, wallet_before - rent);
+    assert_eq!(available(&mut test, &player_one).await, 0);
+    let escrow = test.fetch::<Escrow>(escrow_address(&game)).await.unwrap();
+    assert_eq!(escrow.from_balance, [true, false]);
+    // The escrow rent is still paid by the wallet opening the game
+    assert_eq!(escrow.rent_payer, player_one.pubkey());
+}
+
+#[tokio::test]
+async fn payouts_go_back_to_the_balance() {
+    let mut test = start().await;
+    let global_state = test.global_state().await;
+    let (bet, fee) = (global_state.bet, global_state.fee);
+    let player_one = player_with_balance(&mut test, bet).await;
+    let player_two = test.create_funded_player().await;
+    let game = test.setup_game_from(&player_one, true).await.unwrap();
+    test.setup_game(&player_two).await.unwrap();
+    let wallet_before = test.balance(player_one.pubkey()).await;
+
+    test.play_moves(game, &player_one, &player_two, &PLAYER_ONE_WINS).await;
+
+    assert_eq!(test.game(game).await.state, GameState::Won { winner: player_one.pubkey() });
+    assert_eq!(available(&mut test, &player_one).await, 2 * bet - 2 * bet * fee / 100);
+    assert_eq!(test.balance(player_one.pubkey()).await, wallet_before);
+}
+
+#[tokio::test]
+async fn settlement_requires_the_funding_balance() {
+    let mut test = start().await;
+    let bet = test.global_state().await.bet;
+    let player_one = player_with_balance(&mut test, bet).await;
+    let player_two = test.create_funded_player().await;
+    let game = test.setup_game_from(&player_one, true).await.unwrap();
+    test.setup_game(&player_two).await.unwrap();
+
+    // Resigning without passing the rival balance cannot pay the rival
+    let accounts = tic_tac_toe::accounts::CancelGame {
+        global_state: test.global_state,
+        game,
+        escrow: escrow_address(&game),
+        signer: player_two.pubkey(),
+        rival: player_one.pubkey(),
+        signer_balance: None,
+        rival_balance: None,
+        session: None,
+        session_key: None,
+    };
+    let cancel = signed_by(instruction(accounts, tic_tac_toe::instruction::CancelGame {}), &player_two.pubkey());
+    assert_error(test.process(&[cancel], &[&player_two]).await, ErrorCode::BalanceRequired);
+
+    test.cancel_game(game, &player_two, player_one.pubkey()).await.unwrap();
+    assert!(available(&mut test, &player_one).await > bet);
+}
+
+#[tokio::test]
+async fn bet_needs_enough_in_the_balance() {
+    let mut test = start().await;
+    let bet = test.global_state().await.bet;
+    let player = player_with_balance(&mut test, bet - 1).await;
+
+    assert_error(test.setup_game_from(&player, true).await, ErrorCode::InsufficientFunds);
+}
+
+#[tokio::test]
+async fn paused_withdrawals_keep_the_balance() {
+    let mut test = start().await;
+    let owner = test.create_funded_player().await;
+    test.update_global_state(|global_state| global_state.owner = owner.pubkey()).await;
+    let player = player_with_balance(&mut test, 1_000_000).await;
+    let paused = Pause {
+        withdrawals: true,
+        ..Pause::default()
+    };
+    test.set_paused(&owner, paused).await.unwrap();
+
+    assert_error(test.withdraw(&player, 1).await, ErrorCode::WithdrawalsPaused);
+    // Deposits are still accepted
+    test.deposit(&player, 1).await.unwrap();
+}
@@ -0,0 +1,50 @@
+mod common;
+
+use common::*;
+use solana_sdk::signature::Signer;
+use tic_tac_toe::{Escrow, ErrorCode, Game, CLOSE_GRACE_PERIOD};
+
+#[tokio::test]
+async fn crank_closes_a_settled_game_after_the_grace_period() {
+    let mut test = start().await;
+    let (game, player_one, player_two) = test.start_game().await;
+    test.play_moves(game, &player_one, &player_two, &PLAYER_ONE_WINS).await;
+    let escrow = test.fetch::<Escrow>(escrow_address(&game)).await.unwrap();
+    assert_ne!(escrow.settled_at, 0);
+
+    assert_error(test.close_finished_game(game, None).await, ErrorCode::GracePeriodNotOver);
+
+    test.advance_clock(CLOSE_GRACE_PERIOD).await;
+    let rent = test.balance(game).await + test.balance(escrow_address(&game)).await;
+    let balances_before = [test.balance(player_one.pubkey()).await, test.balance(player_two.pubkey()).await];
+    test.close_finished_game(game, None).await.unwrap();
+
+    assert!(test.fetch::<Game>(game).await.is_none());
+    assert!(test.fetch::<Escrow>(escrow_address(&game)).await.is_none());
+    assert_eq!(test.balance(player_one.pubkey()).await, balances_before[0] + rent);
+    assert_eq!(test.balance(player_two.pubkey()).await, balances_before[1]);
+}
+
+#[tokio::test]
+async fn crank_closes_canceled_games() {
+    let mut test = start().await;
+    let player = test.create_funded_player().await;
+    let game = test.setup_game(&player).await.unwrap();
+    test.cancel_game(game, &player, player.pubkey()).await.unwrap();
+    test.advance_clock(CLOSE_GRACE_PERIOD).await;
+
+    test.close_finished_game(game, None).await.unwrap();
+
+    assert!(test.fetch::<Game>(game).await.is_none());
+}
+
+#[tokio::test]
+async fn crank_on_a_running_game_fails() {
+    let mut test = start().await;
+    let (game, player_one, _) = test.start_game().await;
+    test.advance_clock(CLOSE_GRACE_PERIOD).await;
+
+    assert_error(test.close_finished_game(game, None).await, ErrorCode::GameNotFinished);
+    assert!(test.fetch::<Game>(game).await.is_some());
+    assert_eq!(test.game(game).await.players[0], Some(player_one.pubkey()));
+}
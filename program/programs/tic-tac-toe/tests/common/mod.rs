@@ -0,0 +1,394 @@
+// Shared fixtures for the program integration tests.
+//
+// Every test starts its own in-process bank, so tests do not share any state. The program
+// runs natively by default; set `SBF_OUT_DIR` (e.g. `target/deploy` after `anchor build`)
+// to load the compiled program instead.
+//
+// Every reachable `ErrorCode` is covered by the `admin`, `balance`, `close`, `escrow`, `game`,
+// `hooks`, `hosted`, `house`, `league`, `migration`, `pause`, `relay`, `session` and `side_bets`
+// tests. `PlayerNotFound`, `WinnerNotFound` and `AccountNotFound` cannot be triggered through the
+// instructions, as players and accounts are validated before those checks.
+#![allow(dead_code)]
+
+use anchor_lang::{AccountDeserialize, AccountSerialize, InstructionData, ToAccountMetas};
+use solana_program_test::{processor, BanksClientError, ProgramTest, ProgramTestContext};
+use solana_sdk::{
+    account_info::AccountInfo,
+    clock::Clock,
+    entrypoint::ProgramResult,
+    instruction::{Instruction, InstructionError},
+    native_token::LAMPORTS_PER_SOL,
+    pubkey::Pubkey,
+    signature::{Keypair, Signer},
+    system_instruction, system_program,
+    transaction::{Transaction, TransactionError},
+};
+use tic_tac_toe::{
+    engine::{Board, Mark, Outcome},
+    ErrorCode, Escrow, Game, GlobalState, Pause, Tile,
+};
+
+pub const PLAYER_FUNDS: u64 = 10 * LAMPORTS_PER_SOL;
+
+// Anchor's entrypoint ties the accounts to the instruction lifetime, the builtin processor does not.
+fn process_instruction(program_id: &Pubkey, accounts: &[AccountInfo], data: &[u8]) -> ProgramResult {
+    let accounts = Box::leak(Box::new(accounts.to_vec()));
+    tic_tac_toe::entry(program_id, accounts, data)
+}
+
+pub struct TestContext {
+    pub context: ProgramTestContext,
+    pub global_state: Pubkey,
+}
+
+pub fn program_test() -> ProgramTest {
+    ProgramTest::new("tic_tac_toe", tic_tac_toe::ID, processor!(process_instruction))
+}
+
+// Starts a bank with the program loaded and the global state initialized.
+pub async fn start() -> TestContext {
+    start_with(program_test()).await
+}
+
+// Same as `start`, from a program test that can load other programs.
+pub async fn start_with(program_test: ProgramTest) -> TestContext {
+    let context = program_test.start_with_context().await;
+    let global_state = global_state_address();
+    let mut test = TestContext { context, global_state };
+
+    let payer = test.context.payer.insecure_clone();
+    let accounts = tic_tac_toe::accounts::InitializeGlobalState {
+        global_state,
+        payer: payer.pubkey(),
+        system_program: system_program::ID,
+    };
+    test.process(&[instruction(accounts, tic_tac_toe::instruction::InitializeGlobalState {})], &[])
+        .await
+        .unwrap();
+    test
+}
+
+pub fn global_state_address() -> Pubkey {
+    Pubkey::find_program_address(&[b"global_state"], &tic_tac_toe::ID).0
+}
+
+pub fn game_address(number: u64) -> Pubkey {
+    Pubkey::find_program_address(&[b"game", &number.to_le_bytes()], &tic_tac_toe::ID).0
+}
+
+pub fn escrow_address(game: &Pubkey) -> Pubkey {
+    Pubkey::find_program_address(&[b"escrow", game.as_ref()], &tic_tac_toe::ID).0
+}
+
+pub fn side_pool_address(game: &Pubkey) -> Pubkey {
+    Pubkey::find_program_address(&[b"side_pool", game.as_ref()], &tic_tac_toe::ID).0
+}
+
+pub fn balance_address(owner: &Pubkey) -> Pubkey {
+    Pubkey::find_program_address(&[b"balance", owner.as_ref()], &tic_tac_toe::ID).0
+}
+
+pub fn instruction(accounts: impl ToAccountMetas, data: impl InstructionData) -> Instruction {
+    Instruction {
+        program_id: tic_tac_toe::ID,
+        accounts: accounts.to_account_metas(None),
+        data: data.data(),
+    }
+}
+
+// Marks the player as a signer, as `play` and `cancel_game` also accept a session key instead.
+pub fn signed_by(mut instruction: Instruction, player: &Pubkey) -> Instruction {
+    if let Some(meta) = instruction.accounts.iter_mut().find(|meta| meta.pubkey == *player) {
+        meta.is_signer = true;
+    }
+    instruction
+}
+
+// Asserts that a transaction failed with the given program error.
+pub fn assert_error<T: std::fmt::Debug>(result: Result<T, BanksClientError>, error: ErrorCode) {
+    match result.expect_err("transaction should fail").unwrap() {
+        TransactionError::InstructionError(_, InstructionError::Custom(code)) => {
+            assert_eq!(code, u32::from(error), "expected {:?}", error)
+        }
+        other => panic!("expected {:?}, got {:?}", error, other),
+    }
+}
+
+impl TestContext {
+    // Sends the instructions paid by the bank payer and signed by `signers`.
+    pub async fn process(&mut self, instructions: &[Instruction], signers: &[&Keypair]) -> Result<(), BanksClientError> {
+        let payer = self.context.payer.insecure_clone();
+        let mut all_signers = vec![&payer];
+        all_signers.extend_from_slice(signers);
+        let blockhash = self.context.get_new_latest_blockhash().await.unwrap();
+        let transaction = Transaction::new_signed_with_payer(instructions, Some(&payer.pubkey()), &all_signers, blockhash);
+        self.context.banks_client.process_transaction(transaction).await
+    }
+
+    // Creates a player funded with `lamports` from the bank payer.
+    pub async fn create_player(&mut self, lamports: u64) -> Keypair {
+        let player = Keypair::new();
+        let transfer = system_instruction::transfer(&self.context.payer.pubkey(), &player.pubkey(), lamports);
+        self.process(&[transfer], &[]).await.unwrap();
+        player
+    }
+
+    pub async fn create_funded_player(&mut self) -> Keypair {
+        self.create_player(PLAYER_FUNDS).await
+    }
+
+    pub async fn balance(&mut self, address: Pubkey) -> u64 {
+        self.context.banks_client.get_balance(address).await.unwrap()
+    }
+
+    pub async fn fetch<T: AccountDeserialize>(&mut self, address: Pubkey) -> Option<T> {
+        let account = self.context.banks_client.get_account(address).await.unwrap()?;
+        Some(T::try_deserialize(&mut account.data.as_slice()).unwrap())
+    }
+
+    pub async fn global_state(&mut self) -> GlobalState {
+        self.fetch(self.global_state).await.unwrap()
+    }
+
+    pub async fn game(&mut self, game: Pubkey) -> Game {
+        self.fetch(game).await.unwrap()
+    }
+
+    pub async fn minimum_balance(&mut self, size: usize) -> u64 {
+        self.context.banks_client.get_rent().await.unwrap().minimum_balance(size)
+    }
+
+    pub async fn advance_clock(&mut self, seconds: i64) {
+        let mut clock: Clock = self.context.banks_client.get_sysvar().await.unwrap();
+        clock.unix_timestamp += seconds;
+        self.context.set_sysvar(&clock);
+    }
+
+    // Joins matchmaking and returns the game the player was placed in.
+    pub async fn setup_game(&mut self, player: &Keypair) -> Result<Pubkey, BanksClientError> {
+        self.setup_game_from(player, false).await
+    }
+
+    // Joins matchmaking betting from the player balance or the wallet.
+    pub async fn setup_game_from(&mut self, player: &Keypair, from_balance: bool) -> Result<Pubkey, BanksClientError> {
+        let game = game_address(self.global_state().await.game_count);
+        let accounts = tic_tac_toe::accounts::SetupGame {
+            global_state: self.global_state,
+            game,
+            escrow: escrow_address(&game),
+            player: player.pubkey(),
+            balance: from_balance.then(|| balance_address(&player.pubkey())),
+            system_program: system_program::ID,
+        };
+        self.process(&[instruction(accounts, tic_tac_toe::instruction::SetupGame { hook: None })], &[player])
+            .await?;
+        Ok(game)
+    }
+
+    // Sets up a game between two new funded players and returns it with both players.
+    pub async fn start_game(&mut self) -> (Pubkey, Keypair, Keypair) {
+        let player_one = self.create_funded_player().await;
+        let player_two = self.create_funded_player().await;
+        let game = self.setup_game(&player_one).await.unwrap();
+        assert_eq!(self.setup_game(&player_two).await.unwrap(), game);
+        (game, player_one, player_two)
+    }
+
+    pub async fn play(&mut self, game: Pubkey, player: &Keypair, rival: Pubkey, row: u8, column: u8) -> Result<(), BanksClientError> {
+        let accounts = tic_tac_toe::accounts::Play {
+            global_state: self.global_state,
+            game,
+            escrow: escrow_address(&game),
+            player: player.pubkey(),
+            rival,
+            player_balance: self.balance_of(game, player.pubkey()).await,
+            rival_balance: self.balance_of(game, rival).await,
+            session: None,
+            session_key: None,
+        };
+        let data = tic_tac_toe::instruction::Play { tile: Tile { row, column } };
+        self.process(&[signed_by(instruction(accounts, data), &player.pubkey())], &[player]).await
+    }
+
+    // Plays the tiles alternating between both players, starting with player one.
+    pub async fn play_moves(&mut self, game: Pubkey, player_one: &Keypair, player_two: &Keypair, tiles: &[(u8, u8)]) {
+        for (index, &(row, column)) in tiles.iter().enumerate() {
+            let (player, rival) = if index % 2 == 0 { (player_one, player_two) } else { (player_two, player_one) };
+            self.play(game, player, rival.pubkey(), row, column).await.unwrap();
+        }
+    }
+
+    pub async fn cancel_game(&mut self, game: Pubkey, signer: &Keypair, rival: Pubkey) -> Result<(), BanksClientError> {
+        let accounts = tic_tac_toe::accounts::CancelGame {
+            global_state: self.global_state,
+            game,
+            escrow: escrow_address(&game),
+            signer: signer.pubkey(),
+            rival,
+            signer_balance: self.balance_of(game, signer.pubkey()).await,
+            rival_balance: self.balance_of(game, rival).await.filter(|_| rival != signer.pubkey()),
+            session: None,
+            session_key: None,
+        };
+        self.process(&[signed_by(instruction(accounts, tic_tac_toe::instruction::CancelGame {}), &signer.pubkey())], &[signer])
+            .await
+    }
+
+    // Rewrites the global state account in place, e.g. to hand ownership to a test wallet.
+    pub async fn update_global_state(&mut self, update: impl FnOnce(&mut GlobalState)) {
+        let mut account = self.context.banks_client.get_account(self.global_state).await.unwrap().unwrap();
+        let mut global_state = GlobalState::try_deserialize(&mut account.data.as_slice()).unwrap();
+        update(&mut global_state);
+        global_state.try_serialize(&mut account.data.as_mut_slice()).unwrap();
+        self.context.set_account(&self.global_state, &account.into());
+    }
+
+    // Makes `owner` the global state owner and `house` the house bot, and funds the treasury.
+    pub async fn setup_house(&mut self, owner: &Keypair, house: Pubkey, treasury: u64) {
+        self.update_global_state(|global_state| global_state.owner = owner.pubkey()).await;
+        self.set_house(owner, Some(house)).await.unwrap();
+        let transfer = system_instruction::transfer(&self.context.payer.pubkey(), &self.global_state, treasury);
+        self.process(&[transfer], &[]).await.unwrap();
+    }
+
+    pub async fn set_house(&mut self, owner: &Keypair, house: Option<Pubkey>) -> Result<(), BanksClientError> {
+        let accounts = tic_tac_toe::accounts::SetHouse {
+            global_state: self.global_state,
+            owner: owner.pubkey(),
+        };
+        self.process(&[instruction(accounts, tic_tac_toe::instruction::SetHouse { house })], &[owner])
+            .await
+    }
+
+    // Starts a game against the house and returns it.
+    pub async fn challenge_house(&mut self, player: &Keypair) -> Result<Pubkey, BanksClientError> {
+        let game = game_address(self.global_state().await.game_count);
+        let accounts = tic_tac_toe::accounts::ChallengeHouse {
+            global_state: self.global_state,
+            game,
+            escrow: escrow_address(&game),
+            player: player.pubkey(),
+            balance: None,
+            system_program: system_program::ID,
+        };
+        self.process(&[instruction(accounts, tic_tac_toe::instruction::ChallengeHouse {})], &[player])
+            .await?;
+        Ok(game)
+    }
+
+    pub async fn set_paused(&mut self, owner: &Keypair, paused: Pause) -> Result<(), BanksClientError> {
+        let accounts = tic_tac_toe::accounts::SetPaused {
+            global_state: self.global_state,
+            owner: owner.pubkey(),
+        };
+        self.process(&[instruction(accounts, tic_tac_toe::instruction::SetPaused { paused })], &[owner])
+            .await
+    }
+
+    pub async fn emergency_refund(&mut self, owner: &Keypair, game: Pubkey, player_one: Pubkey, player_two: Pubkey) -> Result<(), BanksClientError> {
+        let accounts = tic_tac_toe::accounts::EmergencyRefund {
+            global_state: self.global_state,
+            owner: owner.pubkey(),
+            game,
+            escrow: escrow_address(&game),
+            player_one,
+            player_two,
+            player_one_balance: self.balance_of(game, player_one).await,
+            player_two_balance: self.balance_of(game, player_two).await.filter(|_| player_two != player_one),
+        };
+        self.process(&[instruction(accounts, tic_tac_toe::instruction::EmergencyRefund {})], &[owner])
+            .await
+    }
+
+    // The balance of a player who bet from it in the game, as the payouts go back there.
+    pub async fn balance_of(&mut self, game: Pubkey, wallet: Pubkey) -> Option<Pubkey> {
+        let escrow = self.fetch::<Escrow>(escrow_address(&game)).await?;
+        let players = self.fetch::<Game>(game).await?.players;
+        let index = players.iter().position(|player| *player == Some(wallet))?;
+        escrow.from_balance[index].then(|| balance_address(&wallet))
+    }
+
+    pub async fn deposit(&mut self, owner: &Keypair, amount: u64) -> Result<(), BanksClientError> {
+        let accounts = tic_tac_toe::accounts::Deposit {
+            balance: balance_address(&owner.pubkey()),
+            owner: owner.pubkey(),
+            system_program: system_program::ID,
+        };
+        self.process(&[instruction(accounts, tic_tac_toe::instruction::Deposit { amount })], &[owner])
+            .await
+    }
+
+    pub async fn withdraw(&mut self, owner: &Keypair, amount: u64) -> Result<(), BanksClientError> {
+        let accounts = tic_tac_toe::accounts::Withdraw {
+            global_state: self.global_state,
+            balance: balance_address(&owner.pubkey()),
+            owner: owner.pubkey(),
+        };
+        self.process(&[instruction(accounts, tic_tac_toe::instruction::Withdraw { amount })], &[owner])
+            .await
+    }
+
+    // Closes a settled game with the crank, paid by the bank payer.
+    pub async fn close_finished_game(&mut self, game: Pubkey, league: Option<Pubkey>) -> Result<(), BanksClientError> {
+        let rent_payer = self.fetch::<Escrow>(escrow_address(&game)).await.unwrap().rent_payer;
+        let accounts = tic_tac_toe::accounts::CloseFinishedGame {
+            global_state: self.global_state,
+            game,
+            escrow: escrow_address(&game),
+            rent_payer,
+            side_pool: side_pool_address(&game),
+            league,
+        };
+        self.process(&[instruction(accounts, tic_tac_toe::instruction::CloseFinishedGame {})], &[])
+            .await
+    }
+
+    // Closes a game, refunding the escrow rent to the wallet that funded it.
+    pub async fn close_game_account(&mut self, game: Pubkey, signer: &Keypair) -> Result<(), BanksClientError> {
+        let rent_payer = self.fetch::<Escrow>(escrow_address(&game)).await.unwrap().rent_payer;
+        let accounts = tic_tac_toe::accounts::CloseGameAccount {
+            global_state: self.global_state,
+            game,
+            escrow: escrow_address(&game),
+            signer: signer.pubkey(),
+            rent_payer,
+            side_pool: side_pool_address(&game),
+            league: self.game(game).await.league,
+        };
+        self.process(&[instruction(accounts, tic_tac_toe::instruction::CloseGameAccount {})], &[signer])
+            .await
+    }
+}
+
+// Player one wins on the top row.
+pub const PLAYER_ONE_WINS: [(u8, u8); 5] = [(0, 0), (1, 0), (0, 1), (1, 1), (0, 2)];
+
+// Full board without a winner.
+pub const TIE: [(u8, u8); 9] = [(0, 0), (0, 1), (0, 2), (1, 1), (1, 0), (1, 2), (2, 1), (2, 0), (2, 2)];
+
+// Winning lines as bit masks over the cells, numbered row by row.
+const WINNING_MASKS: [u16; 8] = [0o007, 0o070, 0o700, 0o111, 0o222, 0o444, 0o421, 0o124];
+
+const FULL_BOARD: u16 = 0o777;
+
+// Independent reference checker, built on cell masks instead of the engine lines.
+pub fn reference_outcome(board: &Board) -> Outcome {
+    let mask = |mark: Mark| {
+        board
+            .iter()
+            .flatten()
+            .enumerate()
+            .filter(|(_, cell)| **cell == Some(mark))
+            .fold(0u16, |mask, (index, _)| mask | 1 << index)
+    };
+    for mark in [Mark::X, Mark::O] {
+        if WINNING_MASKS.iter().any(|line| mask(mark) & line == *line) {
+            return Outcome::Won(mark);
+        }
+    }
+    if mask(Mark::X) | mask(Mark::O) == FULL_BOARD {
+        Outcome::Tie
+    } else {
+        Outcome::InProgress
+    }
+}
@@ -0,0 +1,123 @@
+// Exhaustive and property-based tests of the game engine rules.
+mod common;
+
+use common::reference_outcome;
+use proptest::prelude::*;
+use tic_tac_toe::engine::{self, Board, Mark, MoveError, Outcome, Position};
+
+fn position(cell: u8) -> Position {
+    Position::new(cell / 3, cell % 3)
+}
+
+#[derive(Default)]
+struct Totals {
+    games: u32,
+    x_wins: u32,
+    o_wins: u32,
+    ties: u32,
+}
+
+// Walks every legal continuation of `board`, checking the engine on each position.
+fn enumerate(board: &Board, mark: Mark, totals: &mut Totals) {
+    let legal_moves = engine::legal_moves(board);
+    for cell in 0..9 {
+        let position = position(cell);
+        let mut next = *board;
+        let result = engine::apply_move(&mut next, mark, position);
+        if !legal_moves.contains(&position) {
+            assert!(result.is_err());
+            assert_eq!(next, *board);
+            continue;
+        }
+        assert_eq!(engine::apply_move(&mut board.clone(), mark.opponent(), position), Err(MoveError::NotYourTurn));
+        let outcome = result.unwrap();
+        assert_eq!(outcome, reference_outcome(&next));
+        match outcome {
+            Outcome::InProgress => enumerate(&next, mark.opponent(), totals),
+            Outcome::Won(winner) => {
+                assert_eq!(winner, mark);
+                assert!(engine::legal_moves(&next).is_empty());
+                totals.games += 1;
+                match winner {
+                    Mark::X => totals.x_wins += 1,
+                    Mark::O => totals.o_wins += 1,
+                }
+            }
+            Outcome::Tie => {
+                assert!(engine::legal_moves(&next).is_empty());
+                totals.games += 1;
+                totals.ties += 1;
+            }
+        }
+    }
+}
+
+#[test]
+fn enumerates_every_possible_game() {
+    let mut totals = Totals::default();
+    enumerate(&Board::default(), Mark::X, &mut totals);
+
+    assert_eq!(totals.games, 255_168);
+    assert_eq!(totals.x_wins, 131_184);
+    assert_eq!(totals.o_wins, 77_904);
+    assert_eq!(totals.ties, 46_080);
+}
+
+fn cell_order() -> impl Strategy<Value = Vec<u8>> {
+    Just((0..9).collect::<Vec<u8>>()).prop_shuffle()
+}
+
+proptest! {
+    // Plays the cells in a random order until the game ends.
+    #[test]
+    fn random_games_follow_the_rules(order in cell_order()) {
+        let mut board = Board::default();
+        let mut outcome = Outcome::InProgress;
+        for (index, &cell) in order.iter().enumerate() {
+            let mark = if index % 2 == 0 { Mark::X } else { Mark::O };
+            if outcome != Outcome::InProgress {
+                // No move is accepted once the game is over
+                prop_assert_eq!(engine::apply_move(&mut board, mark, position(cell)), Err(MoveError::GameOver));
+                prop_assert!(engine::legal_moves(&board).is_empty());
+                continue;
+            }
+            prop_assert_eq!(engine::next_mark(&board), mark);
+            prop_assert_eq!(engine::legal_moves(&board).len(), 9 - index);
+            outcome = engine::apply_move(&mut board, mark, position(cell)).unwrap();
+            prop_assert_eq!(board[(cell / 3) as usize][(cell % 3) as usize], Some(mark));
+            prop_assert_eq!(outcome, reference_outcome(&board));
+            prop_assert_eq!(outcome, engine::outcome(&board));
+        }
+        prop_assert_ne!(outcome, Outcome::InProgress);
+    }
+
+    // Rejected moves, including out of the board or out of turn, never change the board.
+    #[test]
+    fn rejected_moves_leave_the_board_unchanged(moves in prop::collection::vec((0..4u8, 0..4u8, any::<bool>()), 0..20)) {
+        let mut board = Board::default();
+        for (row, column, plays_x) in moves {
+            let mark = if plays_x { Mark::X } else { Mark::O };
+            let before = board;
+            match engine::apply_move(&mut board, mark, Position::new(row, column)) {
+                Ok(outcome) => {
+                    prop_assert_eq!(engine::next_mark(&before), mark);
+                    prop_assert_eq!(before[row as usize][column as usize], None);
+                    prop_assert_eq!(outcome, reference_outcome(&board));
+                }
+                Err(error) => {
+                    prop_assert_eq!(board, before);
+                    let expected = if reference_outcome(&before) != Outcome::InProgress {
+                        MoveError::GameOver
+                    } else if engine::next_mark(&before) != mark {
+                        MoveError::NotYourTurn
+                    } else if row > 2 || column > 2 {
+                        MoveError::OutOfBounds
+                    } else {
+                        MoveError::Occupied
+                    };
+                    prop_assert_eq!(error, expected);
+                }
+            }
+        }
+    }
+}
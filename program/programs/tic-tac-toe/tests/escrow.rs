@@ -0,0 +1,90 @@
+mod common;
+
+use common::*;
+use solana_sdk::{
+    instruction::InstructionError,
+    pubkey::Pubkey,
+    signature::{Keypair, Signer},
+    transaction::TransactionError,
+};
+use tic_tac_toe::{Escrow, Game, GameState};
+
+async fn close_game_account(test: &mut TestContext, game: Pubkey, signer: &Keypair, rent_payer: Pubkey) -> Result<(), solana_program_test::BanksClientError> {
+    let accounts = tic_tac_toe::accounts::CloseGameAccount {
+        global_state: test.global_state,
+        game,
+        escrow: escrow_address(&game),
+        signer: signer.pubkey(),
+        rent_payer,
+        side_pool: side_pool_address(&game),
+        league: None,
+    };
+    test.process(&[instruction(accounts, tic_tac_toe::instruction::CloseGameAccount {})], &[signer])
+        .await
+}
+
+#[tokio::test]
+async fn wagers_are_held_in_the_escrow() {
+    let mut test = start().await;
+    let bet = test.global_state().await.bet;
+    let (game, player_one, _) = test.start_game().await;
+
+    let escrow = test.fetch::<Escrow>(escrow_address(&game)).await.unwrap();
+    assert_eq!(escrow.version, Escrow::VERSION);
+    assert_eq!(escrow.game, game);
+    assert_eq!(escrow.rent_payer, player_one.pubkey());
+    let escrow_rent = test.minimum_balance(Escrow::MAXIMUM_SIZE).await;
+    assert_eq!(test.balance(escrow_address(&game)).await, escrow_rent + 2 * bet);
+    // The game only holds its own rent
+    let game_rent = test.minimum_balance(Game::MAXIMUM_SIZE).await;
+    assert_eq!(test.balance(game).await, game_rent);
+    assert_eq!(test.game(game).await.pot, 2 * bet);
+}
+
+#[tokio::test]
+async fn house_bet_is_held_in_the_escrow() {
+    let mut test = start().await;
+    let owner = test.create_funded_player().await;
+    let house = test.create_funded_player().await;
+    let bet = test.global_state().await.bet;
+    test.setup_house(&owner, house.pubkey(), 10 * bet).await;
+    let player = test.create_funded_player().await;
+
+    let game = test.challenge_house(&player).await.unwrap();
+
+    let escrow_rent = test.minimum_balance(Escrow::MAXIMUM_SIZE).await;
+    assert_eq!(test.balance(escrow_address(&game)).await, escrow_rent + 2 * bet);
+    assert_eq!(test.balance(game).await, test.minimum_balance(Game::MAXIMUM_SIZE).await);
+}
+
+#[tokio::test]
+async fn closing_refunds_the_rent_to_its_payer() {
+    let mut test = start().await;
+    let (game, player_one, player_two) = test.start_game().await;
+    test.play_moves(game, &player_one, &player_two, &PLAYER_ONE_WINS).await;
+    let rent = test.minimum_balance(Game::MAXIMUM_SIZE).await + test.minimum_balance(Escrow::MAXIMUM_SIZE).await;
+    let balance_before = test.balance(player_one.pubkey()).await;
+
+    // Another wallet cannot collect the rent
+    let result = close_game_account(&mut test, game, &player_one, player_two.pubkey()).await;
+    let has_one = InstructionError::Custom(anchor_lang::error::ErrorCode::ConstraintHasOne.into());
+    assert_eq!(result.unwrap_err().unwrap(), TransactionError::InstructionError(0, has_one));
+
+    close_game_account(&mut test, game, &player_one, player_one.pubkey()).await.unwrap();
+
+    assert_eq!(test.balance(player_one.pubkey()).await, balance_before + rent);
+    assert!(test.fetch::<Escrow>(escrow_address(&game)).await.is_none());
+}
+
+#[tokio::test]
+async fn canceled_game_leaves_only_rent_in_the_escrow() {
+    let mut test = start().await;
+    let player = test.create_funded_player().await;
+    let game = test.setup_game(&player).await.unwrap();
+
+    test.cancel_game(game, &player, player.pubkey()).await.unwrap();
+
+    assert_eq!(test.game(game).await.state, GameState::Canceled);
+    assert_eq!(test.balance(escrow_address(&game)).await, test.minimum_balance(Escrow::MAXIMUM_SIZE).await);
+    assert_eq!(test.balance(game).await, test.minimum_balance(Game::MAXIMUM_SIZE).await);
+}
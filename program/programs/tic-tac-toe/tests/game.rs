@@ -0,0 +1,249 @@
+mod common;
+
+use common::*;
+use solana_sdk::{
+    instruction::InstructionError, signature::Signer, system_program, transaction::TransactionError,
+};
+use tic_tac_toe::{ErrorCode, GameState, Sign};
+
+#[tokio::test]
+async fn setup_game_matches_two_players() {
+    let mut test = start().await;
+    let player_one = test.create_funded_player().await;
+    let player_two = test.create_funded_player().await;
+
+    let game = test.setup_game(&player_one).await.unwrap();
+    let game_pda = test.game(game).await;
+    assert_eq!(game_pda.state, GameState::Waiting);
+    assert_eq!(game_pda.players, [Some(player_one.pubkey()), None]);
+
+    assert_eq!(test.setup_game(&player_two).await.unwrap(), game);
+    let game_pda = test.game(game).await;
+    let global_state = test.global_state().await;
+    assert_eq!(game_pda.state, GameState::InProgress);
+    assert_eq!(game_pda.players, [Some(player_one.pubkey()), Some(player_two.pubkey())]);
+    assert_eq!(game_pda.pot, global_state.bet * 2);
+    assert_eq!(global_state.game_count, 2);
+    assert_eq!(global_state.players_mapping, vec![player_one.pubkey(), player_two.pubkey()]);
+    assert_eq!(global_state.games_mapping, vec![game, game]);
+}
+
+#[tokio::test]
+async fn player_one_wins() {
+    let mut test = start().await;
+    let (game, player_one, player_two) = test.start_game().await;
+    let global_state = test.global_state().await;
+    let balance_before = test.balance(player_one.pubkey()).await;
+
+    test.play_moves(game, &player_one, &player_two, &PLAYER_ONE_WINS).await;
+
+    let game_pda = test.game(game).await;
+    assert_eq!(game_pda.state, GameState::Won { winner: player_one.pubkey() });
+    assert_eq!(game_pda.board[0], [Some(Sign::X); 3]);
+    assert_eq!(game_pda.moves.len(), PLAYER_ONE_WINS.len());
+    assert!(game_pda.paid);
+
+    let pot = global_state.bet * 2;
+    let prize = pot - pot * global_state.fee / 100;
+    assert_eq!(test.balance(player_one.pubkey()).await, balance_before + prize);
+    assert!(test.global_state().await.players_mapping.is_empty());
+}
+
+#[tokio::test]
+async fn tie_splits_the_pot() {
+    let mut test = start().await;
+    let (game, player_one, player_two) = test.start_game().await;
+    let global_state = test.global_state().await;
+    let balances_before = [test.balance(player_one.pubkey()).await, test.balance(player_two.pubkey()).await];
+
+    test.play_moves(game, &player_one, &player_two, &TIE).await;
+
+    assert_eq!(test.game(game).await.state, GameState::Tie);
+    let pot = global_state.bet * 2;
+    let prize = pot - pot * global_state.fee / 100;
+    assert_eq!(test.balance(player_one.pubkey()).await, balances_before[0] + prize / 2);
+    assert_eq!(test.balance(player_two.pubkey()).await, balances_before[1] + prize / 2);
+}
+
+#[tokio::test]
+async fn resignation_gives_the_win_to_the_rival() {
+    let mut test = start().await;
+    let (game, player_one, player_two) = test.start_game().await;
+
+    test.cancel_game(game, &player_one, player_two.pubkey()).await.unwrap();
+
+    assert_eq!(test.game(game).await.state, GameState::Won { winner: player_two.pubkey() });
+}
+
+#[tokio::test]
+async fn resignation_to_another_rival_fails() {
+    let mut test = start().await;
+    let (game, player_one, _) = test.start_game().await;
+    let accomplice = test.create_funded_player().await;
+
+    assert_error(test.cancel_game(game, &player_one, accomplice.pubkey()).await, ErrorCode::RivalIsNotPlayer);
+}
+
+#[tokio::test]
+async fn close_game_account_returns_lamports_to_player_one() {
+    let mut test = start().await;
+    let (game, player_one, player_two) = test.start_game().await;
+    test.play_moves(game, &player_one, &player_two, &PLAYER_ONE_WINS).await;
+    let game_lamports = test.balance(game).await + test.balance(escrow_address(&game)).await;
+    let balance_before = test.balance(player_one.pubkey()).await;
+
+    test.close_game_account(game, &player_one).await.unwrap();
+
+    assert!(test.fetch::<tic_tac_toe::Game>(game).await.is_none());
+    assert_eq!(test.balance(escrow_address(&game)).await, 0);
+    assert_eq!(test.balance(player_one.pubkey()).await, balance_before + game_lamports);
+}
+
+#[tokio::test]
+async fn setup_game_twice_fails() {
+    let mut test = start().await;
+    let player = test.create_funded_player().await;
+    test.setup_game(&player).await.unwrap();
+
+    assert_error(test.setup_game(&player).await, ErrorCode::GameAlreadyInProgress);
+}
+
+#[tokio::test]
+async fn setup_game_without_funds_fails() {
+    let mut test = start().await;
+    let bet = test.global_state().await.bet;
+    let player = test.create_player(bet / 2).await;
+
+    assert_error(test.setup_game(&player).await, ErrorCode::InsufficientFunds);
+}
+
+#[tokio::test]
+async fn play_out_of_turn_fails() {
+    let mut test = start().await;
+    let (game, player_one, player_two) = test.start_game().await;
+
+    assert_error(test.play(game, &player_two, player_one.pubkey(), 0, 0).await, ErrorCode::NotPlayersTurn);
+}
+
+#[tokio::test]
+async fn play_with_another_rival_fails() {
+    let mut test = start().await;
+    let (game, player_one, _) = test.start_game().await;
+    let accomplice = test.create_funded_player().await;
+
+    assert_error(test.play(game, &player_one, accomplice.pubkey(), 0, 0).await, ErrorCode::RivalIsNotPlayer);
+}
+
+#[tokio::test]
+async fn play_on_a_set_tile_fails() {
+    let mut test = start().await;
+    let (game, player_one, player_two) = test.start_game().await;
+    test.play(game, &player_one, player_two.pubkey(), 0, 0).await.unwrap();
+
+    assert_error(test.play(game, &player_two, player_one.pubkey(), 0, 0).await, ErrorCode::TileAlreadySet);
+}
+
+#[tokio::test]
+async fn play_out_of_bounds_fails() {
+    let mut test = start().await;
+    let (game, player_one, player_two) = test.start_game().await;
+
+    assert_error(test.play(game, &player_one, player_two.pubkey(), 3, 0).await, ErrorCode::TileOutOfBounds);
+}
+
+#[tokio::test]
+async fn play_without_an_active_game_fails() {
+    let mut test = start().await;
+    let (game, player_one, _) = test.start_game().await;
+    let outsider = test.create_funded_player().await;
+
+    assert_error(test.play(game, &outsider, player_one.pubkey(), 0, 0).await, ErrorCode::PlayerHasNotAnActiveGame);
+}
+
+#[tokio::test]
+async fn play_on_a_waiting_game_fails() {
+    let mut test = start().await;
+    let player = test.create_funded_player().await;
+    let game = test.setup_game(&player).await.unwrap();
+
+    assert_error(test.play(game, &player, player.pubkey(), 0, 0).await, ErrorCode::GameNotInProgress);
+}
+
+#[tokio::test]
+async fn play_on_a_finished_game_fails() {
+    let mut test = start().await;
+    let (finished_game, player_one, player_two) = test.start_game().await;
+    test.play_moves(finished_game, &player_one, &player_two, &PLAYER_ONE_WINS).await;
+    // Player one opens a new game, so it has an active game again
+    test.setup_game(&player_one).await.unwrap();
+
+    assert_error(
+        test.play(finished_game, &player_one, player_two.pubkey(), 2, 2).await,
+        ErrorCode::GameAlreadyOver,
+    );
+}
+
+#[tokio::test]
+async fn cancel_game_by_a_non_player_fails() {
+    let mut test = start().await;
+    let (game, player_one, _) = test.start_game().await;
+    let outsider = test.create_funded_player().await;
+
+    assert_error(test.cancel_game(game, &outsider, player_one.pubkey()).await, ErrorCode::SignerIsNotPlayer);
+}
+
+#[tokio::test]
+async fn cancel_waiting_game_by_another_player_fails() {
+    let mut test = start().await;
+    let player = test.create_funded_player().await;
+    let outsider = test.create_funded_player().await;
+    let game = test.setup_game(&player).await.unwrap();
+
+    assert_error(test.cancel_game(game, &outsider, player.pubkey()).await, ErrorCode::SignerIsNotPlayer);
+}
+
+#[tokio::test]
+async fn cancel_finished_game_fails() {
+    let mut test = start().await;
+    let (game, player_one, player_two) = test.start_game().await;
+    test.play_moves(game, &player_one, &player_two, &PLAYER_ONE_WINS).await;
+
+    assert_error(test.cancel_game(game, &player_two, player_one.pubkey()).await, ErrorCode::GameNotInProgress);
+}
+
+#[tokio::test]
+async fn close_game_account_in_progress_fails() {
+    let mut test = start().await;
+    let (game, player_one, _) = test.start_game().await;
+
+    assert_error(test.close_game_account(game, &player_one).await, ErrorCode::GameAlreadyInProgress);
+}
+
+#[tokio::test]
+async fn close_game_account_by_player_two_fails() {
+    let mut test = start().await;
+    let (game, player_one, player_two) = test.start_game().await;
+    test.play_moves(game, &player_one, &player_two, &PLAYER_ONE_WINS).await;
+
+    assert_error(test.close_game_account(game, &player_two).await, ErrorCode::SignerDidNotOpenTheGameAccount);
+}
+
+#[tokio::test]
+async fn withdraw_fees_by_a_non_owner_fails() {
+    let mut test = start().await;
+    let outsider = test.create_funded_player().await;
+    let accounts = tic_tac_toe::accounts::WithdrawFees {
+        global_state: test.global_state,
+        owner: outsider.pubkey(),
+        system_program: system_program::ID,
+    };
+    let data = tic_tac_toe::instruction::WithdrawFees { amount: 1 };
+
+    let result = test.process(&[instruction(accounts, data)], &[&outsider]).await;
+
+    // Rejected by the `has_one = owner` constraint before reaching the instruction
+    assert_eq!(
+        result.unwrap_err().unwrap(),
+        TransactionError::InstructionError(0, InstructionError::Custom(anchor_lang::error::ErrorCode::ConstraintHasOne.into()))
+    );
+}
@@ -0,0 +1,219 @@
+mod common;
+
+use anchor_lang::AnchorDeserialize;
+use common::*;
+use solana_program_test::{processor, BanksClientError};
+use solana_sdk::{
+    account::Account,
+    account_info::AccountInfo,
+    entrypoint::ProgramResult,
+    instruction::AccountMeta,
+    program_error::ProgramError,
+    pubkey::Pubkey,
+    signature::{Keypair, Signer},
+    system_program,
+};
+use tic_tac_toe::{composer::hook_authority_address, ErrorCode, GameResult, GameState, GlobalState, CLOSE_GRACE_PERIOD};
+
+const RECORDING_HOOK: Pubkey = Pubkey::new_from_array([7; 32]);
+const FAILING_HOOK: Pubkey = Pubkey::new_from_array([8; 32]);
+
+// Records the game and the result it is called with in its record account, once signed by its
+// hook authority.
+fn recording_hook(_program_id: &Pubkey, accounts: &[AccountInfo], data: &[u8]) -> ProgramResult {
+    let [game, authority, record, ..] = accounts else {
+        return Err(ProgramError::NotEnoughAccountKeys);
+    };
+    if !authority.is_signer || *authority.key != hook_authority_address(&RECORDING_HOOK) {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+    let mut record = record.try_borrow_mut_data()?;
+    record[..32].copy_from_slice(game.key.as_ref());
+    record[32..32 + data.len()].copy_from_slice(data);
+    Ok(())
+}
+
+fn failing_hook(_program_id: &Pubkey, _accounts: &[AccountInfo], _data: &[u8]) -> ProgramResult {
+    Err(ProgramError::Custom(1))
+}
+
+// Starts a bank with both hooks loaded and allowed, owned by the returned wallet.
+async fn start_with_hooks() -> (TestContext, Keypair) {
+    let mut program_test = program_test();
+    program_test.add_program("recording_hook", RECORDING_HOOK, processor!(recording_hook));
+    program_test.add_program("failing_hook", FAILING_HOOK, processor!(failing_hook));
+    let mut test = start_with(program_test).await;
+    let owner = test.create_funded_player().await;
+    test.update_global_state(|global_state| global_state.owner = owner.pubkey()).await;
+    for hook in [RECORDING_HOOK, FAILING_HOOK] {
+        set_hook(&mut test, &owner, hook, true).await.unwrap();
+    }
+    (test, owner)
+}
+
+async fn set_hook(test: &mut TestContext, owner: &Keypair, hook: Pubkey, allowed: bool) -> Result<(), BanksClientError> {
+    let accounts = tic_tac_toe::accounts::SetHook {
+        global_state: test.global_state,
+        owner: owner.pubkey(),
+    };
+    test.process(&[instruction(accounts, tic_tac_toe::instruction::SetHook { hook, allowed })], &[owner])
+        .await
+}
+
+// Opens a game calling `hook` when it ends.
+async fn setup_game_with_hook(test: &mut TestContext, player: &Keypair, hook: Pubkey) -> Result<Pubkey, BanksClientError> {
+    let game = game_address(test.global_state().await.game_count);
+    let accounts = tic_tac_toe::accounts::SetupGame {
+        global_state: test.global_state,
+        game,
+        escrow: escrow_address(&game),
+        player: player.pubkey(),
+        balance: None,
+        system_program: system_program::ID,
+    };
+    test.process(&[instruction(accounts, tic_tac_toe::instruction::SetupGame { hook: Some(hook) })], &[player])
+        .await?;
+    Ok(game)
+}
+
+async fn start_game_with_hook(test: &mut TestContext, hook: Pubkey) -> (Pubkey, Keypair, Keypair) {
+    let player_one = test.create_funded_player().await;
+    let player_two = test.create_funded_player().await;
+    let game = setup_game_with_hook(test, &player_one, hook).await.unwrap();
+    test.setup_game(&player_two).await.unwrap();
+    (game, player_one, player_two)
+}
+
+// Creates the account the recording hook writes to.
+async fn create_record(test: &mut TestContext) -> Pubkey {
+    let record = Pubkey::new_unique();
+    let account = Account {
+        lamports: test.minimum_balance(256).await,
+        data: vec![0; 256],
+        owner: RECORDING_HOOK,
+        executable: false,
+        rent_epoch: 0,
+    };
+    test.context.set_account(&record, &account.into());
+    record
+}
+
+// Calls the pending hook of a settled game, passing the hook program, the hook `authority` and
+// the record account.
+async fn call_hook_with(test: &mut TestContext, game: Pubkey, hook: Pubkey, authority: Pubkey, record: Pubkey) -> Result<(), BanksClientError> {
+    let accounts = tic_tac_toe::accounts::CallGameHook { global_state: test.global_state, game };
+    let mut call = instruction(accounts, tic_tac_toe::instruction::CallGameHook {});
+    call.accounts.extend([AccountMeta::new_readonly(hook, false), AccountMeta::new_readonly(authority, false), AccountMeta::new(record, false)]);
+    test.process(&[call], &[]).await
+}
+
+async fn call_game_hook(test: &mut TestContext, game: Pubkey, hook: Pubkey, record: Pubkey) -> Result<(), BanksClientError> {
+    call_hook_with(test, game, hook, hook_authority_address(&hook), record).await
+}
+
+async fn recorded(test: &mut TestContext, record: Pubkey) -> Vec<u8> {
+    test.context.banks_client.get_account(record).await.unwrap().unwrap().data
+}
+
+#[tokio::test]
+async fn hook_is_called_with_the_result_of_the_game() {
+    let (mut test, _) = start_with_hooks().await;
+    let (game, player_one, player_two) = start_game_with_hook(&mut test, RECORDING_HOOK).await;
+    let record = create_record(&mut test).await;
+    assert_eq!(test.game(game).await.hook, Some(RECORDING_HOOK));
+    assert_error(call_game_hook(&mut test, game, RECORDING_HOOK, record).await, ErrorCode::GameNotFinished);
+
+    // The winning move pays out without calling the hook
+    test.play_moves(game, &player_one, &player_two, &PLAYER_ONE_WINS).await;
+    assert!(recorded(&mut test, record).await.iter().all(|&byte| byte == 0));
+    assert!(test.game(game).await.paid);
+
+    // Anyone calls it afterwards
+    call_game_hook(&mut test, game, RECORDING_HOOK, record).await.unwrap();
+    let data = recorded(&mut test, record).await;
+    assert_eq!(&data[..32], game.as_ref());
+    // An Anchor instruction named `on_game_end`, with the game already paid out
+    assert_eq!(&data[32..40], &anchor_lang::solana_program::hash::hash(b"global:on_game_end").to_bytes()[..8]);
+    let result = GameResult::deserialize(&mut &data[40..]).unwrap();
+    assert_eq!((result.game, result.state), (game, GameState::Won { winner: player_one.pubkey() }));
+    assert!(test.game(game).await.hook_called);
+}
+
+#[tokio::test]
+async fn failing_hook_cannot_hold_the_payout() {
+    let (mut test, _) = start_with_hooks().await;
+    let (game, player_one, player_two) = start_game_with_hook(&mut test, FAILING_HOOK).await;
+    let record = create_record(&mut test).await;
+
+    let balance_before = test.balance(player_one.pubkey()).await;
+    test.play_moves(game, &player_one, &player_two, &PLAYER_ONE_WINS).await;
+    assert_eq!(test.game(game).await.state, GameState::Won { winner: player_one.pubkey() });
+    assert!(test.balance(player_one.pubkey()).await > balance_before);
+
+    // The hook stays pending, so player one cannot close the game before it is called
+    assert!(call_game_hook(&mut test, game, FAILING_HOOK, record).await.is_err());
+    assert!(!test.game(game).await.hook_called);
+    assert_error(test.close_game_account(game, &player_one).await, ErrorCode::HookPending);
+    test.advance_clock(CLOSE_GRACE_PERIOD).await;
+    test.close_finished_game(game, None).await.unwrap();
+}
+
+#[tokio::test]
+async fn hook_is_called_only_once() {
+    let (mut test, _) = start_with_hooks().await;
+    let (game, player_one, player_two) = start_game_with_hook(&mut test, RECORDING_HOOK).await;
+    let record = create_record(&mut test).await;
+    test.play_moves(game, &player_one, &player_two, &PLAYER_ONE_WINS).await;
+
+    assert_error(call_game_hook(&mut test, game, FAILING_HOOK, record).await, ErrorCode::InvalidHookProgram);
+    call_game_hook(&mut test, game, RECORDING_HOOK, record).await.unwrap();
+    assert_error(call_game_hook(&mut test, game, RECORDING_HOOK, record).await, ErrorCode::NoPendingHook);
+    test.close_game_account(game, &player_one).await.unwrap();
+}
+
+#[tokio::test]
+async fn removed_hook_cannot_be_called() {
+    let (mut test, owner) = start_with_hooks().await;
+    let (game, player_one, player_two) = start_game_with_hook(&mut test, RECORDING_HOOK).await;
+    let record = create_record(&mut test).await;
+
+    set_hook(&mut test, &owner, RECORDING_HOOK, false).await.unwrap();
+    test.play_moves(game, &player_one, &player_two, &PLAYER_ONE_WINS).await;
+
+    assert!(test.game(game).await.paid);
+    assert_error(call_game_hook(&mut test, game, RECORDING_HOOK, record).await, ErrorCode::HookNotAllowed);
+    assert!(recorded(&mut test, record).await.iter().all(|&byte| byte == 0));
+}
+
+#[tokio::test]
+async fn hook_authority_is_the_pda_of_the_hook() {
+    let (mut test, _) = start_with_hooks().await;
+    let (game, player_one, player_two) = start_game_with_hook(&mut test, RECORDING_HOOK).await;
+    let record = create_record(&mut test).await;
+    test.play_moves(game, &player_one, &player_two, &PLAYER_ONE_WINS).await;
+
+    // The authority of another hook cannot sign for this one
+    assert_ne!(hook_authority_address(&RECORDING_HOOK), hook_authority_address(&FAILING_HOOK));
+    let result = call_hook_with(&mut test, game, RECORDING_HOOK, hook_authority_address(&FAILING_HOOK), record).await;
+    assert_error(result, ErrorCode::InvalidHookAuthority);
+    let result = call_hook_with(&mut test, game, RECORDING_HOOK, Pubkey::new_unique(), record).await;
+    assert_error(result, ErrorCode::InvalidHookAuthority);
+    call_game_hook(&mut test, game, RECORDING_HOOK, record).await.unwrap();
+}
+
+#[tokio::test]
+async fn games_only_take_allowed_hooks() {
+    let (mut test, owner) = start_with_hooks().await;
+    let player = test.create_funded_player().await;
+
+    let result = setup_game_with_hook(&mut test, &player, Pubkey::new_unique()).await;
+    assert_error(result, ErrorCode::HookNotAllowed);
+
+    // Allowing a hook twice keeps one entry
+    set_hook(&mut test, &owner, RECORDING_HOOK, true).await.unwrap();
+    while test.global_state().await.hooks.len() < GlobalState::MAX_HOOKS {
+        set_hook(&mut test, &owner, Pubkey::new_unique(), true).await.unwrap();
+    }
+    assert_error(set_hook(&mut test, &owner, Pubkey::new_unique(), true).await, ErrorCode::HookListFull);
+    assert_eq!(test.global_state().await.hooks.iter().filter(|&&hook| hook == RECORDING_HOOK).count(), 1);
+}
@@ -0,0 +1,185 @@
+mod common;
+
+use anchor_lang::AnchorDeserialize;
+use common::*;
+use solana_program_test::BanksClientError;
+use solana_sdk::{
+    instruction::{Instruction, InstructionError},
+    native_token::LAMPORTS_PER_SOL,
+    pubkey::Pubkey,
+    signature::{Keypair, Signer},
+    system_program,
+    transaction::{Transaction, TransactionError},
+};
+use tic_tac_toe::composer::hosted_game_address;
+use tic_tac_toe::{Escrow, ErrorCode, Game, GameResult, GameState, SideBetOutcome, SideBetPool, CLOSE_GRACE_PERIOD};
+
+const ID: u64 = 7;
+
+fn create_hosted_game_instruction(test: &TestContext, host: &Pubkey, id: u64, players: [Pubkey; 2]) -> Instruction {
+    let game = hosted_game_address(host, id);
+    let accounts = tic_tac_toe::accounts::CreateHostedGame {
+        global_state: test.global_state,
+        game,
+        escrow: escrow_address(&game),
+        host: *host,
+        player_one: players[0],
+        player_two: players[1],
+        system_program: system_program::ID,
+    };
+    instruction(accounts, tic_tac_toe::instruction::CreateHostedGame { id, hook: None })
+}
+
+async fn create_hosted_game(test: &mut TestContext, host: &Keypair, id: u64, players: [&Keypair; 2]) -> Result<Pubkey, BanksClientError> {
+    let create = create_hosted_game_instruction(test, &host.pubkey(), id, [players[0].pubkey(), players[1].pubkey()]);
+    test.process(&[create], &[host, players[0], players[1]]).await?;
+    Ok(hosted_game_address(&host.pubkey(), id))
+}
+
+// Sends the instruction and decodes the `GameResult` it returned.
+async fn returned_result(test: &mut TestContext, instruction: Instruction, signers: &[&Keypair]) -> Result<GameResult, BanksClientError> {
+    let payer = test.context.payer.insecure_clone();
+    let mut all_signers = vec![&payer];
+    all_signers.extend_from_slice(signers);
+    let blockhash = test.context.get_new_latest_blockhash().await.unwrap();
+    let transaction = Transaction::new_signed_with_payer(&[instruction], Some(&payer.pubkey()), &all_signers, blockhash);
+    let processed = test.context.banks_client.process_transaction_with_metadata(transaction).await?;
+    processed.result?;
+    let return_data = processed.metadata.unwrap().return_data.unwrap();
+    assert_eq!(return_data.program_id, tic_tac_toe::ID);
+    Ok(GameResult::try_from_slice(&return_data.data).unwrap())
+}
+
+async fn settle_hosted_game(test: &mut TestContext, host: &Keypair, id: u64) -> Result<GameResult, BanksClientError> {
+    let game = hosted_game_address(&host.pubkey(), id);
+    let accounts = tic_tac_toe::accounts::SettleHostedGame {
+        global_state: test.global_state,
+        game,
+        escrow: escrow_address(&game),
+        host: host.pubkey(),
+        side_pool: side_pool_address(&game),
+    };
+    returned_result(test, instruction(accounts, tic_tac_toe::instruction::SettleHostedGame { id }), &[host]).await
+}
+
+// Creates a host with two players in a hosted game.
+async fn start_hosted_game(test: &mut TestContext) -> (Pubkey, Keypair, Keypair, Keypair) {
+    let host = test.create_funded_player().await;
+    let player_one = test.create_funded_player().await;
+    let player_two = test.create_funded_player().await;
+    let game = create_hosted_game(test, &host, ID, [&player_one, &player_two]).await.unwrap();
+    (game, host, player_one, player_two)
+}
+
+#[tokio::test]
+async fn host_creates_and_settles_a_game() {
+    let mut test = start().await;
+    let (game, host, player_one, player_two) = start_hosted_game(&mut test).await;
+
+    let created = test.game(game).await;
+    assert_eq!((created.number, created.state, created.pot), (ID, GameState::InProgress, 0));
+    assert_eq!(test.fetch::<Escrow>(escrow_address(&game)).await.unwrap().rent_payer, host.pubkey());
+    let host_before = test.balance(host.pubkey()).await;
+    let rent = test.minimum_balance(Game::MAXIMUM_SIZE).await + test.minimum_balance(Escrow::MAXIMUM_SIZE).await;
+
+    test.play_moves(game, &player_one, &player_two, &PLAYER_ONE_WINS).await;
+    let result = settle_hosted_game(&mut test, &host, ID).await.unwrap();
+
+    assert_eq!(result.game, game);
+    assert_eq!(result.state, GameState::Won { winner: player_one.pubkey() });
+    assert_eq!((result.number, result.turns), (ID, 5));
+    assert!(test.fetch::<Game>(game).await.is_none());
+    assert_eq!(test.balance(host.pubkey()).await, host_before + rent);
+    // Both players are free to join another game
+    test.setup_game(&player_one).await.unwrap();
+    test.setup_game(&player_two).await.unwrap();
+}
+
+#[tokio::test]
+async fn game_result_is_readable_while_playing() {
+    let mut test = start().await;
+    let (game, _, player_one, player_two) = start_hosted_game(&mut test).await;
+    test.play(game, &player_one, player_two.pubkey(), 1, 1).await.unwrap();
+
+    let read = instruction(tic_tac_toe::accounts::ReadGameResult { game }, tic_tac_toe::instruction::GameResult {});
+    let result = returned_result(&mut test, read, &[]).await.unwrap();
+
+    assert_eq!(result.players, [Some(player_one.pubkey()), Some(player_two.pubkey())]);
+    assert_eq!((result.state, result.turns), (GameState::InProgress, 1));
+}
+
+#[tokio::test]
+async fn only_the_host_closes_a_hosted_game() {
+    let mut test = start().await;
+    let (game, host, player_one, player_two) = start_hosted_game(&mut test).await;
+
+    assert_error(settle_hosted_game(&mut test, &host, ID).await, ErrorCode::GameNotFinished);
+    test.cancel_game(game, &player_two, player_one.pubkey()).await.unwrap();
+
+    assert_error(test.close_game_account(game, &player_one).await, ErrorCode::GameIsHosted);
+    test.advance_clock(CLOSE_GRACE_PERIOD).await;
+    assert_error(test.close_finished_game(game, None).await, ErrorCode::GameIsHosted);
+    let result = settle_hosted_game(&mut test, &host, ID).await.unwrap();
+    assert_eq!(result.state, GameState::Won { winner: player_one.pubkey() });
+}
+
+#[tokio::test]
+async fn hosted_game_players_are_validated() {
+    let mut test = start().await;
+    let host = test.create_funded_player().await;
+    let player = test.create_funded_player().await;
+
+    for players in [[&player, &player], [&host, &player]] {
+        assert_error(create_hosted_game(&mut test, &host, ID, players).await, ErrorCode::InvalidHostedPlayers);
+    }
+
+    // Players already in a game cannot be put in another one
+    let (_, busy, _) = test.start_game().await;
+    let result = create_hosted_game(&mut test, &host, ID, [&player, &busy]).await;
+    assert_error(result, ErrorCode::GameAlreadyInProgress);
+}
+
+#[tokio::test]
+async fn hosted_game_players_must_sign() {
+    let mut test = start().await;
+    let host = test.create_funded_player().await;
+    let player = test.create_funded_player().await;
+    let bystander = test.create_funded_player().await;
+
+    // The host cannot take the slot of a user in the players mapping without their consent
+    let mut create = create_hosted_game_instruction(&test, &host.pubkey(), ID, [player.pubkey(), bystander.pubkey()]);
+    create.accounts[5].is_signer = false;
+    let error = test.process(&[create], &[&host, &player]).await.unwrap_err().unwrap();
+    let not_signed = InstructionError::Custom(anchor_lang::error::ErrorCode::AccountNotSigner.into());
+    assert_eq!(error, TransactionError::InstructionError(0, not_signed));
+    assert!(test.global_state().await.players_mapping.is_empty());
+}
+
+#[tokio::test]
+async fn settling_a_hosted_game_settles_its_side_bets() {
+    let mut test = start().await;
+    let (game, host, player_one, player_two) = start_hosted_game(&mut test).await;
+    let side_pool = side_pool_address(&game);
+    let payer = test.context.payer.pubkey();
+    let accounts = tic_tac_toe::accounts::OpenSidePool { side_pool, game, payer, system_program: system_program::ID };
+    test.process(&[instruction(accounts, tic_tac_toe::instruction::OpenSidePool {})], &[]).await.unwrap();
+    let backer = test.create_funded_player().await;
+    let side_bet = Pubkey::find_program_address(&[b"side_bet", side_pool.as_ref(), backer.pubkey().as_ref()], &tic_tac_toe::ID).0;
+    let accounts = tic_tac_toe::accounts::PlaceSideBet { side_pool, game, side_bet, bettor: backer.pubkey(), system_program: system_program::ID };
+    let bet = LAMPORTS_PER_SOL;
+    let data = tic_tac_toe::instruction::PlaceSideBet { outcome: SideBetOutcome::PlayerOne, amount: bet };
+    test.process(&[instruction(accounts, data)], &[&backer]).await.unwrap();
+
+    test.play_moves(game, &player_one, &player_two, &PLAYER_ONE_WINS).await;
+    settle_hosted_game(&mut test, &host, ID).await.unwrap();
+
+    // The pool is settled on the result before the game is gone, so the backer still wins
+    let pool = test.fetch::<SideBetPool>(side_pool).await.unwrap();
+    assert!(pool.settled);
+    assert_eq!(pool.outcome, Some(SideBetOutcome::PlayerOne));
+    let backer_before = test.balance(backer.pubkey()).await;
+    let side_bet_rent = test.balance(side_bet).await;
+    let accounts = tic_tac_toe::accounts::ClaimSideBet { global_state: test.global_state, side_pool, game, side_bet, bettor: backer.pubkey() };
+    test.process(&[instruction(accounts, tic_tac_toe::instruction::ClaimSideBet {})], &[&backer]).await.unwrap();
+    assert_eq!(test.balance(backer.pubkey()).await, backer_before + side_bet_rent + bet - pool.fee);
+}
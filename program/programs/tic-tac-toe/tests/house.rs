@@ -0,0 +1,160 @@
+mod common;
+
+use common::*;
+use solana_sdk::{
+    instruction::InstructionError,
+    native_token::LAMPORTS_PER_SOL,
+    signature::{Keypair, Signer},
+    transaction::TransactionError,
+};
+use tic_tac_toe::{ErrorCode, GameState};
+
+const TREASURY: u64 = LAMPORTS_PER_SOL;
+
+// Owner, house bot and a player that challenged it in a new game.
+async fn start_house_game(test: &mut TestContext) -> (Keypair, Keypair, solana_sdk::pubkey::Pubkey) {
+    let owner = test.create_funded_player().await;
+    let house = test.create_funded_player().await;
+    test.setup_house(&owner, house.pubkey(), TREASURY).await;
+    let player = test.create_funded_player().await;
+    let game = test.challenge_house(&player).await.unwrap();
+    (house, player, game)
+}
+
+#[tokio::test]
+async fn challenge_house_matches_the_bet_from_the_treasury() {
+    let mut test = start().await;
+    let owner = test.create_funded_player().await;
+    let house = test.create_funded_player().await;
+    test.setup_house(&owner, house.pubkey(), TREASURY).await;
+    let player = test.create_funded_player().await;
+    let treasury_before = test.balance(test.global_state).await;
+
+    let game = test.challenge_house(&player).await.unwrap();
+
+    let bet = test.global_state().await.bet;
+    let game_pda = test.game(game).await;
+    assert_eq!(game_pda.state, GameState::InProgress);
+    assert_eq!(game_pda.players, [Some(player.pubkey()), Some(house.pubkey())]);
+    assert!(game_pda.house);
+    assert_eq!(game_pda.pot, 2 * bet);
+    assert_eq!(test.balance(test.global_state).await, treasury_before - bet);
+    let global_state = test.global_state().await;
+    assert_eq!(global_state.game_count, 2);
+    assert_eq!(global_state.players_mapping, vec![player.pubkey(), house.pubkey()]);
+    assert_eq!(global_state.games_mapping, vec![game, game]);
+}
+
+#[tokio::test]
+async fn house_winnings_go_to_the_treasury() {
+    let mut test = start().await;
+    let (house, player, game) = start_house_game(&mut test).await;
+    let treasury_before = test.balance(test.global_state).await;
+    let house_before = test.balance(house.pubkey()).await;
+    let pot = test.game(game).await.pot;
+
+    test.play_moves(game, &player, &house, &[(0, 0), (1, 0), (2, 2), (1, 1), (0, 2), (1, 2)]).await;
+
+    assert_eq!(test.game(game).await.state, GameState::Won { winner: house.pubkey() });
+    // The fee and the payout both go back to the treasury
+    assert_eq!(test.balance(test.global_state).await, treasury_before + pot);
+    assert_eq!(test.balance(house.pubkey()).await, house_before);
+    assert!(test.global_state().await.players_mapping.is_empty());
+}
+
+#[tokio::test]
+async fn player_beating_the_house_is_paid_from_the_pot() {
+    let mut test = start().await;
+    let (house, player, game) = start_house_game(&mut test).await;
+    let treasury_before = test.balance(test.global_state).await;
+    let player_before = test.balance(player.pubkey()).await;
+    let pot = test.game(game).await.pot;
+    let fee = pot * test.global_state().await.fee / 100;
+
+    test.play_moves(game, &player, &house, &PLAYER_ONE_WINS).await;
+
+    assert_eq!(test.game(game).await.state, GameState::Won { winner: player.pubkey() });
+    assert_eq!(test.balance(player.pubkey()).await, player_before + pot - fee);
+    assert_eq!(test.balance(test.global_state).await, treasury_before + fee);
+}
+
+#[tokio::test]
+async fn house_share_of_a_tie_goes_to_the_treasury() {
+    let mut test = start().await;
+    let (house, player, game) = start_house_game(&mut test).await;
+    let treasury_before = test.balance(test.global_state).await;
+    let house_before = test.balance(house.pubkey()).await;
+    let pot = test.game(game).await.pot;
+    let fee = pot * test.global_state().await.fee / 100;
+    let payout = pot - fee;
+
+    test.play_moves(game, &player, &house, &TIE).await;
+
+    assert_eq!(test.game(game).await.state, GameState::Tie);
+    assert_eq!(test.balance(test.global_state).await, treasury_before + fee + payout / 2);
+    assert_eq!(test.balance(house.pubkey()).await, house_before);
+}
+
+#[tokio::test]
+async fn house_can_resign() {
+    let mut test = start().await;
+    let (house, player, game) = start_house_game(&mut test).await;
+    let player_before = test.balance(player.pubkey()).await;
+    let pot = test.game(game).await.pot;
+    let fee = pot * test.global_state().await.fee / 100;
+
+    test.cancel_game(game, &house, player.pubkey()).await.unwrap();
+
+    assert_eq!(test.game(game).await.state, GameState::Won { winner: player.pubkey() });
+    assert_eq!(test.balance(player.pubkey()).await, player_before + pot - fee);
+}
+
+#[tokio::test]
+async fn challenge_house_without_a_house_fails() {
+    let mut test = start().await;
+    let player = test.create_funded_player().await;
+
+    assert_error(test.challenge_house(&player).await, ErrorCode::HouseNotSet);
+
+    let owner = test.create_funded_player().await;
+    test.setup_house(&owner, Keypair::new().pubkey(), TREASURY).await;
+    test.set_house(&owner, None).await.unwrap();
+    assert_error(test.challenge_house(&player).await, ErrorCode::HouseNotSet);
+}
+
+#[tokio::test]
+async fn challenge_house_with_an_empty_treasury_fails() {
+    let mut test = start().await;
+    let owner = test.create_funded_player().await;
+    test.update_global_state(|global_state| global_state.owner = owner.pubkey()).await;
+    test.set_house(&owner, Some(Keypair::new().pubkey())).await.unwrap();
+    let player = test.create_funded_player().await;
+
+    assert_error(test.challenge_house(&player).await, ErrorCode::TreasuryCannotCoverBet);
+}
+
+#[tokio::test]
+async fn challenge_house_while_a_player_is_waiting_fails() {
+    let mut test = start().await;
+    let owner = test.create_funded_player().await;
+    test.setup_house(&owner, Keypair::new().pubkey(), TREASURY).await;
+    let waiting = test.create_funded_player().await;
+    test.setup_game(&waiting).await.unwrap();
+    let player = test.create_funded_player().await;
+
+    assert_error(test.challenge_house(&player).await, ErrorCode::PlayerWaitingForRival);
+    assert_error(test.challenge_house(&waiting).await, ErrorCode::GameAlreadyInProgress);
+}
+
+#[tokio::test]
+async fn set_house_by_a_non_owner_fails() {
+    let mut test = start().await;
+    let outsider = test.create_funded_player().await;
+
+    let result = test.set_house(&outsider, Some(outsider.pubkey())).await;
+
+    assert_eq!(
+        result.unwrap_err().unwrap(),
+        TransactionError::InstructionError(0, InstructionError::Custom(anchor_lang::error::ErrorCode::ConstraintHasOne.into()))
+    );
+}
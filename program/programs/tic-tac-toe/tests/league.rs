@@ -0,0 +1,403 @@
+mod common;
+
+use common::*;
+use solana_program_test::BanksClientError;
+use solana_sdk::{
+    instruction::AccountMeta,
+    native_token::LAMPORTS_PER_SOL,
+    pubkey::Pubkey,
+    signature::{Keypair, Signer},
+    system_program,
+};
+use tic_tac_toe::{ErrorCode, GameState, League, LeagueState, CLOSE_GRACE_PERIOD, LEAGUE_SEASON_DURATION};
+
+const ENTRY_FEE: u64 = LAMPORTS_PER_SOL;
+
+fn league_address(authority: Pubkey, id: u64) -> Pubkey {
+    Pubkey::find_program_address(&[b"league", authority.as_ref(), &id.to_le_bytes()], &tic_tac_toe::ID).0
+}
+
+fn league_game_address(league: Pubkey, fixture_index: u16) -> Pubkey {
+    Pubkey::find_program_address(&[b"game", league.as_ref(), &fixture_index.to_le_bytes()], &tic_tac_toe::ID).0
+}
+
+async fn create_league(test: &mut TestContext, authority: &Keypair, prize_shares: Vec<u8>) -> Result<Pubkey, BanksClientError> {
+    let league = league_address(authority.pubkey(), 1);
+    let accounts = tic_tac_toe::accounts::CreateLeague {
+        league,
+        authority: authority.pubkey(),
+        system_program: system_program::ID,
+    };
+    let data = tic_tac_toe::instruction::CreateLeague { id: 1, entry_fee: ENTRY_FEE, prize_shares };
+    test.process(&[instruction(accounts, data)], &[authority]).await?;
+    Ok(league)
+}
+
+async fn join_league(test: &mut TestContext, league: Pubkey, player: &Keypair) -> Result<(), BanksClientError> {
+    let accounts = tic_tac_toe::accounts::JoinLeague {
+        league,
+        player: player.pubkey(),
+        system_program: system_program::ID,
+    };
+    test.process(&[instruction(accounts, tic_tac_toe::instruction::JoinLeague {})], &[player])
+        .await
+}
+
+async fn start_league(test: &mut TestContext, league: Pubkey, authority: &Keypair) -> Result<(), BanksClientError> {
+    let accounts = tic_tac_toe::accounts::StartLeague {
+        league,
+        authority: authority.pubkey(),
+    };
+    test.process(&[instruction(accounts, tic_tac_toe::instruction::StartLeague {})], &[authority])
+        .await
+}
+
+async fn setup_league_game(test: &mut TestContext, league: Pubkey, fixture_index: u16, player: &Keypair) -> Result<Pubkey, BanksClientError> {
+    let game = league_game_address(league, fixture_index);
+    let accounts = tic_tac_toe::accounts::SetupLeagueGame {
+        global_state: test.global_state,
+        league,
+        game,
+        escrow: escrow_address(&game),
+        player: player.pubkey(),
+        system_program: system_program::ID,
+    };
+    let data = tic_tac_toe::instruction::SetupLeagueGame { fixture_index };
+    test.process(&[instruction(accounts, data)], &[player]).await?;
+    Ok(game)
+}
+
+async fn record_league_result(test: &mut TestContext, league: Pubkey, game: Pubkey, fixture_index: u16) -> Result<(), BanksClientError> {
+    let accounts = tic_tac_toe::accounts::RecordLeagueResult { league, game };
+    let data = tic_tac_toe::instruction::RecordLeagueResult { fixture_index };
+    test.process(&[instruction(accounts, data)], &[]).await
+}
+
+async fn finalize_league(test: &mut TestContext, league: Pubkey, ranking: &[Pubkey]) -> Result<(), BanksClientError> {
+    let mut instruction = instruction(
+        tic_tac_toe::accounts::FinalizeLeague { league },
+        tic_tac_toe::instruction::FinalizeLeague {},
+    );
+    instruction
+        .accounts
+        .extend(ranking.iter().map(|&player| AccountMeta::new(player, false)));
+    test.process(&[instruction], &[]).await
+}
+
+async fn refund_league(test: &mut TestContext, league: Pubkey, players: &[Pubkey]) -> Result<(), BanksClientError> {
+    let mut instruction = instruction(
+        tic_tac_toe::accounts::RefundLeague { league },
+        tic_tac_toe::instruction::RefundLeague {},
+    );
+    instruction
+        .accounts
+        .extend(players.iter().map(|&player| AccountMeta::new(player, false)));
+    test.process(&[instruction], &[]).await
+}
+
+// Creates a started league with `players` funded players.
+async fn started_league(test: &mut TestContext, players: usize, prize_shares: Vec<u8>) -> (Pubkey, Keypair, Vec<Keypair>) {
+    let authority = test.create_funded_player().await;
+    let league = create_league(test, &authority, prize_shares).await.unwrap();
+    let mut league_players = Vec::new();
+    for _ in 0..players {
+        let player = test.create_funded_player().await;
+        join_league(test, league, &player).await.unwrap();
+        league_players.push(player);
+    }
+    start_league(test, league, &authority).await.unwrap();
+    (league, authority, league_players)
+}
+
+// Sets up the fixture game and returns it with its players ordered as in the game.
+async fn play_fixture(test: &mut TestContext, league: Pubkey, fixture_index: u16, players: &[Keypair]) -> (Pubkey, usize, usize) {
+    let fixture = test.fetch::<League>(league).await.unwrap().fixtures[fixture_index as usize].clone();
+    let (one, two) = (fixture.player_one as usize, fixture.player_two as usize);
+    let game = setup_league_game(test, league, fixture_index, &players[one]).await.unwrap();
+    setup_league_game(test, league, fixture_index, &players[two]).await.unwrap();
+    (game, one, two)
+}
+
+#[tokio::test]
+async fn round_robin_league_pays_out_by_ranking() {
+    let mut test = start().await;
+    let (league, _, players) = started_league(&mut test, 3, vec![70, 30]).await;
+
+    let league_account = test.fetch::<League>(league).await.unwrap();
+    assert_eq!(league_account.state, LeagueState::InProgress);
+    assert_eq!(league_account.pool, ENTRY_FEE * 3);
+    assert_eq!(league_account.fixtures.len(), 3);
+
+    // The first player of every fixture wins, the last fixture is a tie
+    for fixture_index in 0..3u16 {
+        let (game, one, two) = play_fixture(&mut test, league, fixture_index, &players).await;
+        assert_eq!(test.game(game).await.league, Some(league));
+        let tiles: &[(u8, u8)] = if fixture_index == 2 { &TIE } else { &PLAYER_ONE_WINS };
+        test.play_moves(game, &players[one], &players[two], tiles).await;
+        record_league_result(&mut test, league, game, fixture_index).await.unwrap();
+    }
+
+    let league_account = test.fetch::<League>(league).await.unwrap();
+    let ranking: Vec<Pubkey> = league_account.ranking().iter().map(|&index| players[index].pubkey()).collect();
+    let standing_points: Vec<u16> = league_account.ranking().iter().map(|&index| league_account.standings[index].points).collect();
+    assert_eq!(standing_points.iter().map(|&points| points as u32).sum::<u32>(), 3 + 3 + 2);
+
+    let balances_before = [test.balance(ranking[0]).await, test.balance(ranking[1]).await];
+    finalize_league(&mut test, league, &ranking[..2]).await.unwrap();
+
+    let league_account = test.fetch::<League>(league).await.unwrap();
+    assert_eq!(league_account.state, LeagueState::Finished);
+    assert_eq!(league_account.pool, 0);
+    assert_eq!(test.balance(ranking[0]).await, balances_before[0] + ENTRY_FEE * 3 * 70 / 100);
+    assert_eq!(test.balance(ranking[1]).await, balances_before[1] + ENTRY_FEE * 3 * 30 / 100);
+}
+
+#[tokio::test]
+async fn create_league_with_invalid_prize_shares_fails() {
+    let mut test = start().await;
+    let authority = test.create_funded_player().await;
+
+    assert_error(create_league(&mut test, &authority, vec![50, 40]).await, ErrorCode::InvalidPrizeShares);
+}
+
+#[tokio::test]
+async fn join_league_twice_fails() {
+    let mut test = start().await;
+    let authority = test.create_funded_player().await;
+    let league = create_league(&mut test, &authority, vec![100]).await.unwrap();
+    let player = test.create_funded_player().await;
+    join_league(&mut test, league, &player).await.unwrap();
+
+    assert_error(join_league(&mut test, league, &player).await, ErrorCode::PlayerAlreadyInLeague);
+}
+
+#[tokio::test]
+async fn join_full_league_fails() {
+    let mut test = start().await;
+    let authority = test.create_funded_player().await;
+    let league = create_league(&mut test, &authority, vec![100]).await.unwrap();
+    for _ in 0..8 {
+        let player = test.create_funded_player().await;
+        join_league(&mut test, league, &player).await.unwrap();
+    }
+    let player = test.create_funded_player().await;
+
+    assert_error(join_league(&mut test, league, &player).await, ErrorCode::LeagueFull);
+}
+
+#[tokio::test]
+async fn join_started_league_fails() {
+    let mut test = start().await;
+    let (league, _, _) = started_league(&mut test, 2, vec![100]).await;
+    let player = test.create_funded_player().await;
+
+    assert_error(join_league(&mut test, league, &player).await, ErrorCode::LeagueNotRegistering);
+}
+
+#[tokio::test]
+async fn start_league_without_enough_players_fails() {
+    let mut test = start().await;
+    let authority = test.create_funded_player().await;
+    let league = create_league(&mut test, &authority, vec![60, 40]).await.unwrap();
+    let player = test.create_funded_player().await;
+    join_league(&mut test, league, &player).await.unwrap();
+
+    assert_error(start_league(&mut test, league, &authority).await, ErrorCode::NotEnoughLeaguePlayers);
+}
+
+#[tokio::test]
+async fn setup_league_game_before_start_fails() {
+    let mut test = start().await;
+    let authority = test.create_funded_player().await;
+    let league = create_league(&mut test, &authority, vec![100]).await.unwrap();
+    let player = test.create_funded_player().await;
+    join_league(&mut test, league, &player).await.unwrap();
+
+    assert_error(setup_league_game(&mut test, league, 0, &player).await, ErrorCode::LeagueNotInProgress);
+}
+
+#[tokio::test]
+async fn setup_unknown_fixture_fails() {
+    let mut test = start().await;
+    let (league, _, players) = started_league(&mut test, 2, vec![100]).await;
+
+    assert_error(setup_league_game(&mut test, league, 1, &players[0]).await, ErrorCode::FixtureNotFound);
+}
+
+#[tokio::test]
+async fn setup_league_game_by_a_non_fixture_player_fails() {
+    let mut test = start().await;
+    let (league, _, players) = started_league(&mut test, 4, vec![100]).await;
+    let fixture = test.fetch::<League>(league).await.unwrap().fixtures[0].clone();
+    let outsider = (0..4)
+        .find(|&index| index != fixture.player_one as usize && index != fixture.player_two as usize)
+        .unwrap();
+
+    assert_error(setup_league_game(&mut test, league, 0, &players[outsider]).await, ErrorCode::SignerNotInFixture);
+}
+
+#[tokio::test]
+async fn setup_finished_league_game_fails() {
+    let mut test = start().await;
+    let (league, _, players) = started_league(&mut test, 2, vec![100]).await;
+    let (game, one, two) = play_fixture(&mut test, league, 0, &players).await;
+    test.play_moves(game, &players[one], &players[two], &PLAYER_ONE_WINS).await;
+
+    assert_error(setup_league_game(&mut test, league, 0, &players[one]).await, ErrorCode::NoUninitializedOrWaitingGame);
+}
+
+#[tokio::test]
+async fn reopened_fixture_refunds_the_rent_to_its_first_payer() {
+    let mut test = start().await;
+    let (league, _, players) = started_league(&mut test, 2, vec![100]).await;
+    let fixture = test.fetch::<League>(league).await.unwrap().fixtures[0].clone();
+    let (one, two) = (fixture.player_one as usize, fixture.player_two as usize);
+    let game = setup_league_game(&mut test, league, 0, &players[one]).await.unwrap();
+    test.cancel_game(game, &players[one], players[one].pubkey()).await.unwrap();
+
+    // The rival opens the fixture again and becomes player one
+    setup_league_game(&mut test, league, 0, &players[two]).await.unwrap();
+    setup_league_game(&mut test, league, 0, &players[one]).await.unwrap();
+    test.play_moves(game, &players[two], &players[one], &PLAYER_ONE_WINS).await;
+    record_league_result(&mut test, league, game, 0).await.unwrap();
+    let rent = test.balance(game).await + test.balance(escrow_address(&game)).await;
+    let balance_before = test.balance(players[one].pubkey()).await;
+
+    test.close_game_account(game, &players[two]).await.unwrap();
+
+    assert_eq!(test.balance(players[one].pubkey()).await, balance_before + rent);
+}
+
+#[tokio::test]
+async fn player_one_cannot_close_a_league_game_before_its_result() {
+    let mut test = start().await;
+    let (league, _, players) = started_league(&mut test, 2, vec![100]).await;
+    let (game, one, two) = play_fixture(&mut test, league, 0, &players).await;
+    test.play_moves(game, &players[one], &players[two], &PLAYER_ONE_WINS).await;
+
+    // Closing and setting the fixture up again would erase the loss of player two
+    assert_error(test.close_game_account(game, &players[one]).await, ErrorCode::FixtureNotReported);
+
+    record_league_result(&mut test, league, game, 0).await.unwrap();
+    test.close_game_account(game, &players[one]).await.unwrap();
+    assert_error(setup_league_game(&mut test, league, 0, &players[two]).await, ErrorCode::FixtureAlreadyReported);
+}
+
+#[tokio::test]
+async fn canceling_a_waiting_fixture_keeps_the_matchmaking_game() {
+    let mut test = start().await;
+    let (league, _, players) = started_league(&mut test, 2, vec![100]).await;
+    let waiting = test.create_funded_player().await;
+    let matchmaking_game = test.setup_game(&waiting).await.unwrap();
+    let fixture = test.fetch::<League>(league).await.unwrap().fixtures[0].clone();
+    let one = fixture.player_one as usize;
+    let game = setup_league_game(&mut test, league, 0, &players[one]).await.unwrap();
+
+    test.cancel_game(game, &players[one], players[one].pubkey()).await.unwrap();
+
+    // The next player still joins the waiting player
+    let rival = test.create_funded_player().await;
+    assert_eq!(test.setup_game(&rival).await.unwrap(), matchmaking_game);
+    assert_eq!(test.game(matchmaking_game).await.state, GameState::InProgress);
+}
+
+#[tokio::test]
+async fn closing_a_league_game_waits_for_its_result() {
+    let mut test = start().await;
+    let (league, _, players) = started_league(&mut test, 2, vec![100]).await;
+    let (game, one, two) = play_fixture(&mut test, league, 0, &players).await;
+    test.play_moves(game, &players[one], &players[two], &PLAYER_ONE_WINS).await;
+    test.advance_clock(CLOSE_GRACE_PERIOD).await;
+
+    assert_error(test.close_finished_game(game, None).await, ErrorCode::FixtureNotReported);
+    assert_error(test.close_finished_game(game, Some(league)).await, ErrorCode::FixtureNotReported);
+
+    record_league_result(&mut test, league, game, 0).await.unwrap();
+    test.close_finished_game(game, Some(league)).await.unwrap();
+    assert!(test.fetch::<tic_tac_toe::Game>(game).await.is_none());
+}
+
+#[tokio::test]
+async fn record_unfinished_game_fails() {
+    let mut test = start().await;
+    let (league, _, players) = started_league(&mut test, 2, vec![100]).await;
+    let (game, _, _) = play_fixture(&mut test, league, 0, &players).await;
+    assert_eq!(test.game(game).await.state, GameState::InProgress);
+
+    assert_error(record_league_result(&mut test, league, game, 0).await, ErrorCode::GameNotFinished);
+}
+
+#[tokio::test]
+async fn record_result_with_another_game_fails() {
+    let mut test = start().await;
+    let (league, _, players) = started_league(&mut test, 2, vec![100]).await;
+    play_fixture(&mut test, league, 0, &players).await;
+    let (other_game, player_one, player_two) = test.start_game().await;
+    test.play_moves(other_game, &player_one, &player_two, &PLAYER_ONE_WINS).await;
+
+    assert_error(record_league_result(&mut test, league, other_game, 0).await, ErrorCode::GameNotFound);
+}
+
+#[tokio::test]
+async fn record_result_twice_fails() {
+    let mut test = start().await;
+    let (league, _, players) = started_league(&mut test, 2, vec![100]).await;
+    let (game, one, two) = play_fixture(&mut test, league, 0, &players).await;
+    test.play_moves(game, &players[one], &players[two], &PLAYER_ONE_WINS).await;
+    record_league_result(&mut test, league, game, 0).await.unwrap();
+
+    assert_error(setup_league_game(&mut test, league, 0, &players[one]).await, ErrorCode::FixtureAlreadyReported);
+    assert_error(record_league_result(&mut test, league, game, 0).await, ErrorCode::FixtureAlreadyReported);
+}
+
+#[tokio::test]
+async fn finalize_with_pending_fixtures_fails() {
+    let mut test = start().await;
+    let (league, _, players) = started_league(&mut test, 2, vec![100]).await;
+
+    assert_error(finalize_league(&mut test, league, &[players[0].pubkey()]).await, ErrorCode::LeagueFixturesPending);
+}
+
+#[tokio::test]
+async fn finalize_with_wrong_ranking_fails() {
+    let mut test = start().await;
+    let (league, _, players) = started_league(&mut test, 2, vec![100]).await;
+    let (game, one, two) = play_fixture(&mut test, league, 0, &players).await;
+    test.play_moves(game, &players[one], &players[two], &PLAYER_ONE_WINS).await;
+    record_league_result(&mut test, league, game, 0).await.unwrap();
+
+    assert_error(finalize_league(&mut test, league, &[players[two].pubkey()]).await, ErrorCode::RankingMismatch);
+    assert_error(finalize_league(&mut test, league, &[]).await, ErrorCode::RankingMismatch);
+}
+
+#[tokio::test]
+async fn league_with_a_fixture_never_played_is_refunded_after_the_season() {
+    let mut test = start().await;
+    let (league, _, players) = started_league(&mut test, 3, vec![100]).await;
+    let player_keys: Vec<Pubkey> = players.iter().map(|player| player.pubkey()).collect();
+    // One fixture is recorded, one is finished without its result and the last one is never played
+    let (game, one, two) = play_fixture(&mut test, league, 0, &players).await;
+    test.play_moves(game, &players[one], &players[two], &PLAYER_ONE_WINS).await;
+    record_league_result(&mut test, league, game, 0).await.unwrap();
+    let (unrecorded_game, one, two) = play_fixture(&mut test, league, 1, &players).await;
+    test.play_moves(unrecorded_game, &players[one], &players[two], &PLAYER_ONE_WINS).await;
+
+    assert_error(finalize_league(&mut test, league, &player_keys[..1]).await, ErrorCode::LeagueFixturesPending);
+    assert_error(refund_league(&mut test, league, &player_keys).await, ErrorCode::LeagueSeasonNotOver);
+
+    test.advance_clock(LEAGUE_SEASON_DURATION).await;
+    let reversed: Vec<Pubkey> = player_keys.iter().rev().copied().collect();
+    assert_error(refund_league(&mut test, league, &reversed).await, ErrorCode::RankingMismatch);
+    let balances_before = [test.balance(player_keys[0]).await, test.balance(player_keys[1]).await, test.balance(player_keys[2]).await];
+    refund_league(&mut test, league, &player_keys).await.unwrap();
+
+    for (player, balance_before) in player_keys.iter().zip(balances_before) {
+        assert_eq!(test.balance(*player).await, balance_before + ENTRY_FEE);
+    }
+    let league_account = test.fetch::<League>(league).await.unwrap();
+    assert_eq!((league_account.state, league_account.pool), (LeagueState::Refunded, 0));
+    assert_error(refund_league(&mut test, league, &player_keys).await, ErrorCode::LeagueNotInProgress);
+    assert_error(record_league_result(&mut test, league, unrecorded_game, 1).await, ErrorCode::LeagueNotInProgress);
+    // The unrecorded game can still be closed
+    test.close_game_account(unrecorded_game, &players[one]).await.unwrap();
+}
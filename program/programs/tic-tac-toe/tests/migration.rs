@@ -0,0 +1,274 @@
+mod common;
+
+use anchor_lang::Discriminator;
+use common::*;
+use solana_program_test::BanksClientError;
+use solana_sdk::{
+    account::Account,
+    native_token::LAMPORTS_PER_SOL,
+    pubkey::Pubkey,
+    signature::{Keypair, Signer},
+    system_program,
+};
+use tic_tac_toe::{ErrorCode, Game, GameState, GlobalState, GlobalStateV1, GlobalStateV2, LegacyGame, Pause};
+
+async fn migrate_global_state(test: &mut TestContext, payer: &Keypair) -> Result<(), BanksClientError> {
+    let accounts = tic_tac_toe::accounts::MigrateGlobalState {
+        global_state: test.global_state,
+        payer: payer.pubkey(),
+        system_program: system_program::ID,
+    };
+    test.process(&[instruction(accounts, tic_tac_toe::instruction::MigrateGlobalState {})], &[payer])
+        .await
+}
+
+// Borsh encoding of the fields the first layout held, written by hand so the tests do not depend
+// on the legacy structs they check.
+fn encode_v1_fields(data: &mut Vec<u8>, global_state: &GlobalState) {
+    data.extend_from_slice(global_state.owner.as_ref());
+    data.extend_from_slice(&global_state.game_count.to_le_bytes());
+    for mapping in [&global_state.players_mapping, &global_state.games_mapping] {
+        data.extend_from_slice(&(mapping.len() as u32).to_le_bytes());
+        mapping.iter().for_each(|key| data.extend_from_slice(key.as_ref()));
+    }
+    data.extend_from_slice(&global_state.fee.to_le_bytes());
+    data.extend_from_slice(&global_state.bet.to_le_bytes());
+}
+
+fn set_global_state_data(test: &mut TestContext, data: Vec<u8>, lamports: u64) {
+    let account = Account {
+        lamports,
+        data,
+        owner: tic_tac_toe::ID,
+        executable: false,
+        rent_epoch: 0,
+    };
+    test.context.set_account(&test.global_state, &account.into());
+}
+
+// Rewrites the global state with the version 1 encoding of its current content, as deployed
+// before the version byte: fixed size, no version, no house nor pause switches, and `lamports`
+// in the account.
+async fn downgrade_global_state(test: &mut TestContext, lamports: u64) -> GlobalState {
+    let global_state = test.global_state().await;
+    let mut data = GlobalState::DISCRIMINATOR.to_vec();
+    encode_v1_fields(&mut data, &global_state);
+    data.resize(GlobalStateV1::SPACE, 0);
+    set_global_state_data(test, data, lamports);
+    global_state
+}
+
+// Rewrites the global state with the version 2 encoding of its current content, before the hook
+// allowlist: the version byte, the version 1 fields, the house and the pause switches.
+async fn downgrade_global_state_to_v2(test: &mut TestContext) -> GlobalState {
+    let global_state = test.global_state().await;
+    let mut data = GlobalState::DISCRIMINATOR.to_vec();
+    data.push(2);
+    encode_v1_fields(&mut data, &global_state);
+    match global_state.house {
+        Some(house) => {
+            data.push(1);
+            data.extend_from_slice(house.as_ref());
+        }
+        None => data.push(0),
+    }
+    let paused = global_state.paused;
+    data.extend([paused.new_games, paused.moves, paused.withdrawals].map(u8::from));
+    data.resize(GlobalStateV2::SPACE, 0);
+    let lamports = test.minimum_balance(GlobalStateV2::SPACE).await;
+    set_global_state_data(test, data, lamports);
+    global_state
+}
+
+async fn data_len(test: &mut TestContext) -> usize {
+    let account = test.context.banks_client.get_account(test.global_state).await.unwrap().unwrap();
+    account.data.len()
+}
+
+#[tokio::test]
+async fn new_accounts_have_the_current_version() {
+    let mut test = start().await;
+    let player = test.create_funded_player().await;
+
+    let game = test.setup_game(&player).await.unwrap();
+
+    assert_eq!(test.global_state().await.version, GlobalState::VERSION);
+    assert_eq!(data_len(&mut test).await, GlobalState::MAXIMUM_SIZE);
+    assert_eq!(test.game(game).await.version, Game::VERSION);
+}
+
+#[tokio::test]
+async fn migrates_a_v1_global_state() {
+    let mut test = start().await;
+    let player_one = test.create_funded_player().await;
+    let game = test.setup_game(&player_one).await.unwrap();
+    // Collected fees cover the rent of the bigger account
+    let lamports = test.minimum_balance(GlobalStateV1::SPACE).await + LAMPORTS_PER_SOL;
+    let legacy = downgrade_global_state(&mut test, lamports).await;
+    let payer = test.create_funded_player().await;
+
+    migrate_global_state(&mut test, &payer).await.unwrap();
+
+    let global_state = test.global_state().await;
+    assert_eq!(global_state.version, GlobalState::VERSION);
+    assert_eq!(global_state.owner, legacy.owner);
+    assert_eq!(global_state.game_count, legacy.game_count);
+    assert_eq!(global_state.players_mapping, vec![player_one.pubkey()]);
+    assert_eq!(global_state.games_mapping, vec![game]);
+    assert_eq!((global_state.fee, global_state.bet), (legacy.fee, legacy.bet));
+    assert_eq!(global_state.house, None);
+    assert_eq!(global_state.paused, Pause::default());
+    assert_eq!(data_len(&mut test).await, GlobalState::MAXIMUM_SIZE);
+    assert_eq!(test.balance(test.global_state).await, lamports);
+    assert_eq!(test.balance(payer.pubkey()).await, PLAYER_FUNDS);
+
+    // The waiting game is still matched after the migration
+    let player_two = test.create_funded_player().await;
+    assert_eq!(test.setup_game(&player_two).await.unwrap(), game);
+    assert_eq!(test.game(game).await.state, GameState::InProgress);
+}
+
+#[tokio::test]
+async fn migrates_a_v2_global_state() {
+    let mut test = start().await;
+    let house = test.create_funded_player().await.pubkey();
+    let paused = Pause {
+        withdrawals: true,
+        ..Pause::default()
+    };
+    test.update_global_state(|global_state| {
+        global_state.house = Some(house);
+        global_state.paused = paused;
+    })
+    .await;
+    let player = test.create_funded_player().await;
+    let game = test.setup_game(&player).await.unwrap();
+    let legacy = downgrade_global_state_to_v2(&mut test).await;
+    let payer = test.create_funded_player().await;
+
+    migrate_global_state(&mut test, &payer).await.unwrap();
+
+    let global_state = test.global_state().await;
+    assert_eq!(global_state.version, GlobalState::VERSION);
+    assert_eq!((global_state.owner, global_state.game_count), (legacy.owner, legacy.game_count));
+    assert_eq!(global_state.games_mapping, vec![game]);
+    assert_eq!((global_state.fee, global_state.bet), (legacy.fee, legacy.bet));
+    assert_eq!((global_state.house, global_state.paused), (Some(house), paused));
+    assert!(global_state.hooks.is_empty());
+    assert_eq!(data_len(&mut test).await, GlobalState::MAXIMUM_SIZE);
+    assert_error(migrate_global_state(&mut test, &payer).await, ErrorCode::AccountAlreadyMigrated);
+}
+
+#[tokio::test]
+async fn migration_tops_up_the_rent() {
+    let mut test = start().await;
+    let lamports = test.minimum_balance(GlobalStateV1::SPACE).await;
+    downgrade_global_state(&mut test, lamports).await;
+    let payer = test.create_funded_player().await;
+
+    migrate_global_state(&mut test, &payer).await.unwrap();
+
+    let rent = test.minimum_balance(GlobalState::MAXIMUM_SIZE).await;
+    assert_eq!(test.balance(test.global_state).await, rent);
+    assert_eq!(test.balance(payer.pubkey()).await, PLAYER_FUNDS - (rent - lamports));
+}
+
+#[tokio::test]
+async fn migrating_a_current_global_state_fails() {
+    let mut test = start().await;
+    let payer = test.create_funded_player().await;
+
+    assert_error(migrate_global_state(&mut test, &payer).await, ErrorCode::AccountAlreadyMigrated);
+
+    let lamports = test.minimum_balance(GlobalStateV1::SPACE).await;
+    downgrade_global_state(&mut test, lamports).await;
+    migrate_global_state(&mut test, &payer).await.unwrap();
+    assert_error(migrate_global_state(&mut test, &payer).await, ErrorCode::AccountAlreadyMigrated);
+}
+
+async fn refund_legacy_game(test: &mut TestContext, game: Pubkey, players: [Pubkey; 2]) -> Result<(), BanksClientError> {
+    let accounts = tic_tac_toe::accounts::RefundLegacyGame {
+        global_state: test.global_state,
+        game,
+        player_one: players[0],
+        player_two: players[1],
+    };
+    test.process(&[instruction(accounts, tic_tac_toe::instruction::RefundLegacyGame {})], &[])
+        .await
+}
+
+// Writes an in progress game with the first version layout at `game`, holding both bets and its
+// rent, as the first program version left it.
+async fn set_legacy_game(test: &mut TestContext, game: Pubkey, players: [Pubkey; 2], pot: u64) {
+    let mut data = Game::DISCRIMINATOR.to_vec();
+    data.extend_from_slice(&1u64.to_le_bytes());
+    for player in players {
+        data.push(1);
+        data.extend_from_slice(player.as_ref());
+    }
+    // One move each, then the in progress state
+    data.push(2);
+    data.extend([1, 0, 1, 1]);
+    data.extend([0; 7]);
+    data.push(2);
+    data.extend_from_slice(&pot.to_le_bytes());
+    data.resize(LegacyGame::SPACE, 0);
+    let account = Account {
+        lamports: test.minimum_balance(LegacyGame::SPACE).await + pot,
+        data,
+        owner: tic_tac_toe::ID,
+        executable: false,
+        rent_epoch: 0,
+    };
+    test.context.set_account(&game, &account.into());
+}
+
+#[tokio::test]
+async fn legacy_games_in_flight_are_refunded_after_the_migration() {
+    let mut test = start().await;
+    let players = [test.create_funded_player().await, test.create_funded_player().await];
+    let player_keys = players.each_ref().map(|player| player.pubkey());
+    let game = Pubkey::new_unique();
+    let bet = test.global_state().await.bet;
+    set_legacy_game(&mut test, game, player_keys, 2 * bet).await;
+    let rent = test.minimum_balance(LegacyGame::SPACE).await;
+    test.update_global_state(|global_state| {
+        global_state.players_mapping = player_keys.to_vec();
+        global_state.games_mapping = vec![game; 2];
+    })
+    .await;
+    let lamports = test.minimum_balance(GlobalStateV1::SPACE).await;
+    downgrade_global_state(&mut test, lamports).await;
+    let payer = test.create_funded_player().await;
+    migrate_global_state(&mut test, &payer).await.unwrap();
+
+    // The legacy game cannot be read by the current instructions, its players stay locked
+    assert_error(test.setup_game(&players[0]).await, ErrorCode::GameAlreadyInProgress);
+    assert_error(
+        refund_legacy_game(&mut test, game, [player_keys[1], player_keys[0]]).await,
+        ErrorCode::RivalIsNotPlayer,
+    );
+
+    refund_legacy_game(&mut test, game, player_keys).await.unwrap();
+
+    assert_eq!(test.balance(player_keys[0]).await, PLAYER_FUNDS + bet + rent);
+    assert_eq!(test.balance(player_keys[1]).await, PLAYER_FUNDS + bet);
+    assert!(test.context.banks_client.get_account(game).await.unwrap().is_none());
+    assert!(test.global_state().await.games_mapping.is_empty());
+    // Both players are free to play again
+    for player in &players {
+        test.setup_game(player).await.unwrap();
+    }
+}
+
+#[tokio::test]
+async fn refunding_a_current_game_as_legacy_fails() {
+    let mut test = start().await;
+    let player = test.create_funded_player().await;
+    let game = test.setup_game(&player).await.unwrap();
+
+    assert_error(
+        refund_legacy_game(&mut test, game, [player.pubkey(), player.pubkey()]).await,
+        ErrorCode::NotALegacyGame,
+    );
+}
@@ -0,0 +1,170 @@
+mod common;
+
+use common::*;
+use solana_sdk::{
+    instruction::InstructionError,
+    native_token::LAMPORTS_PER_SOL,
+    pubkey::Pubkey,
+    signature::{Keypair, Signer},
+    transaction::TransactionError,
+};
+use tic_tac_toe::{ErrorCode, GameState, Pause};
+
+// Hands the global state to a test wallet, as the owner wallet cannot sign here.
+async fn owner(test: &mut TestContext) -> Keypair {
+    let owner = test.create_funded_player().await;
+    test.update_global_state(|global_state| global_state.owner = owner.pubkey()).await;
+    owner
+}
+
+#[tokio::test]
+async fn paused_new_games_reject_setup() {
+    let mut test = start().await;
+    let owner = owner(&mut test).await;
+    test.setup_house(&owner, Keypair::new().pubkey(), LAMPORTS_PER_SOL).await;
+    let (game, player_one, player_two) = test.start_game().await;
+    let paused = Pause {
+        new_games: true,
+        ..Pause::default()
+    };
+
+    test.set_paused(&owner, paused).await.unwrap();
+
+    assert_eq!(test.global_state().await.paused, paused);
+    let player = test.create_funded_player().await;
+    assert_error(test.setup_game(&player).await, ErrorCode::NewGamesPaused);
+    assert_error(test.challenge_house(&player).await, ErrorCode::NewGamesPaused);
+    // Games already started can still be played
+    test.play_moves(game, &player_one, &player_two, &PLAYER_ONE_WINS).await;
+
+    test.set_paused(&owner, Pause::default()).await.unwrap();
+    test.setup_game(&player).await.unwrap();
+}
+
+#[tokio::test]
+async fn paused_moves_reject_play_and_cancel() {
+    let mut test = start().await;
+    let owner = owner(&mut test).await;
+    let (game, player_one, player_two) = test.start_game().await;
+    test.set_paused(
+        &owner,
+        Pause {
+            moves: true,
+            ..Pause::default()
+        },
+    )
+    .await
+    .unwrap();
+
+    assert_error(test.play(game, &player_one, player_two.pubkey(), 0, 0).await, ErrorCode::MovesPaused);
+    assert_error(test.cancel_game(game, &player_one, player_two.pubkey()).await, ErrorCode::MovesPaused);
+
+    test.set_paused(&owner, Pause::default()).await.unwrap();
+    test.play(game, &player_one, player_two.pubkey(), 0, 0).await.unwrap();
+}
+
+#[tokio::test]
+async fn emergency_refund_returns_both_bets_without_fee() {
+    let mut test = start().await;
+    let owner = owner(&mut test).await;
+    let (game, player_one, player_two) = test.start_game().await;
+    test.play(game, &player_one, player_two.pubkey(), 1, 1).await.unwrap();
+    test.set_paused(
+        &owner,
+        Pause {
+            new_games: true,
+            moves: true,
+            withdrawals: true,
+        },
+    )
+    .await
+    .unwrap();
+    let bet = test.global_state().await.bet;
+    let treasury_before = test.balance(test.global_state).await;
+    let player_one_before = test.balance(player_one.pubkey()).await;
+    let player_two_before = test.balance(player_two.pubkey()).await;
+
+    // Refunds still work while everything else is paused
+    test.emergency_refund(&owner, game, player_one.pubkey(), player_two.pubkey()).await.unwrap();
+
+    let game_pda = test.game(game).await;
+    assert_eq!(game_pda.state, GameState::Canceled);
+    assert_eq!(game_pda.pot, 0);
+    assert!(game_pda.paid);
+    assert_eq!(test.balance(player_one.pubkey()).await, player_one_before + bet);
+    assert_eq!(test.balance(player_two.pubkey()).await, player_two_before + bet);
+    assert_eq!(test.balance(test.global_state).await, treasury_before);
+    assert!(test.global_state().await.players_mapping.is_empty());
+
+    assert_error(
+        test.emergency_refund(&owner, game, player_one.pubkey(), player_two.pubkey()).await,
+        ErrorCode::GameNotInProgress,
+    );
+}
+
+#[tokio::test]
+async fn emergency_refund_of_a_waiting_game_pays_player_one() {
+    let mut test = start().await;
+    let owner = owner(&mut test).await;
+    let player = test.create_funded_player().await;
+    let game = test.setup_game(&player).await.unwrap();
+    let pot = test.game(game).await.pot;
+    let player_before = test.balance(player.pubkey()).await;
+
+    test.emergency_refund(&owner, game, player.pubkey(), Pubkey::new_unique()).await.unwrap();
+
+    assert_eq!(test.game(game).await.state, GameState::Canceled);
+    assert_eq!(test.balance(player.pubkey()).await, player_before + pot);
+    assert!(test.global_state().await.players_mapping.is_empty());
+    // Matchmaking moves on to a new game
+    let next = test.setup_game(&player).await.unwrap();
+    assert_ne!(next, game);
+    assert_eq!(test.game(next).await.state, GameState::Waiting);
+}
+
+#[tokio::test]
+async fn emergency_refund_of_a_house_game_refills_the_treasury() {
+    let mut test = start().await;
+    let owner = owner(&mut test).await;
+    let house = Keypair::new();
+    test.setup_house(&owner, house.pubkey(), LAMPORTS_PER_SOL).await;
+    let treasury_before = test.balance(test.global_state).await;
+    let player = test.create_funded_player().await;
+    let game = test.challenge_house(&player).await.unwrap();
+
+    test.emergency_refund(&owner, game, player.pubkey(), house.pubkey()).await.unwrap();
+
+    assert_eq!(test.balance(test.global_state).await, treasury_before);
+    assert_eq!(test.balance(house.pubkey()).await, 0);
+}
+
+#[tokio::test]
+async fn emergency_refund_to_other_accounts_fails() {
+    let mut test = start().await;
+    let owner = owner(&mut test).await;
+    let (game, player_one, player_two) = test.start_game().await;
+    let outsider = Pubkey::new_unique();
+
+    assert_error(
+        test.emergency_refund(&owner, game, outsider, player_two.pubkey()).await,
+        ErrorCode::RivalIsNotPlayer,
+    );
+    assert_error(
+        test.emergency_refund(&owner, game, player_one.pubkey(), outsider).await,
+        ErrorCode::RivalIsNotPlayer,
+    );
+}
+
+#[tokio::test]
+async fn pause_and_refund_by_a_non_owner_fail() {
+    let mut test = start().await;
+    let (game, player_one, player_two) = test.start_game().await;
+    let outsider = test.create_funded_player().await;
+    let has_one = TransactionError::InstructionError(0, InstructionError::Custom(anchor_lang::error::ErrorCode::ConstraintHasOne.into()));
+
+    let result = test.set_paused(&outsider, Pause::default()).await;
+    assert_eq!(result.unwrap_err().unwrap(), has_one);
+
+    let result = test.emergency_refund(&outsider, game, player_one.pubkey(), player_two.pubkey()).await;
+    assert_eq!(result.unwrap_err().unwrap(), has_one);
+}
@@ -0,0 +1,58 @@
+// Property-based tests of random games played through the program.
+mod common;
+
+use common::*;
+use proptest::prelude::*;
+use solana_sdk::signature::Signer;
+use tic_tac_toe::{engine::Outcome, ErrorCode, GameState, Sign};
+
+// Every case starts its own bank, so keep the number of games small.
+const GAMES: u32 = 16;
+
+fn cell_order() -> impl Strategy<Value = Vec<u8>> {
+    Just((0..9).collect::<Vec<u8>>()).prop_shuffle()
+}
+
+// Plays the cells in order until the game ends, then tries the next cell.
+async fn play_random_game(order: Vec<u8>) {
+    let mut test = start().await;
+    let (game, player_one, player_two) = test.start_game().await;
+    let players = [&player_one, &player_two];
+
+    for (index, &cell) in order.iter().enumerate() {
+        let (player, rival) = (players[index % 2], players[(index + 1) % 2]);
+        let (row, column) = (cell / 3, cell % 3);
+        let before = test.game(game).await;
+        if before.is_over() {
+            // Settlement removed both players from the mapping
+            assert_error(test.play(game, player, rival.pubkey(), row, column).await, ErrorCode::PlayerHasNotAnActiveGame);
+            assert_eq!(test.game(game).await.board, before.board);
+            return;
+        }
+        // The rival can never play out of turn
+        assert_error(test.play(game, rival, player.pubkey(), row, column).await, ErrorCode::NotPlayersTurn);
+        test.play(game, player, rival.pubkey(), row, column).await.unwrap();
+
+        let game_pda = test.game(game).await;
+        let sign = if index % 2 == 0 { Sign::X } else { Sign::O };
+        assert_eq!(game_pda.board[row as usize][column as usize], Some(sign));
+        assert_eq!(game_pda.moves.len(), index + 1);
+        assert_eq!(game_pda.moves[index].player as usize, index % 2);
+        match reference_outcome(&game_pda.engine_board()) {
+            Outcome::InProgress => assert_eq!(game_pda.state, GameState::InProgress),
+            Outcome::Won(_) => assert_eq!(game_pda.state, GameState::Won { winner: player.pubkey() }),
+            Outcome::Tie => assert_eq!(game_pda.state, GameState::Tie),
+        }
+    }
+    assert!(test.game(game).await.is_over());
+}
+
+proptest! {
+    #![proptest_config(ProptestConfig::with_cases(GAMES))]
+
+    #[test]
+    fn random_games_follow_the_rules(order in cell_order()) {
+        let runtime = tokio::runtime::Builder::new_current_thread().enable_all().build().unwrap();
+        runtime.block_on(play_random_game(order));
+    }
+}
@@ -0,0 +1,180 @@
+mod common;
+
+use common::*;
+use solana_program_test::BanksClientError;
+use solana_sdk::{
+    ed25519_program,
+    instruction::Instruction,
+    pubkey::Pubkey,
+    signature::{Keypair, Signer},
+    system_program, sysvar,
+};
+use tic_tac_toe::{ErrorCode, GameState, RelayNonce, RelayedMove, Tile, MAX_RELAYER_FEE};
+
+fn relay_nonce_address(player: &Pubkey) -> Pubkey {
+    Pubkey::find_program_address(&[b"relay_nonce", player.as_ref()], &tic_tac_toe::ID).0
+}
+
+// Ed25519 program instruction with the key, the signature and the message in its own data.
+fn verify_signature(signer: &Keypair, message: &[u8]) -> Instruction {
+    let signature = signer.sign_message(message);
+    let offsets: [u16; 7] = [48, u16::MAX, 16, u16::MAX, 112, message.len() as u16, u16::MAX];
+    let mut data = vec![1, 0];
+    data.extend(offsets.iter().flat_map(|offset| offset.to_le_bytes()));
+    data.extend_from_slice(signer.pubkey().as_ref());
+    data.extend_from_slice(signature.as_ref());
+    data.extend_from_slice(message);
+    Instruction {
+        program_id: ed25519_program::ID,
+        accounts: vec![],
+        data,
+    }
+}
+
+fn relay_move(relayer: &Keypair, player: Pubkey, rival: Pubkey, relayed: RelayedMove) -> Instruction {
+    let game = relayed.game;
+    let accounts = tic_tac_toe::accounts::RelayMove {
+        global_state: global_state_address(),
+        game,
+        escrow: escrow_address(&game),
+        player,
+        rival,
+        player_balance: None,
+        rival_balance: None,
+        relay_nonce: relay_nonce_address(&player),
+        relayer: relayer.pubkey(),
+        instructions: sysvar::instructions::ID,
+        system_program: system_program::ID,
+    };
+    instruction(accounts, tic_tac_toe::instruction::RelayMove { relayed })
+}
+
+// Move at the current turn of the game, carrying the player's next nonce.
+async fn next_move(test: &mut TestContext, game: Pubkey, player: Pubkey, (row, column): (u8, u8), relayer_fee: u64) -> RelayedMove {
+    let nonce = test.fetch::<RelayNonce>(relay_nonce_address(&player)).await.map_or(0, |relay_nonce| relay_nonce.nonce);
+    RelayedMove {
+        game,
+        turn: test.game(game).await.turn,
+        tile: Tile { row, column },
+        nonce,
+        relayer_fee,
+    }
+}
+
+// Submits a move signed by `signer` for `player`, paid for by the relayer.
+async fn relay(test: &mut TestContext, relayer: &Keypair, signer: &Keypair, player: Pubkey, rival: Pubkey, relayed: RelayedMove) -> Result<(), BanksClientError> {
+    let verify = verify_signature(signer, &relayed.message());
+    test.process(&[verify, relay_move(relayer, player, rival, relayed)], &[relayer]).await
+}
+
+#[tokio::test]
+async fn relayer_plays_signed_moves() {
+    let mut test = start().await;
+    let (game, player_one, player_two) = test.start_game().await;
+    let relayer = test.create_funded_player().await;
+
+    for (index, &tile) in PLAYER_ONE_WINS.iter().enumerate() {
+        if index % 2 == 0 {
+            let relayed = next_move(&mut test, game, player_one.pubkey(), tile, 0).await;
+            relay(&mut test, &relayer, &player_one, player_one.pubkey(), player_two.pubkey(), relayed).await.unwrap();
+        } else {
+            test.play(game, &player_two, player_one.pubkey(), tile.0, tile.1).await.unwrap();
+        }
+    }
+
+    assert_eq!(test.game(game).await.state, GameState::Won { winner: player_one.pubkey() });
+    let relay_nonce = test.fetch::<RelayNonce>(relay_nonce_address(&player_one.pubkey())).await.unwrap();
+    assert_eq!((relay_nonce.version, relay_nonce.player, relay_nonce.nonce), (RelayNonce::VERSION, player_one.pubkey(), 3));
+}
+
+#[tokio::test]
+async fn relayer_is_reimbursed_from_the_pot() {
+    let mut test = start().await;
+    let (game, player_one, player_two) = test.start_game().await;
+    let relayer = test.create_funded_player().await;
+    let pot = test.game(game).await.pot;
+    let relayer_before = test.balance(relayer.pubkey()).await;
+
+    let relayed = next_move(&mut test, game, player_one.pubkey(), (1, 1), MAX_RELAYER_FEE).await;
+    relay(&mut test, &relayer, &player_one, player_one.pubkey(), player_two.pubkey(), relayed).await.unwrap();
+
+    // The relayer also paid the rent of the player's nonce account
+    let rent = test.minimum_balance(RelayNonce::MAXIMUM_SIZE).await;
+    assert_eq!(test.balance(relayer.pubkey()).await, relayer_before + MAX_RELAYER_FEE - rent);
+    assert_eq!(test.game(game).await.pot, pot - MAX_RELAYER_FEE);
+
+    let relayed = next_move(&mut test, game, player_two.pubkey(), (0, 0), MAX_RELAYER_FEE + 1).await;
+    let result = relay(&mut test, &relayer, &player_two, player_two.pubkey(), player_one.pubkey(), relayed).await;
+    assert_error(result, ErrorCode::RelayerFeeTooHigh);
+}
+
+#[tokio::test]
+async fn signed_move_cannot_be_replayed() {
+    let mut test = start().await;
+    let (game, player_one, player_two) = test.start_game().await;
+    let relayer = test.create_funded_player().await;
+    let relayed = next_move(&mut test, game, player_one.pubkey(), (1, 1), 0).await;
+    relay(&mut test, &relayer, &player_one, player_one.pubkey(), player_two.pubkey(), relayed.clone()).await.unwrap();
+    test.play(game, &player_two, player_one.pubkey(), 0, 0).await.unwrap();
+
+    // The same message again was signed for a past turn
+    let result = relay(&mut test, &relayer, &player_one, player_one.pubkey(), player_two.pubkey(), relayed.clone()).await;
+    assert_error(result, ErrorCode::RelayedTurnMismatch);
+
+    // At the current turn, the nonce is already used
+    let replayed = RelayedMove { turn: 2, tile: Tile { row: 2, column: 2 }, ..relayed };
+    let result = relay(&mut test, &relayer, &player_one, player_one.pubkey(), player_two.pubkey(), replayed).await;
+    assert_error(result, ErrorCode::InvalidNonce);
+}
+
+#[tokio::test]
+async fn relayed_move_needs_the_player_signature() {
+    let mut test = start().await;
+    let (game, player_one, player_two) = test.start_game().await;
+    let (other_game, _, _) = test.start_game().await;
+    let relayer = test.create_funded_player().await;
+    let relayed = next_move(&mut test, game, player_one.pubkey(), (1, 1), 0).await;
+
+    // A valid signature by another key
+    let result = relay(&mut test, &relayer, &player_two, player_one.pubkey(), player_two.pubkey(), relayed.clone()).await;
+    assert_error(result, ErrorCode::InvalidRelayedMove);
+
+    // No Ed25519 instruction before the move
+    let result = test.process(&[relay_move(&relayer, player_one.pubkey(), player_two.pubkey(), relayed.clone())], &[&relayer]).await;
+    assert_error(result, ErrorCode::InvalidRelayedMove);
+
+    // A signed move for another game
+    let mut elsewhere = relayed.clone();
+    elsewhere.game = other_game;
+    let verify = verify_signature(&player_one, &elsewhere.message());
+    let result = test.process(&[verify, relay_move(&relayer, player_one.pubkey(), player_two.pubkey(), relayed)], &[&relayer]).await;
+    assert_error(result, ErrorCode::InvalidRelayedMove);
+}
+
+#[tokio::test]
+async fn relayer_fees_come_out_of_the_relayed_player_share() {
+    let mut test = start().await;
+    let (game, player_one, player_two) = test.start_game().await;
+    let relayer = test.create_funded_player().await;
+    let pot = test.game(game).await.pot;
+    let balances_before = [test.balance(player_one.pubkey()).await, test.balance(player_two.pubkey()).await];
+
+    // Player one relays every move of a tie
+    for (index, &tile) in TIE.iter().enumerate() {
+        if index % 2 == 0 {
+            let relayed = next_move(&mut test, game, player_one.pubkey(), tile, MAX_RELAYER_FEE).await;
+            relay(&mut test, &relayer, &player_one, player_one.pubkey(), player_two.pubkey(), relayed).await.unwrap();
+        } else {
+            test.play(game, &player_two, player_one.pubkey(), tile.0, tile.1).await.unwrap();
+        }
+    }
+
+    let relayer_fees = 5 * MAX_RELAYER_FEE;
+    assert_eq!(test.game(game).await.relayer_fees, [relayer_fees, 0]);
+    // Player two gets half the pot as if no move was relayed, player one pays the relayer from
+    // their own half
+    let fee = (pot - relayer_fees) * test.global_state().await.fee / 100;
+    let share = (pot - relayer_fees - fee + relayer_fees) / 2;
+    assert_eq!(test.balance(player_two.pubkey()).await, balances_before[1] + share);
+    assert_eq!(test.balance(player_one.pubkey()).await, balances_before[0] + share - relayer_fees);
+}
@@ -0,0 +1,230 @@
+mod common;
+
+use common::*;
+use solana_program_test::BanksClientError;
+use solana_sdk::{
+    clock::Clock,
+    pubkey::Pubkey,
+    signature::{Keypair, Signer},
+    system_program,
+};
+use tic_tac_toe::{ErrorCode, GameState, Session, Tile, MAX_SESSION_DURATION};
+
+const HOUR: i64 = 60 * 60;
+
+fn session_address(authority: &Pubkey, key: &Pubkey) -> Pubkey {
+    Pubkey::find_program_address(&[b"session", authority.as_ref(), key.as_ref()], &tic_tac_toe::ID).0
+}
+
+async fn now(test: &mut TestContext) -> i64 {
+    test.context.banks_client.get_sysvar::<Clock>().await.unwrap().unix_timestamp
+}
+
+// Authorizes a new session key for the player, valid for an hour.
+async fn create_session(test: &mut TestContext, player: &Keypair, game: Option<Pubkey>, spending_cap: u64) -> Keypair {
+    let key = Keypair::new();
+    let expires_at = now(test).await + HOUR;
+    create_session_until(test, player, &key, game, expires_at, spending_cap).await.unwrap();
+    key
+}
+
+async fn create_session_until(test: &mut TestContext, player: &Keypair, key: &Keypair, game: Option<Pubkey>, expires_at: i64, spending_cap: u64) -> Result<(), BanksClientError> {
+    let accounts = tic_tac_toe::accounts::CreateSession {
+        session: session_address(&player.pubkey(), &key.pubkey()),
+        authority: player.pubkey(),
+        system_program: system_program::ID,
+    };
+    let data = tic_tac_toe::instruction::CreateSession {
+        key: key.pubkey(),
+        game,
+        expires_at,
+        spending_cap,
+    };
+    test.process(&[instruction(accounts, data)], &[player]).await
+}
+
+// Plays for `player` signed only by the session key.
+async fn play_with_session(test: &mut TestContext, game: Pubkey, key: &Keypair, player: Pubkey, rival: Pubkey, (row, column): (u8, u8)) -> Result<(), BanksClientError> {
+    let accounts = tic_tac_toe::accounts::Play {
+        global_state: test.global_state,
+        game,
+        escrow: escrow_address(&game),
+        player,
+        rival,
+        player_balance: None,
+        rival_balance: None,
+        session: Some(session_address(&player, &key.pubkey())),
+        session_key: Some(key.pubkey()),
+    };
+    test.process(&[instruction(accounts, tic_tac_toe::instruction::Play { tile: Tile { row, column } })], &[key])
+        .await
+}
+
+async fn cancel_with_session(test: &mut TestContext, game: Pubkey, key: &Keypair, player: Pubkey, rival: Pubkey) -> Result<(), BanksClientError> {
+    let accounts = tic_tac_toe::accounts::CancelGame {
+        global_state: test.global_state,
+        game,
+        escrow: escrow_address(&game),
+        signer: player,
+        rival,
+        signer_balance: None,
+        rival_balance: None,
+        session: Some(session_address(&player, &key.pubkey())),
+        session_key: Some(key.pubkey()),
+    };
+    test.process(&[instruction(accounts, tic_tac_toe::instruction::CancelGame {})], &[key])
+        .await
+}
+
+#[tokio::test]
+async fn session_key_plays_for_the_player() {
+    let mut test = start().await;
+    let bet = test.global_state().await.bet;
+    let (game, player_one, player_two) = test.start_game().await;
+    let key = create_session(&mut test, &player_one, Some(game), bet).await;
+
+    let session = test.fetch::<Session>(session_address(&player_one.pubkey(), &key.pubkey())).await.unwrap();
+    assert_eq!((session.version, session.authority, session.key), (Session::VERSION, player_one.pubkey(), key.pubkey()));
+    for (index, &tile) in PLAYER_ONE_WINS.iter().enumerate() {
+        if index % 2 == 0 {
+            play_with_session(&mut test, game, &key, player_one.pubkey(), player_two.pubkey(), tile).await.unwrap();
+        } else {
+            test.play(game, &player_two, player_one.pubkey(), tile.0, tile.1).await.unwrap();
+        }
+    }
+
+    // The win is paid to the player, never to the key
+    assert_eq!(test.game(game).await.state, GameState::Won { winner: player_one.pubkey() });
+    assert_eq!(test.balance(key.pubkey()).await, 0);
+    // The bet is charged once for the whole game
+    let session = test.fetch::<Session>(session_address(&player_one.pubkey(), &key.pubkey())).await.unwrap();
+    assert_eq!((session.spent, session.charged_game), (bet, Some(game)));
+}
+
+#[tokio::test]
+async fn playing_counts_the_bet_against_the_spending_cap() {
+    let mut test = start().await;
+    let bet = test.global_state().await.bet;
+    let (game, player_one, player_two) = test.start_game().await;
+    let small = create_session(&mut test, &player_one, None, bet - 1).await;
+    let key = create_session(&mut test, &player_one, None, bet).await;
+
+    // A leaked key with a small cap cannot throw the game
+    let result = play_with_session(&mut test, game, &small, player_one.pubkey(), player_two.pubkey(), (0, 0)).await;
+    assert_error(result, ErrorCode::SessionCapExceeded);
+    play_with_session(&mut test, game, &key, player_one.pubkey(), player_two.pubkey(), (0, 0)).await.unwrap();
+    test.play(game, &player_two, player_one.pubkey(), 1, 0).await.unwrap();
+    // Resigning the game it already plays charges nothing more
+    cancel_with_session(&mut test, game, &key, player_one.pubkey(), player_two.pubkey()).await.unwrap();
+
+    // The next game is charged again, past the cap
+    let next = test.setup_game(&player_one).await.unwrap();
+    let player_three = test.create_funded_player().await;
+    test.setup_game(&player_three).await.unwrap();
+    let result = play_with_session(&mut test, next, &key, player_one.pubkey(), player_three.pubkey(), (0, 0)).await;
+    assert_error(result, ErrorCode::SessionCapExceeded);
+}
+
+#[tokio::test]
+async fn session_key_is_scoped_to_its_player_and_game() {
+    let mut test = start().await;
+    let (game, player_one, player_two) = test.start_game().await;
+    let (other_game, _, _) = test.start_game().await;
+    let key = create_session(&mut test, &player_one, Some(other_game), 0).await;
+
+    let result = play_with_session(&mut test, game, &key, player_one.pubkey(), player_two.pubkey(), (0, 0)).await;
+    assert_error(result, ErrorCode::InvalidSession);
+
+    // A session of player one cannot sign for player two
+    let key = create_session(&mut test, &player_one, None, 0).await;
+    test.play(game, &player_one, player_two.pubkey(), 0, 0).await.unwrap();
+    let play = |session: Option<Pubkey>, session_key: Option<Pubkey>| {
+        let accounts = tic_tac_toe::accounts::Play {
+            global_state: global_state_address(),
+            game,
+            escrow: escrow_address(&game),
+            player: player_two.pubkey(),
+            rival: player_one.pubkey(),
+            player_balance: None,
+            rival_balance: None,
+            session,
+            session_key,
+        };
+        instruction(accounts, tic_tac_toe::instruction::Play { tile: Tile { row: 1, column: 1 } })
+    };
+    let session = session_address(&player_one.pubkey(), &key.pubkey());
+    let result = test.process(&[play(Some(session), Some(key.pubkey()))], &[&key]).await;
+    assert_error(result, ErrorCode::InvalidSession);
+
+    // Without a session, the player must sign
+    let play = play(None, None);
+    assert_error(test.process(&[play], &[]).await, ErrorCode::InvalidSession);
+}
+
+#[tokio::test]
+async fn expired_session_key_is_rejected() {
+    let mut test = start().await;
+    let (game, player_one, player_two) = test.start_game().await;
+    let key = create_session(&mut test, &player_one, None, 0).await;
+
+    test.advance_clock(HOUR).await;
+
+    let result = play_with_session(&mut test, game, &key, player_one.pubkey(), player_two.pubkey(), (0, 0)).await;
+    assert_error(result, ErrorCode::SessionExpired);
+}
+
+#[tokio::test]
+async fn session_expiry_is_bounded() {
+    let mut test = start().await;
+    let player = test.create_funded_player().await;
+    let now = now(&mut test).await;
+
+    for expires_at in [now, now + MAX_SESSION_DURATION + 1] {
+        let result = create_session_until(&mut test, &player, &Keypair::new(), None, expires_at, 0).await;
+        assert_error(result, ErrorCode::InvalidSessionExpiry);
+    }
+    create_session_until(&mut test, &player, &Keypair::new(), None, now + MAX_SESSION_DURATION, 0).await.unwrap();
+}
+
+#[tokio::test]
+async fn resigning_counts_against_the_spending_cap() {
+    let mut test = start().await;
+    let bet = test.global_state().await.bet;
+    let (game, player_one, player_two) = test.start_game().await;
+    let small = create_session(&mut test, &player_two, Some(game), bet - 1).await;
+    let key = create_session(&mut test, &player_two, Some(game), bet).await;
+
+    let result = cancel_with_session(&mut test, game, &small, player_two.pubkey(), player_one.pubkey()).await;
+    assert_error(result, ErrorCode::SessionCapExceeded);
+    cancel_with_session(&mut test, game, &key, player_two.pubkey(), player_one.pubkey()).await.unwrap();
+
+    assert_eq!(test.game(game).await.state, GameState::Won { winner: player_one.pubkey() });
+    let session = test.fetch::<Session>(session_address(&player_two.pubkey(), &key.pubkey())).await.unwrap();
+    assert_eq!(session.spent, bet);
+}
+
+#[tokio::test]
+async fn revoked_session_key_cannot_play() {
+    let mut test = start().await;
+    let (game, player_one, player_two) = test.start_game().await;
+    let key = create_session(&mut test, &player_one, Some(game), 0).await;
+    let session = session_address(&player_one.pubkey(), &key.pubkey());
+    let rent = test.balance(session).await;
+    let balance_before = test.balance(player_one.pubkey()).await;
+
+    // Only the player or the key can revoke it
+    let revoke = |signer: Pubkey| {
+        let accounts = tic_tac_toe::accounts::RevokeSession {
+            session,
+            authority: player_one.pubkey(),
+            signer,
+        };
+        instruction(accounts, tic_tac_toe::instruction::RevokeSession {})
+    };
+    assert_error(test.process(&[revoke(player_two.pubkey())], &[&player_two]).await, ErrorCode::InvalidSession);
+    test.process(&[revoke(key.pubkey())], &[&key]).await.unwrap();
+
+    assert_eq!(test.balance(player_one.pubkey()).await, balance_before + rent);
+    let result = play_with_session(&mut test, game, &key, player_one.pubkey(), player_two.pubkey(), (0, 0)).await;
+    assert!(result.is_err());
+}
@@ -0,0 +1,111 @@
+// Lamport conservation over every way a game is settled.
+mod common;
+
+use common::*;
+use solana_sdk::{pubkey::Pubkey, signature::Signer};
+use tic_tac_toe::{Escrow, Game, GameState};
+
+// Balances of the accounts a settlement moves lamports between.
+struct Snapshot {
+    accounts: Vec<Pubkey>,
+    balances: Vec<u64>,
+    pot: u64,
+    fee: u64,
+}
+
+async fn snapshot(test: &mut TestContext, game: Pubkey, players: [Pubkey; 2]) -> Snapshot {
+    let accounts = vec![game, test.global_state, players[0], players[1], escrow_address(&game)];
+    let mut balances = Vec::new();
+    for &account in &accounts {
+        balances.push(test.balance(account).await);
+    }
+    let pot = test.game(game).await.pot;
+    let fee = test.global_state().await.fee;
+    Snapshot { accounts, balances, pot, fee }
+}
+
+// Asserts the settlement only moved lamports between the snapshot accounts, and returns the
+// amount paid to each player.
+async fn assert_settled(test: &mut TestContext, before: Snapshot) -> [u64; 2] {
+    let mut after = Vec::new();
+    for &account in &before.accounts {
+        after.push(test.balance(account).await);
+    }
+    let game = before.accounts[0];
+
+    // Total lamports are conserved
+    assert_eq!(after.iter().sum::<u64>(), before.balances.iter().sum::<u64>());
+    // The fee collected is the fee percentage of the pot
+    assert_eq!(after[1] - before.balances[1], before.pot * before.fee / 100);
+    // The game and its escrow only keep their rent-exempt reserves
+    assert_eq!(after[0], test.minimum_balance(Game::MAXIMUM_SIZE).await);
+    assert_eq!(after[4], test.minimum_balance(Escrow::MAXIMUM_SIZE).await);
+
+    let game_pda = test.game(game).await;
+    assert!(game_pda.paid);
+    assert_eq!(game_pda.pot, 0);
+    assert!(!test.global_state().await.games_mapping.contains(&game));
+
+    [after[2] - before.balances[2], after[3] - before.balances[3]]
+}
+
+#[tokio::test]
+async fn win_conserves_lamports() {
+    let mut test = start().await;
+    let (game, player_one, player_two) = test.start_game().await;
+    let (last, moves) = PLAYER_ONE_WINS.split_last().unwrap();
+    test.play_moves(game, &player_one, &player_two, moves).await;
+
+    let before = snapshot(&mut test, game, [player_one.pubkey(), player_two.pubkey()]).await;
+    let payout = before.pot - before.pot * before.fee / 100;
+    test.play(game, &player_one, player_two.pubkey(), last.0, last.1).await.unwrap();
+
+    assert_eq!(assert_settled(&mut test, before).await, [payout, 0]);
+    assert_eq!(test.game(game).await.state, GameState::Won { winner: player_one.pubkey() });
+}
+
+#[tokio::test]
+async fn tie_conserves_lamports() {
+    let mut test = start().await;
+    let (game, player_one, player_two) = test.start_game().await;
+    let (last, moves) = TIE.split_last().unwrap();
+    test.play_moves(game, &player_one, &player_two, moves).await;
+
+    let before = snapshot(&mut test, game, [player_one.pubkey(), player_two.pubkey()]).await;
+    let payout = before.pot - before.pot * before.fee / 100;
+    test.play(game, &player_one, player_two.pubkey(), last.0, last.1).await.unwrap();
+
+    assert_eq!(assert_settled(&mut test, before).await, [payout - payout / 2, payout / 2]);
+    assert_eq!(test.game(game).await.state, GameState::Tie);
+}
+
+#[tokio::test]
+async fn waiting_cancel_conserves_lamports() {
+    let mut test = start().await;
+    let player = test.create_funded_player().await;
+    let rival = test.create_funded_player().await;
+    let game = test.setup_game(&player).await.unwrap();
+
+    let before = snapshot(&mut test, game, [player.pubkey(), rival.pubkey()]).await;
+    let payout = before.pot - before.pot * before.fee / 100;
+    test.cancel_game(game, &player, rival.pubkey()).await.unwrap();
+
+    assert_eq!(assert_settled(&mut test, before).await, [payout, 0]);
+    assert_eq!(test.game(game).await.state, GameState::Canceled);
+    // The player can open a new game right away
+    test.setup_game(&player).await.unwrap();
+}
+
+#[tokio::test]
+async fn resignation_conserves_lamports() {
+    let mut test = start().await;
+    let (game, player_one, player_two) = test.start_game().await;
+    test.play(game, &player_one, player_two.pubkey(), 1, 1).await.unwrap();
+
+    let before = snapshot(&mut test, game, [player_one.pubkey(), player_two.pubkey()]).await;
+    let payout = before.pot - before.pot * before.fee / 100;
+    test.cancel_game(game, &player_two, player_one.pubkey()).await.unwrap();
+
+    assert_eq!(assert_settled(&mut test, before).await, [payout, 0]);
+    assert_eq!(test.game(game).await.state, GameState::Won { winner: player_one.pubkey() });
+}
@@ -0,0 +1,307 @@
+mod common;
+
+use common::*;
+use solana_program_test::BanksClientError;
+use solana_sdk::{
+    instruction::AccountMeta,
+    native_token::LAMPORTS_PER_SOL,
+    pubkey::Pubkey,
+    signature::{Keypair, Signer},
+    system_program,
+};
+use tic_tac_toe::{ErrorCode, Pause, SideBetOutcome, SideBetPool, CLOSE_GRACE_PERIOD, SIDE_BET_CUTOFF_TURN};
+
+const BET: u64 = LAMPORTS_PER_SOL;
+
+fn side_bet_address(side_pool: Pubkey, bettor: Pubkey) -> Pubkey {
+    Pubkey::find_program_address(&[b"side_bet", side_pool.as_ref(), bettor.as_ref()], &tic_tac_toe::ID).0
+}
+
+async fn open_side_pool(test: &mut TestContext, game: Pubkey) -> Result<Pubkey, BanksClientError> {
+    let side_pool = side_pool_address(&game);
+    let payer = test.context.payer.pubkey();
+    let accounts = tic_tac_toe::accounts::OpenSidePool {
+        side_pool,
+        game,
+        payer,
+        system_program: system_program::ID,
+    };
+    test.process(&[instruction(accounts, tic_tac_toe::instruction::OpenSidePool {})], &[])
+        .await?;
+    Ok(side_pool)
+}
+
+async fn place_side_bet(test: &mut TestContext, game: Pubkey, bettor: &Keypair, outcome: SideBetOutcome, amount: u64) -> Result<(), BanksClientError> {
+    let side_pool = side_pool_address(&game);
+    let accounts = tic_tac_toe::accounts::PlaceSideBet {
+        side_pool,
+        game,
+        side_bet: side_bet_address(side_pool, bettor.pubkey()),
+        bettor: bettor.pubkey(),
+        system_program: system_program::ID,
+    };
+    let data = tic_tac_toe::instruction::PlaceSideBet { outcome, amount };
+    test.process(&[instruction(accounts, data)], &[bettor]).await
+}
+
+async fn claim_side_bet(test: &mut TestContext, game: Pubkey, bettor: &Keypair) -> Result<(), BanksClientError> {
+    let side_pool = side_pool_address(&game);
+    let accounts = tic_tac_toe::accounts::ClaimSideBet {
+        global_state: test.global_state,
+        side_pool,
+        game,
+        side_bet: side_bet_address(side_pool, bettor.pubkey()),
+        bettor: bettor.pubkey(),
+    };
+    test.process(&[instruction(accounts, tic_tac_toe::instruction::ClaimSideBet {})], &[bettor])
+        .await
+}
+
+// Closes the side pool of `game`, paying out the unclaimed bets of `bettors`.
+async fn close_side_pool(test: &mut TestContext, game: Pubkey, bettors: &[Pubkey]) -> Result<(), BanksClientError> {
+    let side_pool = side_pool_address(&game);
+    let accounts = tic_tac_toe::accounts::CloseSidePool {
+        global_state: test.global_state,
+        side_pool,
+        game,
+        payer: test.context.payer.pubkey(),
+    };
+    let mut close = instruction(accounts, tic_tac_toe::instruction::CloseSidePool {});
+    for bettor in bettors {
+        close.accounts.extend([AccountMeta::new(side_bet_address(side_pool, *bettor), false), AccountMeta::new(*bettor, false)]);
+    }
+    test.process(&[close], &[]).await
+}
+
+#[tokio::test]
+async fn winning_bets_share_the_pool() {
+    let mut test = start().await;
+    let (game, player_one, player_two) = test.start_game().await;
+    let side_pool = open_side_pool(&mut test, game).await.unwrap();
+    let backers = [test.create_funded_player().await, test.create_funded_player().await];
+    let doubter = test.create_funded_player().await;
+    place_side_bet(&mut test, game, &backers[0], SideBetOutcome::PlayerOne, BET).await.unwrap();
+    place_side_bet(&mut test, game, &backers[1], SideBetOutcome::PlayerOne, BET * 3).await.unwrap();
+    place_side_bet(&mut test, game, &doubter, SideBetOutcome::PlayerTwo, BET * 4).await.unwrap();
+
+    test.play_moves(game, &player_one, &player_two, &PLAYER_ONE_WINS).await;
+
+    let fee = test.global_state().await.fee;
+    let fees_before = test.balance(test.global_state).await;
+    let balances_before = [test.balance(backers[0].pubkey()).await, test.balance(backers[1].pubkey()).await];
+    let side_bet_rent = test.balance(side_bet_address(side_pool, backers[0].pubkey())).await;
+    for backer in &backers {
+        claim_side_bet(&mut test, game, backer).await.unwrap();
+    }
+    claim_side_bet(&mut test, game, &doubter).await.unwrap();
+
+    let side_pool_account = test.fetch::<SideBetPool>(side_pool).await.unwrap();
+    let total = BET * 8;
+    let prize = total - total * fee / 100;
+    assert_eq!(side_pool_account.outcome, Some(SideBetOutcome::PlayerOne));
+    assert_eq!(side_pool_account.fee, total * fee / 100);
+    assert_eq!(test.balance(test.global_state).await, fees_before + total * fee / 100);
+    assert_eq!(test.balance(backers[0].pubkey()).await, balances_before[0] + prize / 4 + side_bet_rent);
+    assert_eq!(test.balance(backers[1].pubkey()).await, balances_before[1] + prize * 3 / 4 + side_bet_rent);
+}
+
+#[tokio::test]
+async fn claims_wait_while_withdrawals_are_paused() {
+    let mut test = start().await;
+    let owner = test.create_funded_player().await;
+    test.update_global_state(|global_state| global_state.owner = owner.pubkey()).await;
+    let (game, player_one, player_two) = test.start_game().await;
+    open_side_pool(&mut test, game).await.unwrap();
+    let bettor = test.create_funded_player().await;
+    place_side_bet(&mut test, game, &bettor, SideBetOutcome::PlayerOne, BET).await.unwrap();
+    test.play_moves(game, &player_one, &player_two, &PLAYER_ONE_WINS).await;
+    let withdrawals = Pause {
+        withdrawals: true,
+        ..Pause::default()
+    };
+
+    test.set_paused(&owner, withdrawals).await.unwrap();
+    assert_error(claim_side_bet(&mut test, game, &bettor).await, ErrorCode::WithdrawalsPaused);
+
+    test.set_paused(&owner, Pause::default()).await.unwrap();
+    claim_side_bet(&mut test, game, &bettor).await.unwrap();
+}
+
+#[tokio::test]
+async fn closing_the_game_settles_the_pool_first() {
+    let mut test = start().await;
+    let (game, player_one, player_two) = test.start_game().await;
+    let side_pool = open_side_pool(&mut test, game).await.unwrap();
+    let backer = test.create_funded_player().await;
+    let doubter = test.create_funded_player().await;
+    place_side_bet(&mut test, game, &backer, SideBetOutcome::PlayerOne, BET).await.unwrap();
+    place_side_bet(&mut test, game, &doubter, SideBetOutcome::PlayerTwo, BET).await.unwrap();
+    test.play_moves(game, &player_one, &player_two, &PLAYER_ONE_WINS).await;
+    test.advance_clock(CLOSE_GRACE_PERIOD).await;
+
+    test.close_finished_game(game, None).await.unwrap();
+
+    let pool = test.fetch::<SideBetPool>(side_pool).await.unwrap();
+    assert!(pool.settled);
+    assert_eq!(pool.outcome, Some(SideBetOutcome::PlayerOne));
+    // The backer is still paid once the game account is gone
+    let balance_before = test.balance(backer.pubkey()).await;
+    claim_side_bet(&mut test, game, &backer).await.unwrap();
+    assert!(test.balance(backer.pubkey()).await > balance_before + BET);
+}
+
+#[tokio::test]
+async fn player_one_closing_the_game_settles_the_pool_first() {
+    let mut test = start().await;
+    let (game, player_one, player_two) = test.start_game().await;
+    let side_pool = open_side_pool(&mut test, game).await.unwrap();
+    let backer = test.create_funded_player().await;
+    let doubter = test.create_funded_player().await;
+    place_side_bet(&mut test, game, &backer, SideBetOutcome::PlayerOne, BET).await.unwrap();
+    place_side_bet(&mut test, game, &doubter, SideBetOutcome::PlayerTwo, BET).await.unwrap();
+    test.play_moves(game, &player_one, &player_two, &PLAYER_ONE_WINS).await;
+
+    // Player one closes right away, without waiting for the grace period
+    test.close_game_account(game, &player_one).await.unwrap();
+
+    assert_eq!(test.fetch::<SideBetPool>(side_pool).await.unwrap().outcome, Some(SideBetOutcome::PlayerOne));
+    let balance_before = test.balance(backer.pubkey()).await;
+    claim_side_bet(&mut test, game, &backer).await.unwrap();
+    assert!(test.balance(backer.pubkey()).await > balance_before + BET);
+}
+
+#[tokio::test]
+async fn bets_are_refunded_without_a_winning_backer() {
+    let mut test = start().await;
+    let (game, player_one, player_two) = test.start_game().await;
+    open_side_pool(&mut test, game).await.unwrap();
+    let bettor = test.create_funded_player().await;
+    place_side_bet(&mut test, game, &bettor, SideBetOutcome::PlayerTwo, BET).await.unwrap();
+    let fees_before = test.balance(test.global_state).await;
+
+    test.play_moves(game, &player_one, &player_two, &PLAYER_ONE_WINS).await;
+    let balance_before = test.balance(bettor.pubkey()).await;
+    claim_side_bet(&mut test, game, &bettor).await.unwrap();
+
+    assert!(test.balance(bettor.pubkey()).await > balance_before + BET);
+    // Only the game fee reached the global state
+    let global_state = test.global_state().await;
+    assert_eq!(test.balance(test.global_state).await, fees_before + global_state.bet * 2 * global_state.fee / 100);
+}
+
+#[tokio::test]
+async fn open_side_pool_on_a_waiting_game_fails() {
+    let mut test = start().await;
+    let player = test.create_funded_player().await;
+    let game = test.setup_game(&player).await.unwrap();
+
+    assert_error(open_side_pool(&mut test, game).await, ErrorCode::GameNotInProgress);
+}
+
+#[tokio::test]
+async fn open_side_pool_after_the_cutoff_fails() {
+    let mut test = start().await;
+    let (game, player_one, player_two) = test.start_game().await;
+    test.play_moves(game, &player_one, &player_two, &PLAYER_ONE_WINS[..SIDE_BET_CUTOFF_TURN as usize]).await;
+
+    assert_error(open_side_pool(&mut test, game).await, ErrorCode::InvalidCutoffTurn);
+}
+
+#[tokio::test]
+async fn side_bet_after_cutoff_fails() {
+    let mut test = start().await;
+    let (game, player_one, player_two) = test.start_game().await;
+    let side_pool = open_side_pool(&mut test, game).await.unwrap();
+    assert_eq!(test.fetch::<SideBetPool>(side_pool).await.unwrap().cutoff_turn, SIDE_BET_CUTOFF_TURN);
+    test.play_moves(game, &player_one, &player_two, &PLAYER_ONE_WINS[..SIDE_BET_CUTOFF_TURN as usize]).await;
+    let bettor = test.create_funded_player().await;
+
+    assert_error(
+        place_side_bet(&mut test, game, &bettor, SideBetOutcome::Tie, BET).await,
+        ErrorCode::SideBetsClosed,
+    );
+}
+
+#[tokio::test]
+async fn side_bet_by_a_player_fails() {
+    let mut test = start().await;
+    let (game, player_one, _) = test.start_game().await;
+    open_side_pool(&mut test, game).await.unwrap();
+
+    assert_error(
+        place_side_bet(&mut test, game, &player_one, SideBetOutcome::PlayerOne, BET).await,
+        ErrorCode::SignerIsPlayer,
+    );
+}
+
+#[tokio::test]
+async fn empty_side_bet_fails() {
+    let mut test = start().await;
+    let (game, _, _) = test.start_game().await;
+    open_side_pool(&mut test, game).await.unwrap();
+    let bettor = test.create_funded_player().await;
+
+    assert_error(
+        place_side_bet(&mut test, game, &bettor, SideBetOutcome::Tie, 0).await,
+        ErrorCode::InvalidSideBetAmount,
+    );
+}
+
+#[tokio::test]
+async fn claim_before_the_game_ends_fails() {
+    let mut test = start().await;
+    let (game, _, _) = test.start_game().await;
+    open_side_pool(&mut test, game).await.unwrap();
+    let bettor = test.create_funded_player().await;
+    place_side_bet(&mut test, game, &bettor, SideBetOutcome::Tie, BET).await.unwrap();
+
+    assert_error(claim_side_bet(&mut test, game, &bettor).await, ErrorCode::GameNotFinished);
+}
+
+#[tokio::test]
+async fn closing_the_pool_pays_the_unclaimed_bets() {
+    let mut test = start().await;
+    let (game, player_one, player_two) = test.start_game().await;
+    let side_pool = open_side_pool(&mut test, game).await.unwrap();
+    let backers = [test.create_funded_player().await, test.create_funded_player().await];
+    let doubter = test.create_funded_player().await;
+    place_side_bet(&mut test, game, &backers[0], SideBetOutcome::PlayerOne, BET).await.unwrap();
+    place_side_bet(&mut test, game, &backers[1], SideBetOutcome::PlayerOne, BET).await.unwrap();
+    place_side_bet(&mut test, game, &doubter, SideBetOutcome::PlayerTwo, BET).await.unwrap();
+    let side_bet_rent = test.balance(side_bet_address(side_pool, doubter.pubkey())).await;
+    assert_error(close_side_pool(&mut test, game, &[]).await, ErrorCode::GameNotFinished);
+
+    test.play_moves(game, &player_one, &player_two, &PLAYER_ONE_WINS).await;
+    claim_side_bet(&mut test, game, &backers[0]).await.unwrap();
+
+    // Every bet left must be paid out for the pool to close
+    assert_error(close_side_pool(&mut test, game, &[backers[1].pubkey()]).await, ErrorCode::SideBetsOutstanding);
+    let balances_before = [test.balance(backers[1].pubkey()).await, test.balance(doubter.pubkey()).await];
+    close_side_pool(&mut test, game, &[backers[1].pubkey(), doubter.pubkey()]).await.unwrap();
+
+    let total = BET * 3;
+    let prize = total - total * test.global_state().await.fee / 100;
+    assert_eq!(test.balance(backers[1].pubkey()).await, balances_before[0] + prize / 2 + side_bet_rent);
+    assert_eq!(test.balance(doubter.pubkey()).await, balances_before[1] + side_bet_rent);
+    for account in [side_pool, side_bet_address(side_pool, backers[1].pubkey()), side_bet_address(side_pool, doubter.pubkey())] {
+        assert!(test.context.banks_client.get_account(account).await.unwrap().is_none());
+    }
+}
+
+#[tokio::test]
+async fn closed_pool_leaves_no_bet_to_claim() {
+    let mut test = start().await;
+    let (game, player_one, player_two) = test.start_game().await;
+    let side_pool = open_side_pool(&mut test, game).await.unwrap();
+    let bettor = test.create_funded_player().await;
+    place_side_bet(&mut test, game, &bettor, SideBetOutcome::Tie, BET).await.unwrap();
+    test.cancel_game(game, &player_one, player_two.pubkey()).await.unwrap();
+
+    let balance_before = test.balance(bettor.pubkey()).await;
+    close_side_pool(&mut test, game, &[bettor.pubkey()]).await.unwrap();
+
+    // Nobody backed the winner, so the bet is refunded
+    assert!(test.balance(bettor.pubkey()).await > balance_before + BET);
+    assert!(test.context.banks_client.get_account(side_pool).await.unwrap().is_none());
+    assert!(claim_side_bet(&mut test, game, &bettor).await.is_err());
+}
@@ -97,7 +97,7 @@ describe("tic-tac-toe", () => {
 
     // Call the game setup method
     await program.methods
-      .setupGame()
+      .setupGame(null)
       .accounts({
         globalState: globalStateAddress, // Ensure to pass the global account
         player: player1.publicKey,
@@ -147,7 +147,7 @@ describe("tic-tac-toe", () => {
     try {
       // Call the game setup method
       await program.methods
-        .setupGame()
+        .setupGame(null)
         .accounts({
           globalState: globalStateAddress, // Ensure to pass the global account
           player: player1.publicKey,
@@ -178,7 +178,7 @@ describe("tic-tac-toe", () => {
 
     // Call the game setup method
     await program.methods
-      .setupGame()
+      .setupGame(null)
       .accounts({
         globalState: globalStateAddress, // Ensure to pass the global account
         player: player2.publicKey.toString(),
@@ -220,7 +220,7 @@ describe("tic-tac-toe", () => {
 
     // Call the game setup method
     await program.methods
-      .setupGame()
+      .setupGame(null)
       .accounts({
         globalState: globalStateAddress, // Ensure to pass the global account
         player: player3.publicKey.toString(),
@@ -263,7 +263,7 @@ describe("tic-tac-toe", () => {
 
     // Call the game setup method
     await program.methods
-      .setupGame()
+      .setupGame(null)
       .accounts({
         globalState: globalStateAddress, // Ensure to pass the global account
         player: player4.publicKey.toString(),
@@ -297,7 +297,7 @@ describe("tic-tac-toe", () => {
     try {
       // Call the game setup method
       await program.methods
-        .setupGame()
+        .setupGame(null)
         .accounts({
           globalState: globalStateAddress, // Ensure to pass the global account
           player: player4.publicKey.toString(),
@@ -335,7 +335,7 @@ describe("tic-tac-toe", () => {
 
     // Call the game setup method
     await program.methods
-      .setupGame()
+      .setupGame(null)
       .accounts({
         globalState: globalStateAddress, // Ensure to pass the global account
         player: player5.publicKey.toString(),
@@ -382,7 +382,7 @@ describe("tic-tac-toe", () => {
 
     // Call the game setup method
     await program.methods
-      .setupGame()
+      .setupGame(null)
       .accounts({
         globalState: globalStateAddress, // Ensure to pass the global account
         player: player6.publicKey.toString(),
@@ -741,6 +741,9 @@ describe("tic-tac-toe", () => {
     ]);
 
     // Final move for a draw
+    let finishedEvent;
+    const listener = program.addEventListener("gameFinished", (event) => (finishedEvent = event));
+
     await play(
       program,
       player5,
@@ -770,6 +773,36 @@ describe("tic-tac-toe", () => {
 
     expect(player5BalanceAfter).to.eql(player5BalanceBefore + prize / 2);
     expect(player6BalanceAfter).to.eql(player6BalanceBefore + prize / 2);
+
+    // Every move is kept in order in the game history
+    expect(gamePDAAfter.moves.length).to.eql(9);
+    expect(gamePDAAfter.moves.map((move) => move.tile)).to.eql([
+      { row: 0, column: 0 },
+      { row: 0, column: 1 },
+      { row: 0, column: 2 },
+      { row: 1, column: 1 },
+      { row: 1, column: 0 },
+      { row: 1, column: 2 },
+      { row: 2, column: 1 },
+      { row: 2, column: 0 },
+      { row: 2, column: 2 },
+    ]);
+    expect(gamePDAAfter.moves.map((move) => move.player)).to.eql([0, 1, 0, 1, 0, 1, 0, 1, 0]);
+
+    // Settlement details are included in the GameFinished event
+    await new Promise((resolve) => setTimeout(resolve, 2000));
+    await program.removeEventListener(listener);
+
+    expect(finishedEvent.game.toString()).to.eql(gameAddress);
+    expect(finishedEvent.reason).to.eql({ tie: {} });
+    expect(finishedEvent.winner).to.eql(null);
+    expect(finishedEvent.turns).to.eql(9);
+    expect(finishedEvent.board).to.eql(gamePDAAfter.board);
+    expect(finishedEvent.pot.toNumber()).to.eql(gameBet * 2);
+    expect(finishedEvent.fee.toNumber()).to.eql((gameBet * 2 * gameFeeP) / 100);
+    expect(finishedEvent.playerOnePayout.toNumber()).to.eql(prize / 2);
+    expect(finishedEvent.playerTwoPayout.toNumber()).to.eql(prize / 2);
+    expect(finishedEvent.timestamp.toNumber()).to.be.greaterThan(0);
   });
 
   it("player 4 has not an active game", async () => {
@@ -827,7 +860,7 @@ describe("tic-tac-toe", () => {
 
     // Call the game setup method
     await program.methods
-      .setupGame()
+      .setupGame(null)
       .accounts({
         globalState: globalStateAddress, // Ensure to pass the global account
         player: player4.publicKey.toString(),
@@ -894,17 +927,17 @@ describe("tic-tac-toe", () => {
     console.log("Game PDA:", gamePDA);
 
     // Call the game setup method
-    await program.methods
-      .cancelGame()
-      .accounts({
+    await sendSignedBy(
+      program,
+      program.methods.cancelGame().accounts({
         globalState: globalStateAddress,
         game: gameAddress, // Ensure to pass the global account
         signer: player4.publicKey.toString(),
         rival: player3.publicKey.toString(),
         systemProgram: anchor.web3.SystemProgram.programId,
-      })
-      .signers([player4]) // PlayerTwo is the signer now
-      .rpc();
+      }),
+      player4 // PlayerTwo is the signer now
+    );
 
     // Account doesn´t exist later
     try {
@@ -916,6 +949,328 @@ describe("tic-tac-toe", () => {
     }
   });
 
+  it("league between players 1 & 2", async () => {
+    console.log("");
+    console.log("----------------------------------------");
+    console.log(">>> league between players 1 & 2");
+    console.log("----------------------------------------");
+
+    const leagueId = new anchor.BN(1);
+    const entryFee = new anchor.BN(anchor.web3.LAMPORTS_PER_SOL / 10);
+    const [leagueAddress] = await anchor.web3.PublicKey.findProgramAddress(
+      [Buffer.from("league"), owner.publicKey.toBuffer(), Buffer.from(leagueId.toArray("le", 8))],
+      program.programId
+    );
+
+    await program.methods
+      .createLeague(leagueId, entryFee, Buffer.from([100]))
+      .accounts({
+        league: leagueAddress,
+        authority: owner.publicKey,
+        systemProgram: anchor.web3.SystemProgram.programId,
+      })
+      .rpc();
+
+    for (const player of [player1, player2]) {
+      await program.methods
+        .joinLeague()
+        .accounts({
+          league: leagueAddress,
+          player: player.publicKey,
+          systemProgram: anchor.web3.SystemProgram.programId,
+        })
+        .signers([player])
+        .rpc();
+    }
+
+    await program.methods
+      .startLeague()
+      .accounts({
+        league: leagueAddress,
+        authority: owner.publicKey,
+      })
+      .rpc();
+
+    let league = await program.account.league.fetch(leagueAddress);
+    console.log("League:", league);
+
+    expect(league.state).to.eql({ inProgress: {} });
+    expect(league.pool.toNumber()).to.eql(entryFee.toNumber() * 2);
+    expect(league.fixtures.length).to.eql(1);
+
+    const [gameAddress] = await anchor.web3.PublicKey.findProgramAddress(
+      [Buffer.from("game"), leagueAddress.toBuffer(), Buffer.from([0, 0])],
+      program.programId
+    );
+
+    for (const player of [player1, player2]) {
+      await program.methods
+        .setupLeagueGame(0)
+        .accounts({
+          globalState: globalStateAddress,
+          league: leagueAddress,
+          player: player.publicKey,
+          systemProgram: anchor.web3.SystemProgram.programId,
+        })
+        .signers([player])
+        .rpc();
+    }
+
+    let gamePDA = await program.account.game.fetch(gameAddress);
+    expect(gamePDA.state).to.eql({ inProgress: {} });
+    expect(gamePDA.league).to.eql(leagueAddress);
+
+    // Player 1 wins the only fixture
+    await play(program, player1, player2, globalStateAddress, gameAddress, { row: 0, column: 0 }, 1, { inProgress: {} }, [
+      [{ x: {} }, null, null],
+      [null, null, null],
+      [null, null, null],
+    ]);
+    await play(program, player2, player1, globalStateAddress, gameAddress, { row: 1, column: 0 }, 2, { inProgress: {} }, [
+      [{ x: {} }, null, null],
+      [{ o: {} }, null, null],
+      [null, null, null],
+    ]);
+    await play(program, player1, player2, globalStateAddress, gameAddress, { row: 0, column: 1 }, 3, { inProgress: {} }, [
+      [{ x: {} }, { x: {} }, null],
+      [{ o: {} }, null, null],
+      [null, null, null],
+    ]);
+    await play(program, player2, player1, globalStateAddress, gameAddress, { row: 1, column: 1 }, 4, { inProgress: {} }, [
+      [{ x: {} }, { x: {} }, null],
+      [{ o: {} }, { o: {} }, null],
+      [null, null, null],
+    ]);
+    await play(program, player1, player2, globalStateAddress, gameAddress, { row: 0, column: 2 }, 5, { won: { winner: player1.publicKey } }, [
+      [{ x: {} }, { x: {} }, { x: {} }],
+      [{ o: {} }, { o: {} }, null],
+      [null, null, null],
+    ]);
+
+    await program.methods
+      .recordLeagueResult(0)
+      .accounts({
+        league: leagueAddress,
+        game: gameAddress,
+      })
+      .rpc();
+
+    league = await program.account.league.fetch(leagueAddress);
+    expect(league.standings[0].points).to.eql(3);
+    expect(league.standings[0].wins).to.eql(1);
+    expect(league.standings[1].losses).to.eql(1);
+
+    const player1BalanceBefore = await provider.connection.getBalance(player1.publicKey);
+
+    await program.methods
+      .finalizeLeague()
+      .accounts({
+        league: leagueAddress,
+      })
+      .remainingAccounts([{ pubkey: player1.publicKey, isWritable: true, isSigner: false }])
+      .rpc();
+
+    const player1BalanceAfter = await provider.connection.getBalance(player1.publicKey);
+
+    league = await program.account.league.fetch(leagueAddress);
+    expect(league.state).to.eql({ finished: {} });
+    expect(league.pool.toNumber()).to.eql(0);
+    expect(player1BalanceAfter).to.eql(player1BalanceBefore + entryFee.toNumber() * 2);
+  });
+
+  it("spectators 5 & 6 side bet on players 1 & 2", async () => {
+    console.log("");
+    console.log("----------------------------------------");
+    console.log(">>> spectators 5 & 6 side bet on players 1 & 2");
+    console.log("----------------------------------------");
+
+    let globalState = await program.account.globalState.fetch(globalStateAddress);
+    const [gameAddress] = await anchor.web3.PublicKey.findProgramAddress(
+      [Buffer.from("game"), Buffer.from(globalState.gameCount.toArray("le", 8))],
+      program.programId
+    );
+
+    for (const player of [player1, player2]) {
+      await program.methods
+        .setupGame(null)
+        .accounts({
+          globalState: globalStateAddress,
+          player: player.publicKey,
+          systemProgram: anchor.web3.SystemProgram.programId,
+        })
+        .signers([player])
+        .rpc();
+    }
+
+    const [sidePoolAddress] = await anchor.web3.PublicKey.findProgramAddress([Buffer.from("side_pool"), gameAddress.toBuffer()], program.programId);
+
+    await program.methods
+      .openSidePool(3)
+      .accounts({
+        sidePool: sidePoolAddress,
+        game: gameAddress,
+        payer: player5.publicKey,
+        systemProgram: anchor.web3.SystemProgram.programId,
+      })
+      .signers([player5])
+      .rpc();
+
+    // Player 5 backs player 1 and player 6 backs player 2
+    const betAmount = anchor.web3.LAMPORTS_PER_SOL / 10;
+    const sideBets = [
+      [player5, { playerOne: {} }],
+      [player6, { playerTwo: {} }],
+    ];
+    for (const [bettor, outcome] of sideBets) {
+      await program.methods
+        .placeSideBet(outcome, new anchor.BN(betAmount))
+        .accounts({
+          sidePool: sidePoolAddress,
+          game: gameAddress,
+          bettor: bettor.publicKey,
+          systemProgram: anchor.web3.SystemProgram.programId,
+        })
+        .signers([bettor])
+        .rpc();
+    }
+
+    try {
+      await program.methods
+        .placeSideBet({ tie: {} }, new anchor.BN(betAmount))
+        .accounts({
+          sidePool: sidePoolAddress,
+          game: gameAddress,
+          bettor: player1.publicKey,
+          systemProgram: anchor.web3.SystemProgram.programId,
+        })
+        .signers([player1])
+        .rpc();
+      expect.fail("A player must not be able to side bet on his own game");
+    } catch (error) {
+      expect(error.message).to.contain("SignerIsPlayer");
+    }
+
+    let sidePool = await program.account.sideBetPool.fetch(sidePoolAddress);
+    expect(sidePool.totals.map((total) => total.toNumber())).to.eql([betAmount, betAmount, 0]);
+
+    // Player 1 wins
+    await play(program, player1, player2, globalStateAddress, gameAddress, { row: 0, column: 0 }, 1, { inProgress: {} }, [
+      [{ x: {} }, null, null],
+      [null, null, null],
+      [null, null, null],
+    ]);
+    await play(program, player2, player1, globalStateAddress, gameAddress, { row: 1, column: 0 }, 2, { inProgress: {} }, [
+      [{ x: {} }, null, null],
+      [{ o: {} }, null, null],
+      [null, null, null],
+    ]);
+    await play(program, player1, player2, globalStateAddress, gameAddress, { row: 0, column: 1 }, 3, { inProgress: {} }, [
+      [{ x: {} }, { x: {} }, null],
+      [{ o: {} }, null, null],
+      [null, null, null],
+    ]);
+    await play(program, player2, player1, globalStateAddress, gameAddress, { row: 1, column: 1 }, 4, { inProgress: {} }, [
+      [{ x: {} }, { x: {} }, null],
+      [{ o: {} }, { o: {} }, null],
+      [null, null, null],
+    ]);
+    await play(program, player1, player2, globalStateAddress, gameAddress, { row: 0, column: 2 }, 5, { won: { winner: player1.publicKey } }, [
+      [{ x: {} }, { x: {} }, { x: {} }],
+      [{ o: {} }, { o: {} }, null],
+      [null, null, null],
+    ]);
+
+    const player5BalanceBefore = await provider.connection.getBalance(player5.publicKey);
+
+    for (const [bettor] of sideBets) {
+      const [sideBetAddress] = await anchor.web3.PublicKey.findProgramAddress(
+        [Buffer.from("side_bet"), sidePoolAddress.toBuffer(), bettor.publicKey.toBuffer()],
+        program.programId
+      );
+      await program.methods
+        .claimSideBet()
+        .accounts({
+          globalState: globalStateAddress,
+          sidePool: sidePoolAddress,
+          game: gameAddress,
+          sideBet: sideBetAddress,
+          bettor: bettor.publicKey,
+        })
+        .signers([bettor])
+        .rpc();
+    }
+
+    const player5BalanceAfter = await provider.connection.getBalance(player5.publicKey);
+    const sideBetRent = await provider.connection.getMinimumBalanceForRentExemption(8 + 32 + 32 + 1 + 8);
+    const sidePrize = betAmount * 2 - (betAmount * 2 * gameFeeP) / 100;
+
+    sidePool = await program.account.sideBetPool.fetch(sidePoolAddress);
+    expect(sidePool.outcome).to.eql({ playerOne: {} });
+    expect(player5BalanceAfter).to.eql(player5BalanceBefore + sidePrize + sideBetRent);
+  });
+
+  it("player 3 events on a canceled game", async () => {
+    console.log("");
+    console.log("----------------------------------------");
+    console.log(">>> player 3 events on a canceled game");
+    console.log("----------------------------------------");
+
+    const events = [];
+    const listeners = [];
+    for (const name of ["gameCreated", "gameCanceled", "gameClosed"]) {
+      listeners.push(program.addEventListener(name, (event) => events.push([name, event])));
+    }
+
+    const globalState = await program.account.globalState.fetch(globalStateAddress);
+    const [gameAddress] = await anchor.web3.PublicKey.findProgramAddress(
+      [Buffer.from("game"), Buffer.from(globalState.gameCount.toArray("le", 8))],
+      program.programId
+    );
+
+    await program.methods
+      .setupGame(null)
+      .accounts({
+        globalState: globalStateAddress,
+        player: player3.publicKey,
+        systemProgram: anchor.web3.SystemProgram.programId,
+      })
+      .signers([player3])
+      .rpc();
+
+    await sendSignedBy(
+      program,
+      program.methods.cancelGame().accounts({
+        globalState: globalStateAddress,
+        game: gameAddress,
+        signer: player3.publicKey,
+        rival: player4.publicKey,
+      }),
+      player3
+    );
+
+    await program.methods
+      .closeGameAccount()
+      .accounts({
+        game: gameAddress,
+        signer: player3.publicKey,
+      })
+      .signers([player3])
+      .rpc();
+
+    // Give the listeners some time to receive the logs
+    await new Promise((resolve) => setTimeout(resolve, 2000));
+    for (const listener of listeners) {
+      await program.removeEventListener(listener);
+    }
+
+    expect(events.map(([name]) => name)).to.eql(["gameCreated", "gameCanceled", "gameClosed"]);
+    expect(events[0][1].game).to.eql(gameAddress);
+    expect(events[0][1].playerOne).to.eql(player3.publicKey);
+    expect(events[1][1].canceledBy).to.eql(player3.publicKey);
+    expect(events[1][1].winner).to.eql(null);
+    expect(events[2][1].closedBy).to.eql(player3.publicKey);
+  });
+
   it("game state", async () => {
     console.log("");
     console.log("----------------------------------------");
@@ -930,17 +1285,17 @@ describe("tic-tac-toe", () => {
 // Helper function to simulate a move
 async function play(program: Program<TicTacToe>, player, rival, global_state, game, tile, expectedTurn, expectedGameState, expectedBoard) {
   // Make the move
-  await program.methods
-    .play(tile)
-    .accounts({
+  await sendSignedBy(
+    program,
+    program.methods.play(tile).accounts({
       globalState: global_state,
       game: game,
       player: player.publicKey,
       rival: rival.publicKey,
       systemProgram: anchor.web3.SystemProgram.programId,
-    })
-    .signers(player instanceof (anchor.Wallet as any) ? [] : [player])
-    .rpc();
+    }),
+    player
+  );
 
   // Verify game state after the move
   const gameState = await program.account.game.fetch(game);
@@ -964,6 +1319,15 @@ async function play(program: Program<TicTacToe>, player, rival, global_state, ga
   expect(gameState.board).to.eql(expectedBoard);
 }
 
+// `play` and `cancelGame` take the player as a plain account, as a session key can sign for them
+// instead, so the player's signature is added to the instruction here.
+async function sendSignedBy(program: Program<TicTacToe>, method, signer) {
+  const instruction = await method.instruction();
+  instruction.keys.filter((key) => key.pubkey.equals(signer.publicKey)).forEach((key) => (key.isSigner = true));
+  const transaction = new anchor.web3.Transaction().add(instruction);
+  return program.provider.sendAndConfirm(transaction, signer instanceof (anchor.Wallet as any) ? [] : [signer]);
+}
+
 function getGamePDAFromPlayerPubKey(globalStatePDA, playerPubKey) {
   // Find the index of the target public key
   const gameIndex = globalStatePDA.playersMapping.map((pubKey) => pubKey.toString()).indexOf(playerPubKey);